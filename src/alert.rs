@@ -0,0 +1,885 @@
+use crate::metric::Metrics;
+use crate::realtime::RtResponse;
+use crate::timeseries::{Scope, ScopeFilter, Scoped, TimeSeriesStore};
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Deserialize;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+/// Arithmetic expression over the metrics of a stats, e.g. `status_5xx / requests`
+///
+/// Operands are numbers and metric names of the catalog, see [`Metrics`], combined with `+`, `-`, `*`, `/`
+/// and parentheses. An expression dividing by zero evaluates to a non finite value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expression {
+    source: String,
+    node: Node,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Number(f64),
+    Metric(String),
+    Negate(Box<Node>),
+    Binary(Box<Node>, char, Box<Node>),
+}
+
+impl Expression {
+    pub fn parse(text: &str) -> Result<Expression> {
+        Expression::parse_with(text, &HashMap::new())
+    }
+
+    /// Parse `text`, names of `kpis` standing for their expression
+    pub fn parse_with(text: &str, kpis: &HashMap<String, Expression>) -> Result<Expression> {
+        let mut parser = Parser::new(text, kpis)?;
+        let node = parser.expression()?;
+        parser.end()?;
+
+        Ok(Expression {
+            source: text.trim().to_string(),
+            node,
+        })
+    }
+
+    /// Value of the expression for `stats`, metrics unknown to `S` being NaN
+    pub fn eval<S: Metrics>(&self, stats: &S) -> f64 {
+        eval(&self.node, stats)
+    }
+
+    /// Names of the metrics used
+    pub fn metrics(&self) -> BTreeSet<&str> {
+        let mut names = BTreeSet::new();
+        collect_metrics(&self.node, &mut names);
+        names
+    }
+
+    /// Fail when a metric used is not in the catalog of `S`
+    pub fn validate<S: Metrics>(&self) -> Result<()> {
+        for name in self.metrics() {
+            if S::metric_info(name).is_none() {
+                bail!("unknown metric {} in {}", name, self.source);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+fn eval<S: Metrics>(node: &Node, stats: &S) -> f64 {
+    match node {
+        Node::Number(value) => *value,
+        Node::Metric(name) => stats.metric(name).unwrap_or(f64::NAN),
+        Node::Negate(node) => -eval(node, stats),
+        Node::Binary(left, op, right) => {
+            let (left, right) = (eval(left, stats), eval(right, stats));
+            match op {
+                '+' => left + right,
+                '-' => left - right,
+                '*' => left * right,
+                _ => left / right,
+            }
+        }
+    }
+}
+
+fn collect_metrics<'a>(node: &'a Node, names: &mut BTreeSet<&'a str>) {
+    match node {
+        Node::Number(_) => {}
+        Node::Metric(name) => {
+            names.insert(name);
+        }
+        Node::Negate(node) => collect_metrics(node, names),
+        Node::Binary(left, _, right) => {
+            collect_metrics(left, names);
+            collect_metrics(right, names);
+        }
+    }
+}
+
+/// Comparison of a value with a threshold
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Greater,
+    GreaterOrEqual,
+    Less,
+    LessOrEqual,
+    Equal,
+    NotEqual,
+}
+
+impl Comparison {
+    /// Whether `value` compares to `threshold`, never for NaN
+    pub fn holds(&self, value: f64, threshold: f64) -> bool {
+        match self {
+            Comparison::Greater => value > threshold,
+            Comparison::GreaterOrEqual => value >= threshold,
+            Comparison::Less => value < threshold,
+            Comparison::LessOrEqual => value <= threshold,
+            Comparison::Equal => value == threshold,
+            Comparison::NotEqual => !value.is_nan() && value != threshold,
+        }
+    }
+
+    fn symbol(&self) -> &'static str {
+        match self {
+            Comparison::Greater => ">",
+            Comparison::GreaterOrEqual => ">=",
+            Comparison::Less => "<",
+            Comparison::LessOrEqual => "<=",
+            Comparison::Equal => "==",
+            Comparison::NotEqual => "!=",
+        }
+    }
+}
+
+/// `{expression} {comparison} {threshold}`, optionally followed by `for {duration}`,
+/// e.g. `status_5xx / requests > 0.02 for 30s`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition {
+    pub expression: Expression,
+
+    pub comparison: Comparison,
+
+    pub threshold: f64,
+
+    /// Number of consecutive seconds the comparison must hold before firing
+    pub for_secs: u64,
+}
+
+impl Condition {
+    pub fn parse(text: &str) -> Result<Condition> {
+        Condition::parse_with(text, &HashMap::new())
+    }
+
+    /// Parse `text`, names of `kpis` standing for their expression
+    pub fn parse_with(text: &str, kpis: &HashMap<String, Expression>) -> Result<Condition> {
+        let mut parser = Parser::new(text, kpis)?;
+        let start = parser.offset();
+        let node = parser.expression()?;
+        let source = text[start..parser.offset()].trim().to_string();
+
+        let comparison = match parser.next() {
+            Some(Token::Comparison(comparison)) => comparison,
+            _ => bail!("expected a comparison in {}", text),
+        };
+        let threshold = parser.signed_number()?;
+
+        let mut for_secs = 0;
+        if parser.peek() == Some(&Token::Name("for".to_string())) {
+            parser.next();
+            for_secs = parser.duration()?;
+        }
+        parser.end()?;
+
+        Ok(Condition {
+            expression: Expression { source, node },
+            comparison,
+            threshold,
+            for_secs,
+        })
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {}",
+            self.expression,
+            self.comparison.symbol(),
+            self.threshold
+        )?;
+        if self.for_secs > 0 {
+            write!(f, " for {}s", self.for_secs)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse a duration such as `30s`, `5m` or `1h`, a bare number being seconds
+pub fn parse_duration(text: &str) -> Result<u64> {
+    let kpis = HashMap::new();
+    let mut parser = Parser::new(text, &kpis)?;
+    let secs = parser.duration()?;
+    parser.end()?;
+    Ok(secs)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Name(String),
+    Operator(char),
+    Comparison(Comparison),
+}
+
+/// Recursive descent parser of conditions and expressions
+struct Parser<'a> {
+    tokens: Vec<(usize, Token)>,
+    position: usize,
+    length: usize,
+    kpis: &'a HashMap<String, Expression>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(text: &str, kpis: &'a HashMap<String, Expression>) -> Result<Parser<'a>> {
+        let mut tokens = Vec::new();
+        let chars: Vec<(usize, char)> = text.char_indices().collect();
+        let mut index = 0;
+
+        while index < chars.len() {
+            let (offset, c) = chars[index];
+            if c.is_whitespace() {
+                index += 1;
+            } else if c.is_ascii_digit() || c == '.' {
+                let start = index;
+                while index < chars.len()
+                    && (chars[index].1.is_ascii_digit() || chars[index].1 == '.')
+                {
+                    index += 1;
+                }
+                let end = chars.get(index).map_or(text.len(), |(end, _)| *end);
+                let number = text[offset..end]
+                    .parse()
+                    .map_err(|_| anyhow!("invalid number {} in {}", &text[offset..end], text))?;
+                tokens.push((chars[start].0, Token::Number(number)));
+            } else if c.is_ascii_alphabetic() || c == '_' {
+                while index < chars.len()
+                    && (chars[index].1.is_ascii_alphanumeric() || chars[index].1 == '_')
+                {
+                    index += 1;
+                }
+                let end = chars.get(index).map_or(text.len(), |(end, _)| *end);
+                tokens.push((offset, Token::Name(text[offset..end].to_string())));
+            } else if "+-*/()".contains(c) {
+                tokens.push((offset, Token::Operator(c)));
+                index += 1;
+            } else if "<>=!".contains(c) {
+                let equal = chars.get(index + 1).map(|(_, c)| *c) == Some('=');
+                let comparison = match (c, equal) {
+                    ('>', false) => Comparison::Greater,
+                    ('>', true) => Comparison::GreaterOrEqual,
+                    ('<', false) => Comparison::Less,
+                    ('<', true) => Comparison::LessOrEqual,
+                    ('=', true) => Comparison::Equal,
+                    ('!', true) => Comparison::NotEqual,
+                    _ => bail!("unexpected {} in {}", c, text),
+                };
+                tokens.push((offset, Token::Comparison(comparison)));
+                index += if equal { 2 } else { 1 };
+            } else {
+                bail!("unexpected {} in {}", c, text);
+            }
+        }
+
+        Ok(Parser {
+            tokens,
+            position: 0,
+            length: text.len(),
+            kpis,
+        })
+    }
+
+    /// Offset in the text of the next token
+    fn offset(&self) -> usize {
+        self.tokens
+            .get(self.position)
+            .map_or(self.length, |(offset, _)| *offset)
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(_, token)| token)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek().cloned();
+        self.position += 1;
+        token
+    }
+
+    fn end(&self) -> Result<()> {
+        match self.peek() {
+            None => Ok(()),
+            Some(token) => bail!("unexpected {:?}", token),
+        }
+    }
+
+    fn expression(&mut self) -> Result<Node> {
+        let mut node = self.term()?;
+        while let Some(Token::Operator(op @ ('+' | '-'))) = self.peek().cloned() {
+            self.next();
+            node = Node::Binary(Box::new(node), op, Box::new(self.term()?));
+        }
+        Ok(node)
+    }
+
+    fn term(&mut self) -> Result<Node> {
+        let mut node = self.unary()?;
+        while let Some(Token::Operator(op @ ('*' | '/'))) = self.peek().cloned() {
+            self.next();
+            node = Node::Binary(Box::new(node), op, Box::new(self.unary()?));
+        }
+        Ok(node)
+    }
+
+    fn unary(&mut self) -> Result<Node> {
+        if self.peek() == Some(&Token::Operator('-')) {
+            self.next();
+            return Ok(Node::Negate(Box::new(self.unary()?)));
+        }
+
+        match self.next() {
+            Some(Token::Number(number)) => Ok(Node::Number(number)),
+            Some(Token::Name(name)) => Ok(match self.kpis.get(&name) {
+                Some(kpi) => kpi.node.clone(),
+                None => Node::Metric(name),
+            }),
+            Some(Token::Operator('(')) => {
+                let node = self.expression()?;
+                match self.next() {
+                    Some(Token::Operator(')')) => Ok(node),
+                    _ => bail!("expected )"),
+                }
+            }
+            Some(token) => bail!("unexpected {:?}", token),
+            None => bail!("unexpected end"),
+        }
+    }
+
+    fn signed_number(&mut self) -> Result<f64> {
+        let sign = if self.peek() == Some(&Token::Operator('-')) {
+            self.next();
+            -1.0
+        } else {
+            1.0
+        };
+        match self.next() {
+            Some(Token::Number(number)) => Ok(sign * number),
+            _ => bail!("expected a number"),
+        }
+    }
+
+    fn duration(&mut self) -> Result<u64> {
+        let value = match self.next() {
+            Some(Token::Number(number)) if number >= 0.0 => number,
+            _ => bail!("expected a duration"),
+        };
+        let unit = match self.peek() {
+            Some(Token::Name(unit)) => {
+                let unit = match unit.as_str() {
+                    "s" => 1.0,
+                    "m" => 60.0,
+                    "h" => 3600.0,
+                    _ => bail!("unknown unit of duration {}", unit),
+                };
+                self.next();
+                unit
+            }
+            _ => 1.0,
+        };
+        Ok((value * unit).round() as u64)
+    }
+}
+
+/// An alert rule, evaluated on each second of each scope it applies to
+///
+/// ```
+/// use fastly_rt::alert::Rule;
+///
+/// let rule = Rule::new("high_5xx", "status_5xx / requests > 0.02 for 30s")
+///     .unwrap()
+///     .by_pop(true)
+///     .over(10)
+///     .clear(0.01);
+/// assert_eq!(rule.condition.for_secs, 30);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub name: String,
+
+    pub condition: Condition,
+
+    /// Services, origins and POPs the rule applies to, all services aggregated across POPs by default
+    pub filter: ScopeFilter,
+
+    /// Evaluate the expression over the sum of the last seconds, 1 for each second on its own
+    pub over_secs: u64,
+
+    /// Threshold the value must cross back for a firing alert to resolve, the threshold of the condition when `None`
+    pub clear: Option<f64>,
+
+    /// Number of consecutive cleared seconds before a firing alert resolves
+    pub resolve_secs: u64,
+}
+
+impl Rule {
+    /// Rule named `name` firing on `condition`, see [`Condition`]
+    pub fn new(name: &str, condition: &str) -> Result<Rule> {
+        Ok(Rule::with_condition(name, Condition::parse(condition)?))
+    }
+
+    pub fn with_condition(name: &str, condition: Condition) -> Rule {
+        Rule {
+            name: name.to_string(),
+            condition,
+            filter: ScopeFilter::default(),
+            over_secs: 1,
+            clear: None,
+            resolve_secs: 0,
+        }
+    }
+
+    /// Apply the rule to services matching `pattern` only, may be called several times
+    pub fn service(mut self, pattern: &str) -> Rule {
+        self.filter.services.push(pattern.to_string());
+        self
+    }
+
+    /// Apply the rule to origins matching `pattern` only, may be called several times
+    pub fn origin(mut self, pattern: &str) -> Rule {
+        self.filter.origins.push(pattern.to_string());
+        self
+    }
+
+    pub fn by_pop(mut self, by_pop: bool) -> Rule {
+        self.filter.by_pop = by_pop;
+        self
+    }
+
+    pub fn over(mut self, secs: u64) -> Rule {
+        self.over_secs = secs.max(1);
+        self
+    }
+
+    pub fn clear(mut self, threshold: f64) -> Rule {
+        self.clear = Some(threshold);
+        self
+    }
+
+    pub fn resolve_after(mut self, secs: u64) -> Rule {
+        self.resolve_secs = secs;
+        self
+    }
+}
+
+/// Rules read from TOML, KPIs being named expressions usable in conditions
+///
+/// ```toml
+/// [kpis]
+/// error_ratio = "status_5xx / requests"
+///
+/// [[rule]]
+/// name = "high_5xx"
+/// condition = "error_ratio > 0.02 for 30s"
+/// by_pop = true
+/// over = "10s"
+/// clear = 0.01
+/// resolve_after = "1m"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AlertConfig {
+    #[serde(default)]
+    pub kpis: HashMap<String, String>,
+
+    #[serde(default, rename = "rule")]
+    pub rules: Vec<RuleConfig>,
+}
+
+/// A rule of an [`AlertConfig`], see [`Rule`]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuleConfig {
+    pub name: String,
+
+    pub condition: String,
+
+    #[serde(default)]
+    pub services: Vec<String>,
+
+    #[serde(default)]
+    pub origins: Vec<String>,
+
+    #[serde(default)]
+    pub by_pop: bool,
+
+    /// Duration such as `30s` or `5m`
+    #[serde(default)]
+    pub over: Option<String>,
+
+    #[serde(default)]
+    pub clear: Option<f64>,
+
+    /// Duration such as `30s` or `5m`
+    #[serde(default)]
+    pub resolve_after: Option<String>,
+}
+
+impl AlertConfig {
+    #[cfg(feature = "toml")]
+    pub fn from_toml(toml: &str) -> Result<AlertConfig> {
+        Ok(toml::from_str(toml)?)
+    }
+
+    #[cfg(feature = "toml")]
+    pub fn load<P: AsRef<std::path::Path>>(path: P) -> Result<AlertConfig> {
+        let path = path.as_ref();
+        let toml = std::fs::read_to_string(path).with_context(|| format!("{}", path.display()))?;
+        AlertConfig::from_toml(&toml).with_context(|| format!("{}", path.display()))
+    }
+
+    /// Parse the KPIs and conditions into rules
+    pub fn rules(&self) -> Result<Vec<Rule>> {
+        let mut kpis = HashMap::new();
+        for name in self.kpis.keys() {
+            self.kpi(name, &mut kpis, &mut Vec::new())?;
+        }
+
+        self.rules
+            .iter()
+            .map(|config| {
+                let context = || format!("rule {}", config.name);
+                let condition =
+                    Condition::parse_with(&config.condition, &kpis).with_context(context)?;
+                let mut rule = Rule::with_condition(&config.name, condition).by_pop(config.by_pop);
+                rule.filter.services = config.services.clone();
+                rule.filter.origins = config.origins.clone();
+                rule.clear = config.clear;
+                if let Some(over) = &config.over {
+                    rule = rule.over(parse_duration(over).with_context(context)?);
+                }
+                if let Some(resolve_after) = &config.resolve_after {
+                    rule = rule.resolve_after(parse_duration(resolve_after).with_context(context)?);
+                }
+                Ok(rule)
+            })
+            .collect()
+    }
+
+    /// Parse the KPI `name` into `kpis`, after the KPIs it uses, `parsing` being the KPIs being parsed
+    fn kpi(
+        &self,
+        name: &str,
+        kpis: &mut HashMap<String, Expression>,
+        parsing: &mut Vec<String>,
+    ) -> Result<()> {
+        if kpis.contains_key(name) {
+            return Ok(());
+        }
+        if parsing.iter().any(|parsed| parsed == name) {
+            bail!("kpi {} uses itself", name);
+        }
+
+        let text = &self.kpis[name];
+        parsing.push(name.to_string());
+        let used = Expression::parse(text).with_context(|| format!("kpi {}", name))?;
+        for used_name in used.metrics() {
+            if self.kpis.contains_key(used_name) {
+                self.kpi(used_name, kpis, parsing)?;
+            }
+        }
+        parsing.pop();
+
+        let kpi = Expression::parse_with(text, kpis).with_context(|| format!("kpi {}", name))?;
+        kpis.insert(name.to_string(), kpi);
+        Ok(())
+    }
+}
+
+/// State of an alert reported by an [`AlertEvent`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertState {
+    /// The condition holds, but not for long enough yet
+    Pending,
+
+    Firing,
+
+    /// The alert was firing and its value cleared
+    Resolved,
+}
+
+/// Transition of the alert of a rule in a scope of a service
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlertEvent {
+    pub rule: String,
+
+    pub service: String,
+
+    pub scope: Scope,
+
+    pub state: AlertState,
+
+    /// Value of the expression in the second of the transition
+    pub value: f64,
+
+    /// Second of the transition
+    pub recorded: u64,
+
+    /// First second the condition held, for pending and firing alerts, or was cleared, for resolved ones
+    pub since: u64,
+}
+
+impl fmt::Display for AlertEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?} {} of {} in {}, value {}",
+            self.state, self.rule, self.service, self.scope, self.value
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Phase {
+    Pending { since: u64 },
+    Firing { since: u64, cleared: Option<u64> },
+}
+
+type Callback = Box<dyn FnMut(&AlertEvent) + Send>;
+
+/// Callbacks and channels notified of alert events, of an [`AlertEngine`] or a [`crate::slo::SloTracker`]
+#[derive(Default)]
+pub(crate) struct Notifier {
+    callbacks: Vec<Callback>,
+    senders: Vec<UnboundedSender<AlertEvent>>,
+}
+
+impl Notifier {
+    pub(crate) fn on_event<F: FnMut(&AlertEvent) + Send + 'static>(&mut self, callback: F) {
+        self.callbacks.push(Box::new(callback));
+    }
+
+    pub(crate) fn subscribe(&mut self) -> UnboundedReceiver<AlertEvent> {
+        let (sender, receiver) = unbounded_channel();
+        self.senders.push(sender);
+        receiver
+    }
+
+    /// Call every callback and send to every subscriber still receiving, event by event
+    pub(crate) fn notify(&mut self, events: &[AlertEvent]) {
+        for event in events {
+            for callback in &mut self.callbacks {
+                callback(event);
+            }
+            self.senders
+                .retain(|sender| sender.send(event.clone()).is_ok());
+        }
+    }
+}
+
+/// Evaluates rules on each second of services, and notifies transitions of their alerts
+///
+/// Each rule has one alert per service and scope it applies to: aggregated or each POP for service data,
+/// each origin aggregated or in each POP for origin data. A scope missing from a second counts as
+/// zero, so ratios over it are NaN and never hold.
+///
+/// ```
+/// use fastly_rt::alert::{AlertEngine, AlertState, Rule};
+/// use fastly_rt::service::ServiceDataInSecond;
+///
+/// let rule = Rule::new("high_5xx", "status_5xx / requests > 0.02 for 2s").unwrap();
+/// let mut engine = AlertEngine::<ServiceDataInSecond>::new(vec![rule]).unwrap();
+///
+/// let mut states = Vec::new();
+/// for recorded in 1..=3 {
+///     let mut data = ServiceDataInSecond { recorded, ..Default::default() };
+///     data.aggregated.requests = 100;
+///     data.aggregated.status_5xx = 10;
+///     states.extend(engine.evaluate("www", &data).into_iter().map(|event| event.state));
+/// }
+///
+/// assert_eq!(states, vec![AlertState::Pending, AlertState::Firing]);
+/// ```
+pub struct AlertEngine<D: Scoped> {
+    rules: Vec<Rule>,
+    store: TimeSeriesStore<D>,
+    phases: HashMap<(usize, String, Scope), Phase>,
+    notifier: Notifier,
+}
+
+impl<D: Scoped> AlertEngine<D> {
+    /// Fails when a condition uses a metric unknown to the stats of `D`
+    pub fn new(rules: Vec<Rule>) -> Result<AlertEngine<D>> {
+        for rule in &rules {
+            rule.condition
+                .expression
+                .validate::<D::Stats>()
+                .with_context(|| format!("rule {}", rule.name))?;
+        }
+        let capacity = rules.iter().map(|rule| rule.over_secs).max().unwrap_or(1);
+
+        Ok(AlertEngine {
+            rules,
+            store: TimeSeriesStore::new(capacity as usize),
+            phases: HashMap::new(),
+            notifier: Notifier::default(),
+        })
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// Call `callback` on every event
+    pub fn on_event<F: FnMut(&AlertEvent) + Send + 'static>(
+        mut self,
+        callback: F,
+    ) -> AlertEngine<D> {
+        self.notifier.on_event(callback);
+        self
+    }
+
+    /// Receive every event from now on
+    pub fn subscribe(&mut self) -> UnboundedReceiver<AlertEvent> {
+        self.notifier.subscribe()
+    }
+
+    /// Evaluate every rule applying to `service` on a new second, returns the events, also notified
+    pub fn evaluate(&mut self, service: &str, data: &D) -> Vec<AlertEvent> {
+        self.store.insert(service, data);
+        let recorded = data.recorded();
+        let mut events = Vec::new();
+
+        for (index, rule) in self.rules.iter().enumerate() {
+            // Scopes with data, and those with an alert, which may have no data anymore
+            let mut scopes: BTreeSet<Scope> =
+                self.store.scopes(service).into_iter().cloned().collect();
+            scopes.extend(
+                self.phases
+                    .keys()
+                    .filter(|(rule, alert_service, _)| *rule == index && alert_service == service)
+                    .map(|(_, _, scope)| scope.clone()),
+            );
+
+            for scope in scopes {
+                if !rule.filter.applies(service, &scope) {
+                    continue;
+                }
+                let value = self
+                    .store
+                    .sum(service, &scope, rule.over_secs)
+                    .map_or(f64::NAN, |stats| rule.condition.expression.eval(&stats));
+
+                let key = (index, service.to_string(), scope);
+                let (phase, event) =
+                    transition(rule, self.phases.get(&key).copied(), value, recorded);
+                if let Some((state, since)) = event {
+                    events.push(AlertEvent {
+                        rule: rule.name.clone(),
+                        service: service.to_string(),
+                        scope: key.2.clone(),
+                        state,
+                        value,
+                        recorded,
+                        since,
+                    });
+                }
+                match phase {
+                    Some(phase) => self.phases.insert(key, phase),
+                    None => self.phases.remove(&key),
+                };
+            }
+        }
+
+        self.notifier.notify(&events);
+        events
+    }
+
+    /// Evaluate every second of `response`
+    pub fn evaluate_response(
+        &mut self,
+        service: &str,
+        response: &RtResponse<D>,
+    ) -> Vec<AlertEvent> {
+        response
+            .data
+            .iter()
+            .flat_map(|data| self.evaluate(service, data))
+            .collect()
+    }
+
+    /// Firing alerts, by rule name, service and scope
+    pub fn firing(&self) -> Vec<(&str, &str, &Scope)> {
+        let mut firing: Vec<(&str, &str, &Scope)> = self
+            .phases
+            .iter()
+            .filter(|(_, phase)| matches!(phase, Phase::Firing { .. }))
+            .map(|((index, service, scope), _)| {
+                (self.rules[*index].name.as_str(), service.as_str(), scope)
+            })
+            .collect();
+        firing.sort();
+        firing
+    }
+}
+
+/// Next phase of an alert, `None` when inactive, and the state to notify with its `since` if any
+fn transition(
+    rule: &Rule,
+    phase: Option<Phase>,
+    value: f64,
+    recorded: u64,
+) -> (Option<Phase>, Option<(AlertState, u64)>) {
+    let condition = &rule.condition;
+    let holds = condition.comparison.holds(value, condition.threshold);
+    // Held for `for_secs` seconds, counting both ends
+    let held = |since: u64| recorded + 1 >= since + condition.for_secs;
+
+    match phase {
+        None if holds && held(recorded) => (
+            Some(Phase::Firing {
+                since: recorded,
+                cleared: None,
+            }),
+            Some((AlertState::Firing, recorded)),
+        ),
+        None if holds => (
+            Some(Phase::Pending { since: recorded }),
+            Some((AlertState::Pending, recorded)),
+        ),
+        None => (None, None),
+        Some(Phase::Pending { since }) if holds && held(since) => (
+            Some(Phase::Firing {
+                since,
+                cleared: None,
+            }),
+            Some((AlertState::Firing, since)),
+        ),
+        Some(Phase::Pending { since }) if holds => (Some(Phase::Pending { since }), None),
+        Some(Phase::Pending { .. }) => (None, None),
+        Some(Phase::Firing { since, cleared }) => {
+            let still = match rule.clear {
+                Some(clear) => condition.comparison.holds(value, clear),
+                None => holds,
+            };
+            if still {
+                return (
+                    Some(Phase::Firing {
+                        since,
+                        cleared: None,
+                    }),
+                    None,
+                );
+            }
+
+            let cleared = cleared.unwrap_or(recorded);
+            if recorded + 1 >= cleared + rule.resolve_secs.max(1) {
+                (None, Some((AlertState::Resolved, cleared)))
+            } else {
+                (
+                    Some(Phase::Firing {
+                        since,
+                        cleared: Some(cleared),
+                    }),
+                    None,
+                )
+            }
+        }
+    }
+}
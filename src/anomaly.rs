@@ -0,0 +1,521 @@
+use crate::metric::Metrics;
+use crate::realtime::RtResponse;
+use crate::timeseries::{Scope, ScopeFilter, Scoped};
+use anyhow::{bail, Result};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+
+/// Value a detector expects, and the typical deviation from it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Baseline {
+    pub expected: f64,
+
+    /// Standard deviation, or its estimate
+    pub spread: f64,
+}
+
+impl Baseline {
+    /// Deviation of `value` in spreads, infinite when the spread is zero and the value is not as expected
+    pub fn score(&self, value: f64) -> f64 {
+        let deviation = value - self.expected;
+        if deviation == 0.0 {
+            0.0
+        } else {
+            deviation / self.spread
+        }
+    }
+}
+
+/// Exponentially weighted moving average and variance
+///
+/// Adapts to the level of each series, so the same detector fits services of any traffic.
+#[derive(Debug, Clone)]
+pub struct Ewma {
+    /// Weight of a new value, from 0 to 1
+    pub alpha: f64,
+
+    /// Number of values learned before giving a baseline
+    pub warmup: u64,
+
+    mean: f64,
+    variance: f64,
+    count: u64,
+}
+
+impl Ewma {
+    pub fn new(alpha: f64, warmup: u64) -> Ewma {
+        Ewma {
+            alpha: alpha.clamp(f64::MIN_POSITIVE, 1.0),
+            warmup: warmup.max(1),
+            mean: 0.0,
+            variance: 0.0,
+            count: 0,
+        }
+    }
+
+    pub fn baseline(&self) -> Option<Baseline> {
+        (self.count >= self.warmup).then(|| Baseline {
+            expected: self.mean,
+            spread: self.variance.sqrt(),
+        })
+    }
+
+    pub fn learn(&mut self, value: f64) {
+        if self.count == 0 {
+            self.mean = value;
+        } else {
+            let deviation = value - self.mean;
+            let increment = self.alpha * deviation;
+            self.mean += increment;
+            self.variance = (1.0 - self.alpha) * (self.variance + deviation * increment);
+        }
+        self.count += 1;
+    }
+}
+
+impl Default for Ewma {
+    /// About the last minute weighs most, after a minute of learning
+    fn default() -> Ewma {
+        Ewma::new(0.05, 60)
+    }
+}
+
+/// Baseline by time of the period, e.g. of the day, learned from the previous periods
+///
+/// The period is split in buckets, and the values of a bucket are compared to the mean and
+/// deviation of the same bucket in the previous periods, so a daily peak is not an anomaly.
+/// Times are UTC, as `recorded`.
+#[derive(Debug, Clone)]
+pub struct Seasonal {
+    /// Length of the period in seconds
+    pub period: u64,
+
+    /// Length of a bucket in seconds
+    pub bucket: u64,
+
+    /// Weight of the latest period, from 0 to 1
+    pub alpha: f64,
+
+    /// Number of periods learned before giving a baseline
+    pub warmup: u64,
+
+    buckets: Vec<Bucket>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Bucket {
+    /// Learned from the previous periods
+    mean: f64,
+    variance: f64,
+    periods: u64,
+
+    /// Values of the current period
+    start: u64,
+    count: u64,
+    sum: f64,
+    sum_of_squares: f64,
+}
+
+impl Bucket {
+    /// Learn the values of the current period
+    fn fold(&mut self, alpha: f64) {
+        if self.count == 0 {
+            return;
+        }
+        let mean = self.sum / self.count as f64;
+        let variance = (self.sum_of_squares / self.count as f64 - mean * mean).max(0.0);
+        if self.periods == 0 {
+            self.mean = mean;
+            self.variance = variance;
+        } else {
+            self.mean += alpha * (mean - self.mean);
+            self.variance += alpha * (variance - self.variance);
+        }
+        self.periods += 1;
+        self.count = 0;
+        self.sum = 0.0;
+        self.sum_of_squares = 0.0;
+    }
+}
+
+impl Seasonal {
+    pub fn new(period: u64, bucket: u64) -> Seasonal {
+        let period = period.max(1);
+        let bucket = bucket.clamp(1, period);
+        Seasonal {
+            period,
+            bucket,
+            alpha: 0.3,
+            warmup: 1,
+            buckets: vec![Bucket::default(); period.div_ceil(bucket) as usize],
+        }
+    }
+
+    /// Time of day, by buckets of 15 minutes
+    pub fn daily() -> Seasonal {
+        Seasonal::new(86400, 900)
+    }
+
+    pub fn with_alpha(mut self, alpha: f64) -> Seasonal {
+        self.alpha = alpha.clamp(f64::MIN_POSITIVE, 1.0);
+        self
+    }
+
+    pub fn with_warmup(mut self, periods: u64) -> Seasonal {
+        self.warmup = periods.max(1);
+        self
+    }
+
+    pub fn baseline(&mut self, recorded: u64) -> Option<Baseline> {
+        let (alpha, warmup) = (self.alpha, self.warmup);
+        let start = recorded - recorded % self.period;
+        let bucket = self.bucket_of(recorded);
+        if bucket.count > 0 && bucket.start != start {
+            bucket.fold(alpha);
+        }
+        (bucket.periods >= warmup).then(|| Baseline {
+            expected: bucket.mean,
+            spread: bucket.variance.sqrt(),
+        })
+    }
+
+    pub fn learn(&mut self, recorded: u64, value: f64) {
+        let alpha = self.alpha;
+        let start = recorded - recorded % self.period;
+        let bucket = self.bucket_of(recorded);
+        if bucket.count > 0 && bucket.start != start {
+            bucket.fold(alpha);
+        }
+        bucket.start = start;
+        bucket.count += 1;
+        bucket.sum += value;
+        bucket.sum_of_squares += value * value;
+    }
+
+    fn bucket_of(&mut self, recorded: u64) -> &mut Bucket {
+        let index = (recorded % self.period / self.bucket) as usize;
+        &mut self.buckets[index]
+    }
+}
+
+/// Median and median absolute deviation of the last values
+///
+/// Robust to outliers in the window, which do not move the baseline much.
+#[derive(Debug, Clone)]
+pub struct Mad {
+    /// Number of values of the window, a baseline is given once it is full
+    pub window: usize,
+
+    values: VecDeque<f64>,
+}
+
+impl Mad {
+    pub fn new(window: usize) -> Mad {
+        let window = window.max(1);
+        Mad {
+            window,
+            values: VecDeque::with_capacity(window),
+        }
+    }
+
+    pub fn baseline(&self) -> Option<Baseline> {
+        if self.values.len() < self.window {
+            return None;
+        }
+        let mut values: Vec<f64> = self.values.iter().copied().collect();
+        let expected = median(&mut values);
+        let mut deviations: Vec<f64> = values
+            .iter()
+            .map(|value| (value - expected).abs())
+            .collect();
+        // Scaled to estimate the standard deviation of normally distributed values
+        let spread = 1.4826 * median(&mut deviations);
+
+        Some(Baseline { expected, spread })
+    }
+
+    pub fn learn(&mut self, value: f64) {
+        if self.values.len() == self.window {
+            self.values.pop_front();
+        }
+        self.values.push_back(value);
+    }
+}
+
+impl Default for Mad {
+    /// The last minute
+    fn default() -> Mad {
+        Mad::new(60)
+    }
+}
+
+fn median(values: &mut [f64]) -> f64 {
+    values.sort_by(f64::total_cmp);
+    let middle = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[middle - 1] + values[middle]) / 2.0
+    } else {
+        values[middle]
+    }
+}
+
+/// One of the detectors, which learns a series of values and gives the baseline of the next one
+#[derive(Debug, Clone)]
+pub enum Detector {
+    Ewma(Ewma),
+    Seasonal(Seasonal),
+    Mad(Mad),
+}
+
+impl Detector {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Detector::Ewma(_) => "ewma",
+            Detector::Seasonal(_) => "seasonal",
+            Detector::Mad(_) => "mad",
+        }
+    }
+
+    /// Baseline of the value of `recorded`, `None` while learning
+    pub fn baseline(&mut self, recorded: u64) -> Option<Baseline> {
+        match self {
+            Detector::Ewma(ewma) => ewma.baseline(),
+            Detector::Seasonal(seasonal) => seasonal.baseline(recorded),
+            Detector::Mad(mad) => mad.baseline(),
+        }
+    }
+
+    pub fn learn(&mut self, recorded: u64, value: f64) {
+        match self {
+            Detector::Ewma(ewma) => ewma.learn(value),
+            Detector::Seasonal(seasonal) => seasonal.learn(recorded, value),
+            Detector::Mad(mad) => mad.learn(value),
+        }
+    }
+}
+
+impl From<Ewma> for Detector {
+    fn from(ewma: Ewma) -> Detector {
+        Detector::Ewma(ewma)
+    }
+}
+
+impl From<Seasonal> for Detector {
+    fn from(seasonal: Seasonal) -> Detector {
+        Detector::Seasonal(seasonal)
+    }
+}
+
+impl From<Mad> for Detector {
+    fn from(mad: Mad) -> Detector {
+        Detector::Mad(mad)
+    }
+}
+
+/// A detector attached to a metric, run on each scope it applies to
+///
+/// ```
+/// use fastly_rt::anomaly::{Ewma, Watch};
+///
+/// let watch = Watch::new("requests", Ewma::default()).by_pop(true).threshold(4.0);
+/// assert_eq!(watch.threshold, 4.0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Watch {
+    pub metric: String,
+
+    /// Prototype of the detector of each scope
+    pub detector: Detector,
+
+    /// Score from which a value is an anomaly, also the half width of the expected range in spreads
+    pub threshold: f64,
+
+    /// Services, origins and POPs the watch applies to, all services aggregated across POPs by default
+    pub filter: ScopeFilter,
+}
+
+impl Watch {
+    pub fn new<T: Into<Detector>>(metric: &str, detector: T) -> Watch {
+        Watch {
+            metric: metric.to_string(),
+            detector: detector.into(),
+            threshold: 3.0,
+            filter: ScopeFilter::default(),
+        }
+    }
+
+    pub fn threshold(mut self, threshold: f64) -> Watch {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Watch services matching `pattern` only, may be called several times
+    pub fn service(mut self, pattern: &str) -> Watch {
+        self.filter.services.push(pattern.to_string());
+        self
+    }
+
+    /// Watch origins matching `pattern` only, may be called several times
+    pub fn origin(mut self, pattern: &str) -> Watch {
+        self.filter.origins.push(pattern.to_string());
+        self
+    }
+
+    pub fn by_pop(mut self, by_pop: bool) -> Watch {
+        self.filter.by_pop = by_pop;
+        self
+    }
+}
+
+/// Value of a metric out of the range its detector expected
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnomalyEvent {
+    pub metric: String,
+
+    /// Name of the detector, `ewma`, `seasonal` or `mad`
+    pub detector: &'static str,
+
+    pub service: String,
+
+    pub scope: Scope,
+
+    pub recorded: u64,
+
+    pub value: f64,
+
+    pub expected: f64,
+
+    /// Lower end of the expected range
+    pub lower: f64,
+
+    /// Upper end of the expected range
+    pub upper: f64,
+
+    /// Deviation from the expected value in spreads, negative under it
+    pub score: f64,
+}
+
+impl fmt::Display for AnomalyEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} of {} in {}: {} out of {}..{}, score {:.1}",
+            self.metric, self.service, self.scope, self.value, self.lower, self.upper, self.score
+        )
+    }
+}
+
+/// Runs watches on each second of services, a detector per watch, service and scope
+///
+/// Each value is scored against the baseline learned from the previous ones, then learned.
+/// Scopes missing from a second, e.g. a POP without traffic, are not observed in it,
+/// and seconds not later than the last one observed are ignored.
+///
+/// ```
+/// use fastly_rt::anomaly::{AnomalyMonitor, Mad, Watch};
+/// use fastly_rt::service::ServiceDataInSecond;
+///
+/// let mut monitor = AnomalyMonitor::<ServiceDataInSecond>::new(vec![Watch::new("requests", Mad::new(30))]).unwrap();
+///
+/// let mut anomalies = Vec::new();
+/// for recorded in 1..=40 {
+///     let mut data = ServiceDataInSecond { recorded, ..Default::default() };
+///     data.aggregated.requests = if recorded == 35 { 500 } else { 100 + recorded % 3 };
+///     anomalies.extend(monitor.observe("www", &data));
+/// }
+///
+/// assert_eq!(anomalies.len(), 1);
+/// assert_eq!(anomalies[0].recorded, 35);
+/// ```
+pub struct AnomalyMonitor<D: Scoped> {
+    watches: Vec<Watch>,
+    value: Vec<fn(&D::Stats) -> f64>,
+    detectors: HashMap<(usize, String, Scope), (u64, Detector)>,
+}
+
+impl<D: Scoped> AnomalyMonitor<D> {
+    /// Fails when a watch is of a metric unknown to the stats of `D`
+    pub fn new(watches: Vec<Watch>) -> Result<AnomalyMonitor<D>> {
+        let mut value = Vec::new();
+        for watch in &watches {
+            match D::Stats::metric_info(&watch.metric) {
+                Some(metric) => value.push(metric.value),
+                None => bail!("unknown metric {}", watch.metric),
+            }
+        }
+
+        Ok(AnomalyMonitor {
+            watches,
+            value,
+            detectors: HashMap::new(),
+        })
+    }
+
+    pub fn watches(&self) -> &[Watch] {
+        &self.watches
+    }
+
+    /// Score and learn a new second of `service`, returns the anomalies, by watch then scope
+    pub fn observe(&mut self, service: &str, data: &D) -> Vec<AnomalyEvent> {
+        let recorded = data.recorded();
+        let mut scopes = data.scopes();
+        scopes.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let mut events = Vec::new();
+
+        for (index, watch) in self.watches.iter().enumerate() {
+            for (scope, stats) in &scopes {
+                if !watch.filter.applies(service, scope) {
+                    continue;
+                }
+                let value = (self.value[index])(stats);
+                let (last, detector) = self
+                    .detectors
+                    .entry((index, service.to_string(), scope.clone()))
+                    .or_insert_with(|| (0, watch.detector.clone()));
+                if *last >= recorded {
+                    continue;
+                }
+                *last = recorded;
+
+                if let Some(baseline) = detector.baseline(recorded) {
+                    let score = baseline.score(value);
+                    if score.abs() > watch.threshold {
+                        events.push(AnomalyEvent {
+                            metric: watch.metric.clone(),
+                            detector: detector.name(),
+                            service: service.to_string(),
+                            scope: scope.clone(),
+                            recorded,
+                            value,
+                            expected: baseline.expected,
+                            lower: baseline.expected - watch.threshold * baseline.spread,
+                            upper: baseline.expected + watch.threshold * baseline.spread,
+                            score,
+                        });
+                    }
+                }
+                detector.learn(recorded, value);
+            }
+        }
+        events
+    }
+
+    /// Observe every second of `response`
+    pub fn observe_response(
+        &mut self,
+        service: &str,
+        response: &RtResponse<D>,
+    ) -> Vec<AnomalyEvent> {
+        response
+            .data
+            .iter()
+            .flat_map(|data| self.observe(service, data))
+            .collect()
+    }
+
+    /// Forget what was learned of `service`, e.g. after a deployment changing its traffic
+    pub fn reset(&mut self, service: &str) {
+        self.detectors
+            .retain(|(_, detector_service, _), _| detector_service != service);
+    }
+}
@@ -0,0 +1,457 @@
+use crate::origin::OriginDataInSecond;
+use crate::realtime::RealtimeData;
+use crate::service::ServiceDataInSecond;
+use crate::utc;
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Real time data which can be archived
+pub trait Archived: RealtimeData + Serialize {
+    /// Kind of data, used as prefix of file names
+    const NAME: &'static str;
+
+    fn recorded(&self) -> u64;
+}
+
+impl Archived for ServiceDataInSecond {
+    const NAME: &'static str = "service";
+
+    fn recorded(&self) -> u64 {
+        self.recorded
+    }
+}
+
+impl Archived for OriginDataInSecond {
+    const NAME: &'static str = "origin";
+
+    fn recorded(&self) -> u64 {
+        self.recorded
+    }
+}
+
+/// Compression of closed archive files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+
+    /// `.ndjson.gz` files
+    Gzip,
+
+    /// `.ndjson.zst` files
+    Zstd,
+}
+
+impl Compression {
+    fn extension(&self) -> &'static str {
+        match self {
+            Compression::None => "ndjson",
+            Compression::Gzip => "ndjson.gz",
+            Compression::Zstd => "ndjson.zst",
+        }
+    }
+}
+
+/// Configuration of an [`Archiver`]
+#[derive(Debug, Clone)]
+pub struct ArchiveConfig {
+    /// Directory of the archive, created if missing
+    pub dir: PathBuf,
+
+    /// Start a new file when the current one would grow over this size, before compression
+    pub max_file_size: Option<u64>,
+
+    /// Start a new file with every hour of `recorded`, in UTC
+    pub hourly: bool,
+
+    pub compression: Compression,
+
+    /// Remove files of data older than this, relative to the latest archived second
+    pub max_age: Option<Duration>,
+
+    /// Remove the oldest files while the archive is larger than this
+    pub max_total_size: Option<u64>,
+}
+
+impl ArchiveConfig {
+    /// Hourly files of at most 64 MiB, compressed with zstd and kept forever
+    pub fn new<P: AsRef<Path>>(dir: P) -> ArchiveConfig {
+        ArchiveConfig {
+            dir: dir.as_ref().to_path_buf(),
+            max_file_size: Some(64 * 1024 * 1024),
+            hourly: true,
+            compression: Compression::Zstd,
+            max_age: None,
+            max_total_size: None,
+        }
+    }
+}
+
+/// Appends every second of data as a JSON line to rotating files
+///
+/// Files are named `{name}-{YYYY-MM-DDTHH}-{recorded}.ndjson` after [`Archived::NAME`] and the first second
+/// they hold, and compressed once closed, as a new frame of a compressed file of the same name if any.
+/// Files left uncompressed by a previous run are compressed on start.
+/// Retention is enforced on start and on every rotation, only on closed files.
+///
+/// ```no_run
+/// use fastly_rt::archive::{ArchiveConfig, Archiver};
+/// use fastly_rt::service::{ServiceClient, ServiceDataInSecond};
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let mut rt = ServiceClient::new("api_key", "service_id").unwrap();
+///     let mut archiver = Archiver::<ServiceDataInSecond>::new(ArchiveConfig::new("archive")).unwrap();
+///
+///     loop {
+///         archiver.append(&rt.get_stats_consecutive().await.unwrap().data).unwrap();
+///     }
+/// }
+/// ```
+pub struct Archiver<D: Archived> {
+    config: ArchiveConfig,
+    current: Option<Current>,
+    latest: u64,
+    data: PhantomData<fn(&D)>,
+}
+
+/// File being appended to
+struct Current {
+    path: PathBuf,
+    hour: u64,
+    size: u64,
+    writer: BufWriter<File>,
+}
+
+impl<D: Archived> Archiver<D> {
+    pub fn new(config: ArchiveConfig) -> Result<Archiver<D>> {
+        fs::create_dir_all(&config.dir)?;
+
+        let files = archive_files(&config.dir, D::NAME)?;
+        let archiver = Archiver {
+            config,
+            current: None,
+            // At least the first second of the latest file, until seconds are appended
+            latest: files.last().map_or(0, |file| file.first),
+            data: PhantomData,
+        };
+        for file in files {
+            if file.plain {
+                archiver.compress(&file.path)?;
+            }
+        }
+        archiver.enforce_retention()?;
+
+        Ok(archiver)
+    }
+
+    pub fn config(&self) -> &ArchiveConfig {
+        &self.config
+    }
+
+    /// Append seconds of data, and flush them
+    pub fn append(&mut self, seconds: &[D]) -> Result<()> {
+        for data in seconds {
+            let mut line = serde_json::to_vec(data)?;
+            line.push(b'\n');
+            let recorded = data.recorded();
+
+            if let Some(current) = &self.current {
+                let hour_changed = self.config.hourly && recorded / 3600 != current.hour;
+                let full = self
+                    .config
+                    .max_file_size
+                    .is_some_and(|max| current.size > 0 && current.size + line.len() as u64 > max);
+                if hour_changed || full {
+                    self.rotate()?;
+                }
+            }
+
+            let current = match &mut self.current {
+                Some(current) => current,
+                None => self.current.insert(self.create(recorded)?),
+            };
+            current.writer.write_all(&line)?;
+            current.size += line.len() as u64;
+            self.latest = self.latest.max(recorded);
+        }
+
+        if let Some(current) = &mut self.current {
+            current.writer.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Close and compress the current file, then enforce retention
+    pub fn rotate(&mut self) -> Result<()> {
+        self.finish()?;
+        self.enforce_retention()
+    }
+
+    /// Close and compress the current file
+    pub fn close(mut self) -> Result<()> {
+        self.finish()
+    }
+
+    fn create(&self, recorded: u64) -> Result<Current> {
+        let path = self.config.dir.join(format!(
+            "{}-{}-{}.ndjson",
+            D::NAME,
+            utc::hour_label(recorded),
+            recorded
+        ));
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)?;
+        let size = file.metadata()?.len();
+
+        Ok(Current {
+            path,
+            hour: recorded / 3600,
+            size,
+            writer: BufWriter::new(file),
+        })
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        if let Some(mut current) = self.current.take() {
+            current.writer.flush()?;
+            drop(current.writer);
+            self.compress(&current.path)?;
+        }
+        Ok(())
+    }
+
+    /// Replace the plain file at `path` by its compressed version, through a temporary file
+    /// A compressed file of the same name, e.g. from a previous run, gets the data as a new frame.
+    fn compress(&self, path: &Path) -> Result<()> {
+        if self.config.compression == Compression::None {
+            return Ok(());
+        }
+
+        let extension = self.config.compression.extension();
+        let compressed = path.with_extension(extension);
+        let temporary = path.with_extension(format!("{}.tmp", extension));
+        let mut input = File::open(path)?;
+        let mut output = BufWriter::new(File::create(&temporary)?);
+        if compressed.exists() {
+            std::io::copy(&mut File::open(&compressed)?, &mut output)?;
+        }
+        match self.config.compression {
+            Compression::None => {}
+            Compression::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(output, flate2::Compression::default());
+                std::io::copy(&mut input, &mut encoder)?;
+                encoder.finish()?.flush()?;
+            }
+            Compression::Zstd => {
+                let mut encoder = zstd::Encoder::new(output, 0)?;
+                std::io::copy(&mut input, &mut encoder)?;
+                encoder.finish()?.flush()?;
+            }
+        }
+
+        fs::rename(&temporary, &compressed)?;
+        fs::remove_file(path)?;
+        Ok(())
+    }
+
+    /// Remove closed files too old, then the oldest closed files while the archive is too large
+    fn enforce_retention(&self) -> Result<()> {
+        let files = archive_files(&self.config.dir, D::NAME)?;
+        let current = self.current.as_ref().map(|current| &current.path);
+        let mut removed = vec![false; files.len()];
+
+        if let Some(max_age) = self.config.max_age {
+            let cutoff = self.latest.saturating_sub(max_age.as_secs());
+            // A file holds data up to the first second of the next one
+            for index in 0..files.len().saturating_sub(1) {
+                if files[index + 1].first <= cutoff && Some(&files[index].path) != current {
+                    removed[index] = true;
+                }
+            }
+        }
+
+        if let Some(max_total_size) = self.config.max_total_size {
+            let mut total: u64 = files
+                .iter()
+                .zip(&removed)
+                .filter(|(_, removed)| !**removed)
+                .map(|(file, _)| file.size)
+                .sum();
+            for (file, removed) in files.iter().zip(removed.iter_mut()) {
+                if total <= max_total_size {
+                    break;
+                }
+                if !*removed && Some(&file.path) != current {
+                    *removed = true;
+                    total -= file.size;
+                }
+            }
+        }
+
+        for (file, removed) in files.iter().zip(removed) {
+            if removed {
+                fs::remove_file(&file.path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<D: Archived> Drop for Archiver<D> {
+    fn drop(&mut self) {
+        self.finish().ok();
+    }
+}
+
+/// Reads archived seconds back, from plain and compressed files alike
+pub struct ArchiveReader<D: Archived> {
+    dir: PathBuf,
+    data: PhantomData<fn() -> D>,
+}
+
+impl<D: Archived> ArchiveReader<D> {
+    pub fn new<P: AsRef<Path>>(dir: P) -> ArchiveReader<D> {
+        ArchiveReader {
+            dir: dir.as_ref().to_path_buf(),
+            data: PhantomData,
+        }
+    }
+
+    /// Every archived second
+    pub fn all(&self) -> Result<Seconds<D>> {
+        self.range(0, u64::MAX)
+    }
+
+    /// Archived seconds with `recorded` in `from..to`, in the order of the files
+    pub fn range(&self, from: u64, to: u64) -> Result<Seconds<D>> {
+        let files = archive_files(&self.dir, D::NAME)?;
+        let mut selected = VecDeque::new();
+        for (index, file) in files.iter().enumerate() {
+            let next = files.get(index + 1).map(|next| next.first);
+            if file.first < to && next.is_none_or(|next| next > from) {
+                selected.push_back(file.path.clone());
+            }
+        }
+
+        Ok(Seconds {
+            files: selected,
+            lines: None,
+            from,
+            to,
+            data: PhantomData,
+        })
+    }
+}
+
+/// Iterator over archived seconds, reading one file at a time
+pub struct Seconds<D> {
+    files: VecDeque<PathBuf>,
+    lines: Option<std::io::Lines<Box<dyn BufRead>>>,
+    from: u64,
+    to: u64,
+    data: PhantomData<fn() -> D>,
+}
+
+impl<D: Archived> Iterator for Seconds<D> {
+    type Item = Result<D>;
+
+    fn next(&mut self) -> Option<Result<D>> {
+        loop {
+            if let Some(lines) = &mut self.lines {
+                match lines.next() {
+                    Some(Ok(line)) if line.is_empty() => continue,
+                    Some(Ok(line)) => match serde_json::from_str::<D>(&line) {
+                        Ok(data) if (self.from..self.to).contains(&data.recorded()) => {
+                            return Some(Ok(data))
+                        }
+                        Ok(_) => continue,
+                        Err(e) => return Some(Err(e.into())),
+                    },
+                    Some(Err(e)) => return Some(Err(e.into())),
+                    None => self.lines = None,
+                }
+            }
+
+            let path = self.files.pop_front()?;
+            match open(&path) {
+                Ok(reader) => self.lines = Some(reader.lines()),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+fn open(path: &Path) -> Result<Box<dyn BufRead>> {
+    let file = File::open(path)?;
+    let name = path.to_string_lossy();
+
+    Ok(if name.ends_with(".gz") {
+        Box::new(BufReader::new(flate2::read::MultiGzDecoder::new(file)))
+    } else if name.ends_with(".zst") {
+        Box::new(BufReader::new(zstd::Decoder::new(file)?))
+    } else {
+        Box::new(BufReader::new(file))
+    })
+}
+
+/// A file of the archive
+struct ArchiveFile {
+    path: PathBuf,
+
+    /// `recorded` of its first second
+    first: u64,
+
+    size: u64,
+
+    /// Not compressed
+    plain: bool,
+}
+
+/// Files of the archive of `name` in `dir`, sorted by their first second
+fn archive_files(dir: &Path, name: &str) -> Result<Vec<ArchiveFile>> {
+    let prefix = format!("{}-", name);
+    let mut files = Vec::new();
+
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let Some(rest) = file_name.strip_prefix(&prefix) else {
+            continue;
+        };
+        let (stem, plain) = if let Some(stem) = rest.strip_suffix(".ndjson") {
+            (stem, true)
+        } else if let Some(stem) = rest
+            .strip_suffix(".ndjson.gz")
+            .or_else(|| rest.strip_suffix(".ndjson.zst"))
+        {
+            (stem, false)
+        } else {
+            continue;
+        };
+        let Some(first) = stem
+            .rsplit_once('-')
+            .and_then(|(_, first)| first.parse().ok())
+        else {
+            continue;
+        };
+
+        files.push(ArchiveFile {
+            path: entry.path(),
+            first,
+            size: entry.metadata()?.len(),
+            plain,
+        });
+    }
+
+    files.sort_by_key(|file| file.first);
+    Ok(files)
+}
@@ -0,0 +1,383 @@
+use crate::metric::{sorted, Metrics};
+use crate::origin::{OriginDataInSecond, OriginStats};
+use crate::service::{ServiceDataInSecond, ServiceStats};
+use crate::utc;
+use anyhow::Result;
+use arrow_array::builder::{Float64Builder, StringBuilder, TimestampSecondBuilder};
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use std::fs::File;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Shape of record batches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    /// One row per second and scope, one column per metric
+    Wide,
+
+    /// One row per second, scope and metric, with `metric` and `value` columns
+    Long,
+}
+
+/// Name and value of an extra nullable column
+type Extra<S> = (&'static str, fn(&S) -> Option<f64>);
+
+/// Percentiles of `miss_histogram` in milliseconds, extra columns of service data
+const PERCENTILES: &[Extra<ServiceStats>] = &[
+    ("miss_latency_p50", |stats| stats.miss_histogram.p50()),
+    ("miss_latency_p90", |stats| stats.miss_histogram.p90()),
+    ("miss_latency_p99", |stats| stats.miss_histogram.p99()),
+];
+
+const NO_PERCENTILES: &[Extra<OriginStats>] = &[];
+
+/// Real time data convertible to Arrow record batches
+///
+/// Batches start with a `recorded` timestamp column in seconds, UTC, and a `pop` column,
+/// null for data aggregated across POPs. Origin data adds an `origin` column.
+/// Service data has nullable `miss_latency_p50`, `miss_latency_p90` and `miss_latency_p99`
+/// columns in milliseconds after the metrics of the catalog.
+///
+/// ```
+/// use fastly_rt::arrow::Columnar;
+/// use fastly_rt::service::ServiceDataInSecond;
+///
+/// let data = vec![ServiceDataInSecond { recorded: 1, ..Default::default() }];
+/// let batch = ServiceDataInSecond::wide(&data, false).unwrap();
+///
+/// assert_eq!(batch.num_rows(), 1);
+/// assert!(batch.schema().field_with_name("requests").is_ok());
+/// ```
+pub trait Columnar: Sized {
+    /// Kind of data, used as prefix of file names
+    const NAME: &'static str;
+
+    fn recorded(&self) -> u64;
+
+    /// One row per second, or per second and POP when `by_pop`
+    fn wide(seconds: &[Self], by_pop: bool) -> Result<RecordBatch>;
+
+    /// One row per second and metric, or per second, POP and metric when `by_pop`
+    fn long(seconds: &[Self], by_pop: bool) -> Result<RecordBatch>;
+
+    fn batch(seconds: &[Self], layout: Layout, by_pop: bool) -> Result<RecordBatch> {
+        match layout {
+            Layout::Wide => Self::wide(seconds, by_pop),
+            Layout::Long => Self::long(seconds, by_pop),
+        }
+    }
+}
+
+impl Columnar for ServiceDataInSecond {
+    const NAME: &'static str = "service";
+
+    fn recorded(&self) -> u64 {
+        self.recorded
+    }
+
+    fn wide(seconds: &[ServiceDataInSecond], by_pop: bool) -> Result<RecordBatch> {
+        wide(&service_rows(seconds, by_pop), false, PERCENTILES)
+    }
+
+    fn long(seconds: &[ServiceDataInSecond], by_pop: bool) -> Result<RecordBatch> {
+        long(&service_rows(seconds, by_pop), false, PERCENTILES)
+    }
+}
+
+impl Columnar for OriginDataInSecond {
+    const NAME: &'static str = "origin";
+
+    fn recorded(&self) -> u64 {
+        self.recorded
+    }
+
+    fn wide(seconds: &[OriginDataInSecond], by_pop: bool) -> Result<RecordBatch> {
+        wide(&origin_rows(seconds, by_pop), true, NO_PERCENTILES)
+    }
+
+    fn long(seconds: &[OriginDataInSecond], by_pop: bool) -> Result<RecordBatch> {
+        long(&origin_rows(seconds, by_pop), true, NO_PERCENTILES)
+    }
+}
+
+/// Writes real time data to Parquet files, one per hour in UTC
+///
+/// Files are named `{name}-{YYYY-MM-DDTHH}.parquet` after [`Columnar::NAME`] and the hour of their data,
+/// with a `-{n}` suffix when such a file already exists.
+/// Seconds are expected in order, an hour seen again opening a new file.
+///
+/// ```no_run
+/// use fastly_rt::arrow::{Layout, ParquetWriter};
+/// use fastly_rt::service::{ServiceClient, ServiceDataInSecond};
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let mut rt = ServiceClient::new("api_key", "service_id").unwrap();
+///     let mut writer = ParquetWriter::<ServiceDataInSecond>::new("data", Layout::Wide).by_pop(true);
+///
+///     loop {
+///         writer.write(&rt.get_stats_consecutive().await.unwrap().data).unwrap();
+///     }
+/// }
+/// ```
+pub struct ParquetWriter<D: Columnar> {
+    dir: PathBuf,
+    layout: Layout,
+    by_pop: bool,
+    current: Option<(u64, ArrowWriter<File>)>,
+    files: Vec<PathBuf>,
+    data: PhantomData<fn(&D)>,
+}
+
+impl<D: Columnar> ParquetWriter<D> {
+    /// Write files to the existing directory `dir`
+    pub fn new<P: AsRef<Path>>(dir: P, layout: Layout) -> ParquetWriter<D> {
+        ParquetWriter {
+            dir: dir.as_ref().to_path_buf(),
+            layout,
+            by_pop: false,
+            current: None,
+            files: Vec::new(),
+            data: PhantomData,
+        }
+    }
+
+    /// Write data by POP instead of aggregated across POPs
+    pub fn by_pop(mut self, by_pop: bool) -> ParquetWriter<D> {
+        self.by_pop = by_pop;
+        self
+    }
+
+    /// Append seconds of data to the file of their hour, row groups being split by size
+    pub fn write(&mut self, seconds: &[D]) -> Result<()> {
+        let mut start = 0;
+        while start < seconds.len() {
+            let hour = seconds[start].recorded() / 3600;
+            let count = seconds[start..]
+                .iter()
+                .take_while(|data| data.recorded() / 3600 == hour)
+                .count();
+
+            let batch = D::batch(&seconds[start..start + count], self.layout, self.by_pop)?;
+            self.writer(hour, batch.schema())?.write(&batch)?;
+            start += count;
+        }
+
+        Ok(())
+    }
+
+    /// Finish the file being written, returns every file written
+    pub fn close(mut self) -> Result<Vec<PathBuf>> {
+        self.finish()?;
+        Ok(std::mem::take(&mut self.files))
+    }
+
+    fn writer(&mut self, hour: u64, schema: SchemaRef) -> Result<&mut ArrowWriter<File>> {
+        if self.current.as_ref().map(|(current, _)| *current) != Some(hour) {
+            self.finish()?;
+
+            let label = utc::hour_label(hour * 3600);
+            let mut path = self.dir.join(format!("{}-{}.parquet", D::NAME, label));
+            let mut n = 1;
+            while path.exists() {
+                path = self
+                    .dir
+                    .join(format!("{}-{}-{}.parquet", D::NAME, label, n));
+                n += 1;
+            }
+
+            let properties = WriterProperties::builder()
+                .set_compression(Compression::SNAPPY)
+                .build();
+            let writer = ArrowWriter::try_new(File::create(&path)?, schema, Some(properties))?;
+            self.files.push(path);
+            self.current = Some((hour, writer));
+        }
+
+        Ok(&mut self.current.as_mut().unwrap().1)
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        if let Some((_, writer)) = self.current.take() {
+            writer.close()?;
+        }
+        Ok(())
+    }
+}
+
+impl<D: Columnar> Drop for ParquetWriter<D> {
+    fn drop(&mut self) {
+        self.finish().ok();
+    }
+}
+
+/// Stats of a second in a scope
+struct Row<'a, S> {
+    recorded: u64,
+    pop: Option<&'a str>,
+    origin: Option<&'a str>,
+    stats: &'a S,
+}
+
+fn service_rows(seconds: &[ServiceDataInSecond], by_pop: bool) -> Vec<Row<'_, ServiceStats>> {
+    let mut rows = Vec::new();
+    for data in seconds {
+        if by_pop {
+            for (pop_name, stats) in sorted(&data.datacenter) {
+                rows.push(Row {
+                    recorded: data.recorded,
+                    pop: Some(pop_name),
+                    origin: None,
+                    stats,
+                });
+            }
+        } else {
+            rows.push(Row {
+                recorded: data.recorded,
+                pop: None,
+                origin: None,
+                stats: &data.aggregated,
+            });
+        }
+    }
+    rows
+}
+
+fn origin_rows(seconds: &[OriginDataInSecond], by_pop: bool) -> Vec<Row<'_, OriginStats>> {
+    let mut rows = Vec::new();
+    for data in seconds {
+        if by_pop {
+            for (pop_name, origins) in sorted(&data.datacenter) {
+                for (origin_name, stats) in sorted(origins) {
+                    rows.push(Row {
+                        recorded: data.recorded,
+                        pop: Some(pop_name),
+                        origin: Some(origin_name),
+                        stats,
+                    });
+                }
+            }
+        } else {
+            for (origin_name, stats) in sorted(&data.aggregated) {
+                rows.push(Row {
+                    recorded: data.recorded,
+                    pop: None,
+                    origin: Some(origin_name),
+                    stats,
+                });
+            }
+        }
+    }
+    rows
+}
+
+/// Columns identifying the scope of rows
+fn key_fields(with_origin: bool) -> Vec<Field> {
+    let mut fields = vec![
+        Field::new(
+            "recorded",
+            DataType::Timestamp(TimeUnit::Second, Some("UTC".into())),
+            false,
+        ),
+        Field::new("pop", DataType::Utf8, true),
+    ];
+    if with_origin {
+        fields.push(Field::new("origin", DataType::Utf8, false));
+    }
+    fields
+}
+
+fn key_columns<'a, S: 'a>(
+    keys: impl Iterator<Item = &'a Row<'a, S>>,
+    with_origin: bool,
+) -> Vec<ArrayRef> {
+    let mut recorded = TimestampSecondBuilder::new().with_timezone("UTC");
+    let mut pop = StringBuilder::new();
+    let mut origin = StringBuilder::new();
+    for row in keys {
+        recorded.append_value(row.recorded as i64);
+        pop.append_option(row.pop);
+        origin.append_option(row.origin);
+    }
+
+    let mut columns: Vec<ArrayRef> = vec![Arc::new(recorded.finish()), Arc::new(pop.finish())];
+    if with_origin {
+        columns.push(Arc::new(origin.finish()));
+    }
+    columns
+}
+
+fn wide<S: Metrics>(
+    rows: &[Row<S>],
+    with_origin: bool,
+    percentiles: &[Extra<S>],
+) -> Result<RecordBatch> {
+    let mut fields = key_fields(with_origin);
+    let mut columns = key_columns(rows.iter(), with_origin);
+
+    for metric in S::METRICS {
+        fields.push(Field::new(metric.name, DataType::Float64, false));
+        let mut values = Float64Builder::with_capacity(rows.len());
+        for row in rows {
+            values.append_value((metric.value)(row.stats));
+        }
+        columns.push(Arc::new(values.finish()));
+    }
+
+    for (name, percentile) in percentiles {
+        fields.push(Field::new(*name, DataType::Float64, true));
+        let mut values = Float64Builder::with_capacity(rows.len());
+        for row in rows {
+            values.append_option(percentile(row.stats));
+        }
+        columns.push(Arc::new(values.finish()));
+    }
+
+    Ok(RecordBatch::try_new(
+        Arc::new(Schema::new(fields)),
+        columns,
+    )?)
+}
+
+fn long<S: Metrics>(
+    rows: &[Row<S>],
+    with_origin: bool,
+    percentiles: &[Extra<S>],
+) -> Result<RecordBatch> {
+    let mut keys = Vec::new();
+    let mut metric = StringBuilder::new();
+    let mut value = Float64Builder::new();
+
+    for row in rows {
+        for (info, row_value) in row.stats.metrics() {
+            keys.push(row);
+            metric.append_value(info.name);
+            value.append_value(row_value);
+        }
+        for (name, percentile) in percentiles {
+            if let Some(percentile) = percentile(row.stats) {
+                keys.push(row);
+                metric.append_value(name);
+                value.append_value(percentile);
+            }
+        }
+    }
+
+    let mut fields = key_fields(with_origin);
+    fields.push(Field::new("metric", DataType::Utf8, false));
+    fields.push(Field::new("value", DataType::Float64, false));
+
+    let mut columns = key_columns(keys.into_iter(), with_origin);
+    columns.push(Arc::new(metric.finish()));
+    columns.push(Arc::new(value.finish()));
+
+    Ok(RecordBatch::try_new(
+        Arc::new(Schema::new(fields)),
+        columns,
+    )?)
+}
@@ -0,0 +1,59 @@
+//! Prometheus exporter of Fastly real time data
+//!
+//! ```text
+//! fastly-rt-exporter --config exporter.toml [--listen 0.0.0.0:9150]
+//! ```
+//!
+//! See `fastly_rt::exporter::ExporterConfig` for the configuration file,
+//! the API key is read from the `FASTLY_API_KEY` environment variable when not configured.
+use anyhow::{anyhow, Context, Result};
+use fastly_rt::exporter::{Exporter, ExporterConfig};
+use std::env;
+
+fn parse_args() -> Result<ExporterConfig> {
+    let mut config = None;
+    let mut listen = None;
+    let mut args = env::args().skip(1);
+
+    while let Some(flag) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| anyhow!("missing value of {}", flag))?;
+
+        match flag.as_str() {
+            "--config" => config = Some(ExporterConfig::load(&value)?),
+            "--listen" => listen = Some(value.parse().context("--listen")?),
+            _ => return Err(anyhow!("unknown option {}", flag)),
+        }
+    }
+
+    let mut config = config.ok_or_else(|| anyhow!("--config is required"))?;
+    if let Some(listen) = listen {
+        config.listen = listen;
+    }
+    if config.api_key.is_empty() {
+        config.api_key = env::var("FASTLY_API_KEY")
+            .context("api_key not configured and FASTLY_API_KEY not set")?;
+    }
+    if config.services.is_empty() {
+        return Err(anyhow!("no service configured"));
+    }
+
+    Ok(config)
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let config = parse_args()?;
+    let services = config.services.len();
+    let exporter = Exporter::start(config).await?;
+
+    println!(
+        "exporting {} services on http://{}/metrics",
+        services,
+        exporter.local_addr()
+    );
+
+    tokio::signal::ctrl_c().await?;
+    Ok(())
+}
@@ -0,0 +1,61 @@
+//! Local fake of Fastly real time API
+//!
+//! ```text
+//! fastly-rt-fake [--addr 127.0.0.1:8080] [--key KEY] [--pops NRT,LHR] [--origins a,b]
+//!                [--rps 100] [--seed 0] [--aggregate-delay 3] [--delay-ms 0]
+//!                [--error-every 0] [--error-status 503]
+//! ```
+use anyhow::{anyhow, Context, Result};
+use fastly_rt::fake::{FakeConfig, FakeServer};
+use std::env;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+fn parse_args() -> Result<(SocketAddr, FakeConfig)> {
+    let mut addr = SocketAddr::from(([127, 0, 0, 1], 8080));
+    let mut config = FakeConfig::default();
+    let mut args = env::args().skip(1);
+
+    while let Some(flag) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| anyhow!("missing value of {}", flag))?;
+        let list = || value.split(',').map(|item| item.to_string()).collect();
+
+        match flag.as_str() {
+            "--addr" => addr = value.parse().context("--addr")?,
+            "--key" => config.api_key = value,
+            "--pops" => config.traffic.pops = list(),
+            "--origins" => config.traffic.origins = list(),
+            "--rps" => config.traffic.requests_per_second = value.parse().context("--rps")?,
+            "--seed" => config.traffic.seed = value.parse().context("--seed")?,
+            "--aggregate-delay" => {
+                config.aggregate_delay = value.parse().context("--aggregate-delay")?
+            }
+            "--delay-ms" => {
+                config.response_delay = Duration::from_millis(value.parse().context("--delay-ms")?)
+            }
+            "--error-every" => config.error_every = value.parse().context("--error-every")?,
+            "--error-status" => config.error_status = value.parse().context("--error-status")?,
+            _ => return Err(anyhow!("unknown option {}", flag)),
+        }
+    }
+
+    Ok((addr, config))
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let (addr, config) = parse_args()?;
+    let api_key = config.api_key.clone();
+    let server = FakeServer::bind(addr, config).await?;
+
+    println!(
+        "fake Fastly real time API listening on {}, API key {:?}",
+        server.base_url(),
+        api_key
+    );
+
+    tokio::signal::ctrl_c().await?;
+    Ok(())
+}
@@ -0,0 +1,362 @@
+use crate::realtime::{Query, RealtimeClient, RealtimeData, RtClient, RtResponse};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::future::Future;
+use std::io::{BufRead, BufReader, Write};
+use std::marker::PhantomData;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// One line of an NDJSON cassette: a query and the raw response, or the error, it got,
+/// or a reset of the consecutive timestamp
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CassetteEntry {
+    /// Unix time in milliseconds when the response was received
+    pub at_ms: u64,
+
+    /// Path of the endpoint, e.g. `/v1/channel`, see [`RealtimeData::PATH`]
+    pub endpoint: String,
+
+    pub service_id: String,
+
+    pub query: Query,
+
+    /// Raw JSON response, `None` when the query failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<Value>,
+
+    /// Error message when the query failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+
+    /// The client reset its consecutive timestamp, an entry without response nor error
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub reset: bool,
+}
+
+/// Client wrapper writing every response it gets, and every reset, to an NDJSON cassette
+///
+/// A failure to write the cassette does not fail the query, whose response is still returned,
+/// but is kept until taken with [`Recorder::take_write_error`].
+///
+/// ```no_run
+/// use fastly_rt::cassette::Recorder;
+/// use fastly_rt::service::ServiceClient;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let client = ServiceClient::new("api_key", "service_id").unwrap();
+///     let mut rt = Recorder::to_file(client, "service.ndjson").unwrap();
+///
+///     let rt_data = rt.get_stats_consecutive().await.unwrap();
+/// }
+/// ```
+pub struct Recorder<D, W> {
+    client: tokio::sync::Mutex<RtClient<D>>,
+    service_id: String,
+    cassette: Mutex<W>,
+    write_error: Mutex<Option<anyhow::Error>>,
+}
+
+impl<D: RealtimeData> Recorder<D, File> {
+    /// Record to the cassette file at `path`, appending to it when it exists
+    pub fn to_file<P: AsRef<Path>>(client: RtClient<D>, path: P) -> Result<Recorder<D, File>> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Recorder::new(client, file))
+    }
+}
+
+impl<D: RealtimeData, W: Write + Send> Recorder<D, W> {
+    /// Record responses of `client` to `cassette`
+    pub fn new(client: RtClient<D>, cassette: W) -> Recorder<D, W> {
+        let service_id = client.service_id().to_string();
+
+        Recorder {
+            client: tokio::sync::Mutex::new(client),
+            service_id,
+            cassette: Mutex::new(cassette),
+            write_error: Mutex::new(None),
+        }
+    }
+
+    /// Get back the client and the cassette
+    pub fn into_inner(self) -> (RtClient<D>, W) {
+        (
+            self.client.into_inner(),
+            self.cassette
+                .into_inner()
+                .unwrap_or_else(|e| e.into_inner()),
+        )
+    }
+
+    /// Error of the last failed write to the cassette, if any since the last call
+    pub fn take_write_error(&self) -> Option<anyhow::Error> {
+        self.write_error.lock().unwrap().take()
+    }
+
+    /// Reset internal timestamp which used to track consecutive stats to 0
+    pub fn reset_stats_consecutive(&mut self) {
+        self.client.get_mut().reset_stats_consecutive();
+
+        let mut entry = self.entry(Query::Consecutive);
+        entry.reset = true;
+        self.write(&entry);
+    }
+
+    /// See [`RtClient::get_stats_consecutive`]
+    pub async fn get_stats_consecutive(&mut self) -> Result<RtResponse<D>> {
+        self.record(Query::Consecutive).await
+    }
+
+    /// See [`RtClient::get_stats_from`]
+    pub async fn get_stats_from(&mut self, start_timestamp: u64) -> Result<RtResponse<D>> {
+        self.record(Query::From(start_timestamp)).await
+    }
+
+    /// See [`RtClient::get_stats_120s`]
+    pub async fn get_stats_120s(&self) -> Result<RtResponse<D>> {
+        self.record(Query::Last120s).await
+    }
+
+    /// See [`RtClient::get_stats_max`]
+    pub async fn get_stats_max(&self, max_entries: u64) -> Result<RtResponse<D>> {
+        self.record(Query::Max(max_entries)).await
+    }
+
+    async fn record(&self, query: Query) -> Result<RtResponse<D>> {
+        let result = self.client.lock().await.query_raw(query).await;
+
+        let mut entry = self.entry(query);
+        match &result {
+            Ok((raw, _)) => match serde_json::from_str(raw) {
+                Ok(response) => entry.response = Some(response),
+                Err(e) => entry.error = Some(e.to_string()),
+            },
+            Err(e) => entry.error = Some(e.to_string()),
+        }
+        self.write(&entry);
+
+        result.map(|(_, response)| response)
+    }
+
+    fn entry(&self, query: Query) -> CassetteEntry {
+        CassetteEntry {
+            at_ms: now_ms(),
+            endpoint: D::PATH.to_string(),
+            service_id: self.service_id.clone(),
+            query,
+            response: None,
+            error: None,
+            reset: false,
+        }
+    }
+
+    /// Write `entry` as a line of the cassette, keeping the error when it fails
+    fn write(&self, entry: &CassetteEntry) {
+        let mut cassette = self.cassette.lock().unwrap();
+        let written = serde_json::to_writer(&mut *cassette, entry)
+            .map_err(anyhow::Error::from)
+            .and_then(|_| Ok(cassette.write_all(b"\n")?))
+            .and_then(|_| Ok(cassette.flush()?));
+        if let Err(e) = written {
+            *self.write_error.lock().unwrap() = Some(e);
+        }
+    }
+}
+
+impl<D: RealtimeData, W: Write + Send> RealtimeClient for Recorder<D, W> {
+    type Data = D;
+
+    fn reset_stats_consecutive(&mut self) {
+        Recorder::reset_stats_consecutive(self)
+    }
+
+    fn get_stats_consecutive(&mut self) -> impl Future<Output = Result<RtResponse<D>>> + Send {
+        Recorder::get_stats_consecutive(self)
+    }
+
+    fn get_stats_from(
+        &mut self,
+        start_timestamp: u64,
+    ) -> impl Future<Output = Result<RtResponse<D>>> + Send {
+        Recorder::get_stats_from(self, start_timestamp)
+    }
+
+    fn get_stats_120s(&self) -> impl Future<Output = Result<RtResponse<D>>> + Send {
+        Recorder::get_stats_120s(self)
+    }
+
+    fn get_stats_max(
+        &self,
+        max_entries: u64,
+    ) -> impl Future<Output = Result<RtResponse<D>>> + Send {
+        Recorder::get_stats_max(self, max_entries)
+    }
+}
+
+/// Pace at which a [`Replay`] serves entries
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pace {
+    /// Serve entries as fast as they are queried
+    Immediate,
+
+    /// Wait between entries as long as between their recording
+    RealTime,
+
+    /// Wait between entries as long as between their recording, divided by the factor
+    Accelerated(f64),
+}
+
+struct ReplayState {
+    entries: VecDeque<CassetteEntry>,
+    last_at_ms: Option<u64>,
+}
+
+/// Real time source serving the entries of a cassette, with the same API as the clients
+///
+/// Entries of the endpoint of `D` are served in recorded order, whichever query method is called.
+/// Recorded errors are replayed as errors, and queries fail once the cassette is exhausted.
+/// A recorded reset is consumed by a reset of the replay, and skipped by queries.
+pub struct Replay<D> {
+    state: Mutex<ReplayState>,
+    pace: Pace,
+    data: PhantomData<fn() -> D>,
+}
+
+impl<D: RealtimeData> Replay<D> {
+    /// Replay the cassette file at `path`
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Replay<D>> {
+        Replay::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    /// Replay a cassette read from `reader`, entries of other endpoints are skipped
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<Replay<D>> {
+        let mut entries = VecDeque::new();
+
+        for (number, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let entry: CassetteEntry = serde_json::from_str(&line)
+                .map_err(|e| anyhow!("invalid cassette entry at line {}: {}", number + 1, e))?;
+            if entry.endpoint == D::PATH {
+                entries.push_back(entry);
+            }
+        }
+
+        Ok(Replay::from_entries(entries))
+    }
+
+    pub fn from_entries<I: IntoIterator<Item = CassetteEntry>>(entries: I) -> Replay<D> {
+        Replay {
+            state: Mutex::new(ReplayState {
+                entries: entries.into_iter().collect(),
+                last_at_ms: None,
+            }),
+            pace: Pace::Immediate,
+            data: PhantomData,
+        }
+    }
+
+    /// Serve entries at `pace`, [`Pace::Immediate`] by default
+    pub fn with_pace(mut self, pace: Pace) -> Replay<D> {
+        self.pace = pace;
+        self
+    }
+
+    /// Number of entries not served yet
+    pub fn remaining(&self) -> usize {
+        self.state.lock().unwrap().entries.len()
+    }
+
+    fn serve(&self) -> impl Future<Output = Result<RtResponse<D>>> + Send {
+        let (entry, wait) = {
+            let mut state = self.state.lock().unwrap();
+            while state.entries.front().is_some_and(|entry| entry.reset) {
+                state.entries.pop_front();
+            }
+            let entry = state.entries.pop_front();
+            let elapsed_ms = match (&entry, state.last_at_ms) {
+                (Some(entry), Some(last_at_ms)) => entry.at_ms.saturating_sub(last_at_ms),
+                _ => 0,
+            };
+            if let Some(entry) = &entry {
+                state.last_at_ms = Some(entry.at_ms);
+            }
+
+            let wait = match self.pace {
+                Pace::Immediate => Duration::ZERO,
+                Pace::RealTime => Duration::from_millis(elapsed_ms),
+                Pace::Accelerated(factor) if factor > 0.0 => {
+                    Duration::from_secs_f64(elapsed_ms as f64 / 1000.0 / factor)
+                }
+                Pace::Accelerated(_) => Duration::ZERO,
+            };
+            (entry, wait)
+        };
+
+        async move {
+            if !wait.is_zero() {
+                tokio::time::sleep(wait).await;
+            }
+
+            match entry {
+                None => Err(anyhow!("cassette exhausted")),
+                Some(CassetteEntry {
+                    error: Some(error), ..
+                }) => Err(anyhow!(error)),
+                Some(CassetteEntry {
+                    response: Some(response),
+                    ..
+                }) => Ok(serde_json::from_value(response)?),
+                Some(_) => Err(anyhow!("cassette entry without response nor error")),
+            }
+        }
+    }
+}
+
+impl<D: RealtimeData> RealtimeClient for Replay<D> {
+    type Data = D;
+
+    fn reset_stats_consecutive(&mut self) {
+        let state = self.state.get_mut().unwrap();
+        if state.entries.front().is_some_and(|entry| entry.reset) {
+            state.entries.pop_front();
+        }
+    }
+
+    fn get_stats_consecutive(&mut self) -> impl Future<Output = Result<RtResponse<D>>> + Send {
+        self.serve()
+    }
+
+    fn get_stats_from(
+        &mut self,
+        _start_timestamp: u64,
+    ) -> impl Future<Output = Result<RtResponse<D>>> + Send {
+        self.serve()
+    }
+
+    fn get_stats_120s(&self) -> impl Future<Output = Result<RtResponse<D>>> + Send {
+        self.serve()
+    }
+
+    fn get_stats_max(
+        &self,
+        _max_entries: u64,
+    ) -> impl Future<Output = Result<RtResponse<D>>> + Send {
+        self.serve()
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
@@ -1,3 +1,4 @@
+use crate::realtime::Query;
 use anyhow::Result;
 use serde::de::DeserializeOwned;
 
@@ -6,7 +7,7 @@ pub struct CliObj {
     reqwest_client: reqwest::Client,
     timestamp: u64,
     service_id: String,
-    api_endpoint: &'static str,
+    api_endpoint: String,
 }
 
 pub trait TimestampHolder {
@@ -14,13 +15,13 @@ pub trait TimestampHolder {
 }
 
 impl CliObj {
-    pub fn new(api_key: &str, service_id: &str, endpoint: &'static str) -> Result<CliObj> {
+    pub fn new(api_key: &str, service_id: &str, endpoint: &str) -> Result<CliObj> {
         let client = CliObj {
             api_key: api_key.to_string(),
             reqwest_client: reqwest::Client::builder().build()?,
             timestamp: 0,
             service_id: service_id.to_string(),
-            api_endpoint: endpoint,
+            api_endpoint: endpoint.to_string(),
         };
 
         Ok(client)
@@ -30,50 +31,77 @@ impl CliObj {
         self.timestamp = 0;
     }
 
+    pub fn service_id(&self) -> &str {
+        &self.service_id
+    }
+
     pub async fn get_stats_consecutive<T: DeserializeOwned + TimestampHolder>(
         &mut self,
     ) -> Result<T> {
-        let rt_stats = self.get_stats_from::<T>(self.timestamp).await?;
-
-        self.timestamp = rt_stats.get_timestamp();
+        let (_, rt_stats) = self.query_raw::<T>(Query::Consecutive).await?;
 
         Ok(rt_stats)
     }
 
     pub async fn get_stats_from<T: DeserializeOwned>(&mut self, start_timestamp: u64) -> Result<T> {
-        let url = format!(
-            "{}/{}/ts/{}",
-            self.api_endpoint, self.service_id, start_timestamp
-        );
-
-        self.fetch_data(&url).await
+        self.fetch_data(Query::From(start_timestamp)).await
     }
 
     pub async fn get_stats_120s<T: DeserializeOwned>(&self) -> Result<T> {
-        let url = format!("{}/{}/ts/h", self.api_endpoint, self.service_id);
-
-        self.fetch_data(&url).await
+        self.fetch_data(Query::Last120s).await
     }
 
     pub async fn get_stats_max<T: DeserializeOwned>(&self, max_entries: u64) -> Result<T> {
-        let url = format!(
-            "{}/{}/ts/h/limit/{}",
-            self.api_endpoint, self.service_id, max_entries
-        );
+        self.fetch_data(Query::Max(max_entries)).await
+    }
+
+    /// Run `query`, returning the raw JSON body along with the parsed stats
+    pub async fn query_raw<T: DeserializeOwned + TimestampHolder>(
+        &mut self,
+        query: Query,
+    ) -> Result<(String, T)> {
+        let raw = self.fetch_raw(query).await?;
+        let rt_stats = serde_json::from_str::<T>(&raw)?;
 
-        self.fetch_data(&url).await
+        if query == Query::Consecutive {
+            self.timestamp = rt_stats.get_timestamp();
+        }
+
+        Ok((raw, rt_stats))
+    }
+
+    fn url(&self, query: Query) -> String {
+        match query {
+            Query::Consecutive => self.url(Query::From(self.timestamp)),
+            Query::From(start_timestamp) => format!(
+                "{}/{}/ts/{}",
+                self.api_endpoint, self.service_id, start_timestamp
+            ),
+            Query::Last120s => format!("{}/{}/ts/h", self.api_endpoint, self.service_id),
+            Query::Max(max_entries) => format!(
+                "{}/{}/ts/h/limit/{}",
+                self.api_endpoint, self.service_id, max_entries
+            ),
+        }
+    }
+
+    async fn fetch_data<T: DeserializeOwned>(&self, query: Query) -> Result<T> {
+        let raw = self.fetch_raw(query).await?;
+        let rt_stats = serde_json::from_str::<T>(&raw)?;
+
+        Ok(rt_stats)
     }
 
-    async fn fetch_data<T: DeserializeOwned>(&self, url: &str) -> Result<T> {
+    async fn fetch_raw(&self, query: Query) -> Result<String> {
         let response = self
             .reqwest_client
-            .get(url)
+            .get(self.url(query))
             .header("fastly-key", &self.api_key)
             .send()
             .await?;
 
-        let rt_stats = response.error_for_status()?.json::<T>().await?;
+        let raw = response.error_for_status()?.text().await?;
 
-        Ok(rt_stats)
+        Ok(raw)
     }
 }
@@ -0,0 +1,170 @@
+use crate::metric::{sorted, Metric, Metrics};
+use crate::origin::{OriginDataInSecond, OriginResponse, OriginStats};
+use crate::service::{ServiceDataInSecond, ServiceResponse, ServiceStats};
+use anyhow::{anyhow, Result};
+use std::fmt::Write as _;
+use std::io::Write;
+
+/// Value of the `scope` column of rows aggregated across POPs
+pub const AGGREGATED: &str = "aggregated";
+
+/// Streaming writer of real time data to CSV, with a row per second and scope
+///
+/// Columns are `recorded`, `scope`, either [`AGGREGATED`] or the name of a POP, `origin` for origin data,
+/// then the selected metrics of the catalog in the order given.
+/// The header is written on creation and every row as soon as its second is written,
+/// so `out` is better buffered, e.g. with a [`std::io::BufWriter`].
+///
+/// ```
+/// use fastly_rt::csv::CsvWriter;
+/// use fastly_rt::service::ServiceDataInSecond;
+///
+/// let mut csv = CsvWriter::service(Vec::new(), &["requests", "hits"]).unwrap();
+/// csv.write_service_second(&ServiceDataInSecond { recorded: 1, ..Default::default() }).unwrap();
+///
+/// let text = String::from_utf8(csv.into_inner().unwrap()).unwrap();
+/// assert_eq!(text, "recorded,scope,requests,hits\n1,aggregated,0,0\n");
+/// ```
+pub struct CsvWriter<W: Write, S: Metrics> {
+    out: W,
+    metrics: Vec<&'static Metric<S>>,
+    pops: bool,
+}
+
+impl<W: Write, S: Metrics> CsvWriter<W, S> {
+    /// Also write a row per POP after the aggregated row of each second
+    pub fn with_pops(mut self, pops: bool) -> CsvWriter<W, S> {
+        self.pops = pops;
+        self
+    }
+
+    /// Selected metrics, in the order of their columns
+    pub fn metrics(&self) -> &[&'static Metric<S>] {
+        &self.metrics
+    }
+
+    /// Flush and return the underlying writer
+    pub fn into_inner(mut self) -> Result<W> {
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    /// Metrics of the catalog named `names`, all of them when empty
+    fn new(out: W, names: &[&str], key_columns: &[&str]) -> Result<CsvWriter<W, S>> {
+        let metrics = if names.is_empty() {
+            S::METRICS.iter().collect()
+        } else {
+            names
+                .iter()
+                .map(|name| S::metric_info(name).ok_or_else(|| anyhow!("unknown metric {}", name)))
+                .collect::<Result<Vec<_>>>()?
+        };
+
+        let mut csv = CsvWriter {
+            out,
+            metrics,
+            pops: false,
+        };
+        let header: Vec<&str> = key_columns
+            .iter()
+            .copied()
+            .chain(csv.metrics.iter().map(|metric| metric.name))
+            .collect();
+        csv.out.write_all(line(&header).as_bytes())?;
+
+        Ok(csv)
+    }
+
+    fn write_row(&mut self, keys: &[&str], stats: &S) -> Result<()> {
+        let values: Vec<String> = self
+            .metrics
+            .iter()
+            .map(|metric| (metric.value)(stats).to_string())
+            .collect();
+        let fields: Vec<&str> = keys
+            .iter()
+            .copied()
+            .chain(values.iter().map(String::as_str))
+            .collect();
+
+        self.out.write_all(line(&fields).as_bytes())?;
+        Ok(())
+    }
+}
+
+impl<W: Write> CsvWriter<W, ServiceStats> {
+    /// Write service data to `out`, with the columns of the metrics named `metrics`, all of them when empty
+    pub fn service(out: W, metrics: &[&str]) -> Result<CsvWriter<W, ServiceStats>> {
+        CsvWriter::new(out, metrics, &["recorded", "scope"])
+    }
+
+    /// Write every second of `response`
+    pub fn write_service(&mut self, response: &ServiceResponse) -> Result<()> {
+        for data in &response.data {
+            self.write_service_second(data)?;
+        }
+        Ok(())
+    }
+
+    pub fn write_service_second(&mut self, data: &ServiceDataInSecond) -> Result<()> {
+        let recorded = data.recorded.to_string();
+        self.write_row(&[&recorded, AGGREGATED], &data.aggregated)?;
+
+        if self.pops {
+            for (pop_name, stats) in sorted(&data.datacenter) {
+                self.write_row(&[&recorded, pop_name], stats)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<W: Write> CsvWriter<W, OriginStats> {
+    /// Write origin data to `out`, with the columns of the metrics named `metrics`, all of them when empty
+    pub fn origins(out: W, metrics: &[&str]) -> Result<CsvWriter<W, OriginStats>> {
+        CsvWriter::new(out, metrics, &["recorded", "scope", "origin"])
+    }
+
+    /// Write every second of `response`
+    pub fn write_origins(&mut self, response: &OriginResponse) -> Result<()> {
+        for data in &response.data {
+            self.write_origin_second(data)?;
+        }
+        Ok(())
+    }
+
+    pub fn write_origin_second(&mut self, data: &OriginDataInSecond) -> Result<()> {
+        let recorded = data.recorded.to_string();
+        for (origin_name, stats) in sorted(&data.aggregated) {
+            self.write_row(&[&recorded, AGGREGATED, origin_name], stats)?;
+        }
+
+        if self.pops {
+            for (pop_name, origins) in sorted(&data.datacenter) {
+                for (origin_name, stats) in sorted(origins) {
+                    self.write_row(&[&recorded, pop_name, origin_name], stats)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Join fields into a line, quoting those with a comma, quote or line break
+fn line(fields: &[&str]) -> String {
+    let mut line = String::new();
+    for (index, field) in fields.iter().enumerate() {
+        if index > 0 {
+            line.push(',');
+        }
+        if field.contains([',', '"', '\n', '\r']) {
+            write!(line, "\"{}\"", field.replace('"', "\"\"")).ok();
+        } else {
+            line.push_str(field);
+        }
+    }
+    line.push('\n');
+    line
+}
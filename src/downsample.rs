@@ -0,0 +1,220 @@
+use crate::origin::OriginDataInSecond;
+use crate::realtime::RtResponse;
+use crate::service::ServiceDataInSecond;
+use crate::timeseries::Scoped;
+use std::collections::{BTreeMap, HashSet};
+
+pub const MINUTE: u64 = 60;
+
+pub const HOUR: u64 = 3600;
+
+/// Seconds of data which can be summed up into a window
+pub trait Mergeable: Scoped + Default {
+    /// Add the measurements of `other` to this one, counters, times and histograms alike
+    fn merge(&mut self, other: &Self);
+}
+
+impl Mergeable for ServiceDataInSecond {
+    fn merge(&mut self, other: &ServiceDataInSecond) {
+        ServiceDataInSecond::merge(self, other);
+    }
+}
+
+impl Mergeable for OriginDataInSecond {
+    fn merge(&mut self, other: &OriginDataInSecond) {
+        OriginDataInSecond::merge(self, other);
+    }
+}
+
+/// Sum of the seconds of `start..start + resolution`
+#[derive(Debug, Clone)]
+pub struct Window<D> {
+    pub start: u64,
+
+    /// Length of the window in seconds
+    pub resolution: u64,
+
+    /// Sum of the seconds received, `recorded` being the latest of them
+    pub data: D,
+
+    /// Number of distinct seconds received
+    pub seconds: u64,
+}
+
+impl<D> Window<D> {
+    pub fn end(&self) -> u64 {
+        self.start + self.resolution
+    }
+
+    /// Whether every second of the window was received
+    pub fn is_complete(&self) -> bool {
+        self.seconds == self.resolution
+    }
+
+    /// Share of the seconds of the window which were received, from 0 to 1
+    pub fn coverage(&self) -> f64 {
+        self.seconds as f64 / self.resolution as f64
+    }
+}
+
+/// Window being filled
+struct Open<D> {
+    data: D,
+    received: HashSet<u64>,
+}
+
+/// Windows of one resolution
+struct Level<D> {
+    resolution: u64,
+    open: BTreeMap<u64, Open<D>>,
+
+    /// End of the latest window emitted, seconds before it are late
+    closed_until: u64,
+}
+
+/// Sums a stream of seconds into windows aligned on multiples of their resolution, e.g. minutes and hours
+///
+/// A window is emitted once a second `lateness` seconds after its last second has been received, so seconds
+/// coming out of order by up to `lateness` seconds are still counted. Seconds of windows already emitted
+/// are late, and dropped. Seconds received twice, e.g. from overlapping polls, are counted once.
+/// Missing seconds are counted as no traffic, see [`Window::coverage`].
+///
+/// Windows add up counters and times, keep the largest value of gauges, and merge histograms.
+///
+/// ```
+/// use fastly_rt::downsample::{Downsampler, MINUTE};
+/// use fastly_rt::service::ServiceDataInSecond;
+///
+/// let mut downsampler = Downsampler::<ServiceDataInSecond>::new(&[MINUTE]);
+/// let mut windows = Vec::new();
+/// for recorded in 0..=60 {
+///     let mut data = ServiceDataInSecond { recorded, ..Default::default() };
+///     data.aggregated.requests = 1;
+///     windows.extend(downsampler.push(&data));
+/// }
+///
+/// assert_eq!(windows.len(), 1);
+/// assert_eq!(windows[0].data.aggregated.requests, 60);
+/// assert!(windows[0].is_complete());
+/// ```
+pub struct Downsampler<D: Mergeable> {
+    levels: Vec<Level<D>>,
+    lateness: u64,
+    latest: u64,
+    late: u64,
+    duplicates: u64,
+}
+
+impl<D: Mergeable> Downsampler<D> {
+    /// Windows of each of `resolutions`, in seconds
+    pub fn new(resolutions: &[u64]) -> Downsampler<D> {
+        Downsampler {
+            levels: resolutions
+                .iter()
+                .map(|resolution| Level {
+                    resolution: (*resolution).max(1),
+                    open: BTreeMap::new(),
+                    closed_until: 0,
+                })
+                .collect(),
+            lateness: 0,
+            latest: 0,
+            late: 0,
+            duplicates: 0,
+        }
+    }
+
+    /// Minimum wait for out of order seconds, raised to `aggregate_delay` by [`Downsampler::push_response`]
+    pub fn with_lateness(mut self, lateness: u64) -> Downsampler<D> {
+        self.lateness = lateness;
+        self
+    }
+
+    /// Add a second, returns the windows it completes, by end then resolution
+    pub fn push(&mut self, data: &D) -> Vec<Window<D>> {
+        self.add(data);
+        self.close(false)
+    }
+
+    /// Add the seconds of `response`, waiting at least its `aggregate_delay` for out of order seconds
+    pub fn push_response(&mut self, response: &RtResponse<D>) -> Vec<Window<D>> {
+        let lateness = self.lateness.max(response.aggregate_delay);
+        for data in &response.data {
+            self.add(data);
+        }
+        self.close_with(lateness, false)
+    }
+
+    /// Emit every window being filled, e.g. on shutdown
+    pub fn flush(&mut self) -> Vec<Window<D>> {
+        self.close(true)
+    }
+
+    /// Number of seconds dropped because a window of theirs was already emitted
+    pub fn late_seconds(&self) -> u64 {
+        self.late
+    }
+
+    /// Number of seconds received more than once
+    pub fn duplicate_seconds(&self) -> u64 {
+        self.duplicates
+    }
+
+    fn add(&mut self, data: &D) {
+        let recorded = data.recorded();
+        self.latest = self.latest.max(recorded);
+
+        let mut late = false;
+        let mut duplicate = false;
+        for level in &mut self.levels {
+            if recorded < level.closed_until {
+                late = true;
+                continue;
+            }
+
+            let start = recorded / level.resolution * level.resolution;
+            let open = level.open.entry(start).or_insert_with(|| Open {
+                data: D::default(),
+                received: HashSet::new(),
+            });
+            if open.received.insert(recorded) {
+                open.data.merge(data);
+            } else {
+                duplicate = true;
+            }
+        }
+
+        self.late += u64::from(late);
+        self.duplicates += u64::from(duplicate);
+    }
+
+    fn close(&mut self, all: bool) -> Vec<Window<D>> {
+        self.close_with(self.lateness, all)
+    }
+
+    fn close_with(&mut self, lateness: u64, all: bool) -> Vec<Window<D>> {
+        let mut windows = Vec::new();
+
+        for level in &mut self.levels {
+            while let Some(entry) = level.open.first_entry() {
+                let end = entry.key() + level.resolution;
+                if !all && self.latest < end - 1 + lateness {
+                    break;
+                }
+
+                let start = *entry.key();
+                let open = entry.remove();
+                level.closed_until = end;
+                windows.push(Window {
+                    start,
+                    resolution: level.resolution,
+                    data: open.data,
+                    seconds: open.received.len() as u64,
+                });
+            }
+        }
+
+        windows.sort_by_key(|window| (window.end(), window.resolution));
+        windows
+    }
+}
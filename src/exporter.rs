@@ -0,0 +1,488 @@
+use crate::histogram::LatencyHistogram;
+pub use crate::metric::MetricFilter;
+use crate::metric::{Metric, MetricKind, Metrics};
+use crate::openmetrics::{format_value, write_header, write_sample, Format};
+use crate::origin::{OriginClient, OriginDataInSecond, OriginStats};
+use crate::realtime::{RealtimeClient, RtClient};
+use crate::service::{ServiceClient, ServiceDataInSecond, ServiceStats};
+use anyhow::{Context, Result};
+use hyper::header::{HeaderValue, CONTENT_TYPE};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+/// Prefix of the names of exported metrics
+pub const PREFIX: &str = "fastly_rt";
+
+/// Configuration of an [`Exporter`], usually read from a TOML file
+///
+/// ```toml
+/// listen = "0.0.0.0:9150"
+/// api_key = "..."
+/// poll_interval_ms = 1000
+///
+/// [[service]]
+/// id = "SU1Z0isxPaozGVKXdv0eY"
+/// name = "www"
+/// origins = true
+///
+/// [filter]
+/// deny = ["compute_*", "imgvideo*"]
+/// pop_allow = ["requests", "status_*", "miss_histogram"]
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExporterConfig {
+    /// Address `/metrics` is served on
+    #[serde(default = "ExporterConfig::default_listen")]
+    pub listen: SocketAddr,
+
+    /// Fastly API key, the `FASTLY_API_KEY` environment variable is used by the binary when empty
+    #[serde(default)]
+    pub api_key: String,
+
+    /// Base URL of the real time API, see [`RtClient::with_base_url`]
+    #[serde(default = "ExporterConfig::default_base_url")]
+    pub base_url: String,
+
+    /// Pause between two polls of an endpoint
+    #[serde(default = "ExporterConfig::default_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+
+    /// Polled services
+    #[serde(default, rename = "service")]
+    pub services: Vec<ServiceTarget>,
+
+    #[serde(default)]
+    pub filter: MetricFilter,
+}
+
+impl ExporterConfig {
+    /// Parse a TOML configuration
+    pub fn from_toml(toml: &str) -> Result<ExporterConfig> {
+        Ok(toml::from_str(toml)?)
+    }
+
+    /// Read the TOML configuration file at `path`
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<ExporterConfig> {
+        let path = path.as_ref();
+        let toml = std::fs::read_to_string(path).with_context(|| format!("{}", path.display()))?;
+        ExporterConfig::from_toml(&toml).with_context(|| format!("{}", path.display()))
+    }
+
+    fn default_listen() -> SocketAddr {
+        SocketAddr::from(([0, 0, 0, 0], 9150))
+    }
+
+    fn default_base_url() -> String {
+        RtClient::<ServiceDataInSecond>::BASE_URL.to_string()
+    }
+
+    fn default_poll_interval_ms() -> u64 {
+        1000
+    }
+}
+
+/// A polled service
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ServiceTarget {
+    /// Service ID
+    pub id: String,
+
+    /// Value of the `service` label, the service ID when not set
+    #[serde(default)]
+    pub name: Option<String>,
+
+    /// Poll real time origin metrics too
+    #[serde(default)]
+    pub origins: bool,
+}
+
+impl ServiceTarget {
+    /// Value of the `service` label
+    pub fn label(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.id)
+    }
+}
+
+/// Label names and values of a series, in the order `service`, `pop`, `origin`
+type Labels = Vec<(&'static str, String)>;
+
+struct Family<V> {
+    help: &'static str,
+    series: BTreeMap<Labels, V>,
+}
+
+impl<V: Default> Family<V> {
+    fn new(help: &'static str) -> Family<V> {
+        Family {
+            help,
+            series: BTreeMap::new(),
+        }
+    }
+
+    fn get(&mut self, labels: Labels) -> &mut V {
+        self.series.entry(labels).or_default()
+    }
+}
+
+/// Monotonic counters accumulated from polled real time data, rendered in Prometheus text format
+///
+/// Every field of a stats becomes a counter named `fastly_rt_{field}_total`, `fastly_rt_origin_{field}_total`
+/// for origins, and `miss_histogram` the `fastly_rt_miss_latency_seconds` histogram.
+/// Gauges such as `compute_ram_used` become a gauge `fastly_rt_{field}` holding the last value.
+pub struct Registry {
+    filter: MetricFilter,
+    counters: BTreeMap<String, Family<f64>>,
+    gauges: BTreeMap<String, Family<f64>>,
+    histograms: BTreeMap<String, Family<LatencyHistogram>>,
+}
+
+impl Registry {
+    pub fn new(filter: MetricFilter) -> Registry {
+        Registry {
+            filter,
+            counters: BTreeMap::new(),
+            gauges: BTreeMap::new(),
+            histograms: BTreeMap::new(),
+        }
+    }
+
+    /// Add the measurements of one second of `service`
+    pub fn add_service(&mut self, service: &str, data: &ServiceDataInSecond) {
+        let service_labels = || vec![("service", service.to_string())];
+
+        for metric in ServiceStats::METRICS {
+            if self.filter.allows_pop(metric.name) {
+                for (pop_name, stats) in &data.datacenter {
+                    self.add_metric("", metric, stats, pop_labels(service, pop_name));
+                }
+            } else if self.filter.allows(metric.name) {
+                self.add_metric("", metric, &data.aggregated, service_labels());
+            }
+        }
+
+        let name = format!("{}_miss_latency_seconds", PREFIX);
+        let help = "Latency of requests to origin";
+        if self.filter.allows_pop("miss_histogram") {
+            for (pop_name, stats) in &data.datacenter {
+                self.histograms
+                    .entry(name.clone())
+                    .or_insert_with(|| Family::new(help))
+                    .get(pop_labels(service, pop_name))
+                    .merge(&stats.miss_histogram);
+            }
+        } else if self.filter.allows("miss_histogram") {
+            self.histograms
+                .entry(name)
+                .or_insert_with(|| Family::new(help))
+                .get(service_labels())
+                .merge(&data.aggregated.miss_histogram);
+        }
+    }
+
+    /// Add the measurements of one second of the origins of `service`
+    pub fn add_origins(&mut self, service: &str, data: &OriginDataInSecond) {
+        for metric in OriginStats::METRICS {
+            let name = format!("origin_{}", metric.name);
+
+            if self.filter.allows_pop(&name) {
+                for (pop_name, origins) in &data.datacenter {
+                    for (origin_name, stats) in origins {
+                        let mut labels = pop_labels(service, pop_name);
+                        labels.push(("origin", origin_name.clone()));
+                        self.add_metric("origin_", metric, stats, labels);
+                    }
+                }
+            } else if self.filter.allows(&name) {
+                for (origin_name, stats) in &data.aggregated {
+                    let labels = vec![
+                        ("service", service.to_string()),
+                        ("origin", origin_name.clone()),
+                    ];
+                    self.add_metric("origin_", metric, stats, labels);
+                }
+            }
+        }
+    }
+
+    /// Record the outcome of a poll of `endpoint` (`service` or `origin`) of `service`,
+    /// with the timestamp of the response when it succeeded
+    pub fn record_poll(&mut self, service: &str, endpoint: &str, timestamp: Option<u64>) {
+        let labels = || {
+            vec![
+                ("service", service.to_string()),
+                ("endpoint", endpoint.to_string()),
+            ]
+        };
+
+        *self
+            .counters
+            .entry(format!("{}_exporter_polls_total", PREFIX))
+            .or_insert_with(|| Family::new("Number of polls of the real time API"))
+            .get(labels()) += 1.0;
+
+        let errors = self
+            .counters
+            .entry(format!("{}_exporter_poll_errors_total", PREFIX))
+            .or_insert_with(|| Family::new("Number of failed polls of the real time API"))
+            .get(labels());
+
+        match timestamp {
+            Some(timestamp) => {
+                *self
+                    .gauges
+                    .entry(format!("{}_exporter_timestamp_seconds", PREFIX))
+                    .or_insert_with(|| {
+                        Family::new("Timestamp returned by the last successful poll")
+                    })
+                    .get(labels()) = timestamp as f64
+            }
+            None => *errors += 1.0,
+        }
+    }
+
+    /// Render every metric in Prometheus text format
+    pub fn render(&self) -> String {
+        let mut text = String::new();
+
+        for (name, family) in &self.counters {
+            write_header(&mut text, name, "counter", family.help);
+            for (labels, value) in &family.series {
+                write_series(&mut text, name, labels, None, *value);
+            }
+        }
+
+        for (name, family) in &self.gauges {
+            write_header(&mut text, name, "gauge", family.help);
+            for (labels, value) in &family.series {
+                write_series(&mut text, name, labels, None, *value);
+            }
+        }
+
+        for (name, family) in &self.histograms {
+            write_header(&mut text, name, "histogram", family.help);
+            for (labels, histogram) in &family.series {
+                let bucket_name = format!("{}_bucket", name);
+                for (le, count) in histogram.cumulative_buckets() {
+                    let le = format_value(le);
+                    write_series(&mut text, &bucket_name, labels, Some(&le), count as f64);
+                }
+                let sum = histogram.sum_ms() / 1000.0;
+                write_series(&mut text, &format!("{}_sum", name), labels, None, sum);
+                let count = histogram.total() as f64;
+                write_series(&mut text, &format!("{}_count", name), labels, None, count);
+            }
+        }
+
+        text
+    }
+
+    fn add_metric<S>(&mut self, prefix: &str, metric: &Metric<S>, stats: &S, labels: Labels) {
+        let value = (metric.value)(stats);
+
+        if metric.kind == MetricKind::Gauge {
+            let name = format!("{}_{}{}", PREFIX, prefix, metric.name);
+            *self
+                .gauges
+                .entry(name)
+                .or_insert_with(|| Family::new(metric.help))
+                .get(labels) = value;
+            return;
+        }
+
+        let name = format!("{}_{}{}_total", PREFIX, prefix, metric.name);
+        *self
+            .counters
+            .entry(name)
+            .or_insert_with(|| Family::new(metric.help))
+            .get(labels) += value;
+    }
+}
+
+fn pop_labels(service: &str, pop_name: &str) -> Labels {
+    vec![
+        ("service", service.to_string()),
+        ("pop", pop_name.to_string()),
+    ]
+}
+
+fn write_series(text: &mut String, name: &str, labels: &Labels, le: Option<&str>, value: f64) {
+    let mut labels: Vec<(&str, &str)> = labels
+        .iter()
+        .map(|(label, label_value)| (*label, label_value.as_str()))
+        .collect();
+    labels.extend(le.map(|le| ("le", le)));
+
+    write_sample(text, name, &labels, value, None);
+}
+
+/// Prometheus exporter polling the real time API of configured services and serving `/metrics`
+///
+/// Polling and serving stop when the exporter is dropped.
+///
+/// ```no_run
+/// use fastly_rt::exporter::{Exporter, ExporterConfig};
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let config = ExporterConfig::load("exporter.toml").unwrap();
+///     let exporter = Exporter::start(config).await.unwrap();
+///     println!("serving http://{}/metrics", exporter.local_addr());
+///
+///     tokio::signal::ctrl_c().await.unwrap();
+/// }
+/// ```
+pub struct Exporter {
+    addr: SocketAddr,
+    registry: Arc<Mutex<Registry>>,
+    pollers: Vec<JoinHandle<()>>,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl Exporter {
+    /// Start polling the services of `config` and serving `/metrics` on `config.listen`
+    pub async fn start(config: ExporterConfig) -> Result<Exporter> {
+        let registry = Arc::new(Mutex::new(Registry::new(config.filter.clone())));
+        let interval = Duration::from_millis(config.poll_interval_ms);
+
+        let mut pollers = Vec::new();
+        for service in &config.services {
+            let label = service.label().to_string();
+
+            let client =
+                ServiceClient::with_base_url(&config.api_key, &service.id, &config.base_url)?;
+            pollers.push(tokio::spawn(poll(
+                client,
+                interval,
+                registry.clone(),
+                label.clone(),
+                "service",
+                Registry::add_service,
+            )));
+
+            if service.origins {
+                let client =
+                    OriginClient::with_base_url(&config.api_key, &service.id, &config.base_url)?;
+                pollers.push(tokio::spawn(poll(
+                    client,
+                    interval,
+                    registry.clone(),
+                    label,
+                    "origin",
+                    Registry::add_origins,
+                )));
+            }
+        }
+
+        let service_registry = registry.clone();
+        let make_service = make_service_fn(move |_| {
+            let registry = service_registry.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| handle(registry.clone(), request)))
+            }
+        });
+
+        let server = Server::try_bind(&config.listen)?.serve(make_service);
+        let addr = server.local_addr();
+        let (shutdown, shutdown_signal) = oneshot::channel::<()>();
+        tokio::spawn(server.with_graceful_shutdown(async {
+            shutdown_signal.await.ok();
+        }));
+
+        Ok(Exporter {
+            addr,
+            registry,
+            pollers,
+            shutdown: Some(shutdown),
+        })
+    }
+
+    /// Address `/metrics` is served on
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Render the metrics accumulated so far, as served on `/metrics`
+    pub fn render(&self) -> String {
+        self.registry.lock().unwrap().render()
+    }
+}
+
+impl Drop for Exporter {
+    fn drop(&mut self) {
+        for poller in &self.pollers {
+            poller.abort();
+        }
+        if let Some(shutdown) = self.shutdown.take() {
+            shutdown.send(()).ok();
+        }
+    }
+}
+
+async fn poll<C, F>(
+    mut client: C,
+    interval: Duration,
+    registry: Arc<Mutex<Registry>>,
+    service: String,
+    endpoint: &'static str,
+    add: F,
+) where
+    C: RealtimeClient,
+    F: Fn(&mut Registry, &str, &C::Data),
+{
+    loop {
+        let result = client.get_stats_consecutive().await;
+
+        {
+            let mut registry = registry.lock().unwrap();
+            match result {
+                Ok(response) => {
+                    for data in &response.data {
+                        add(&mut registry, &service, data);
+                    }
+                    registry.record_poll(&service, endpoint, Some(response.timestamp));
+                }
+                Err(_) => registry.record_poll(&service, endpoint, None),
+            }
+        }
+
+        tokio::time::sleep(interval).await;
+    }
+}
+
+async fn handle(
+    registry: Arc<Mutex<Registry>>,
+    request: Request<Body>,
+) -> Result<Response<Body>, Infallible> {
+    let response = match (request.method(), request.uri().path()) {
+        (&Method::GET, "/metrics") => {
+            let text = registry.lock().unwrap().render();
+            let mut response = Response::new(Body::from(text));
+            response.headers_mut().insert(
+                CONTENT_TYPE,
+                HeaderValue::from_static(Format::Prometheus.content_type()),
+            );
+            response
+        }
+        (&Method::GET, _) => status_response(StatusCode::NOT_FOUND),
+        _ => status_response(StatusCode::METHOD_NOT_ALLOWED),
+    };
+
+    Ok(response)
+}
+
+fn status_response(status: StatusCode) -> Response<Body> {
+    let mut response = Response::new(Body::from(status.canonical_reason().unwrap_or("")));
+    *response.status_mut() = status;
+    response
+}
@@ -0,0 +1,299 @@
+use crate::synth::{Generator, SynthConfig};
+use anyhow::Result;
+use hyper::header::{HeaderValue, CONTENT_TYPE, RETRY_AFTER};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::oneshot;
+
+/// Configuration of a [`FakeServer`]
+#[derive(Debug, Clone)]
+pub struct FakeConfig {
+    /// API key expected in the `fastly-key` header of every request
+    pub api_key: String,
+
+    /// Reported `AggregateDelay`, the latest available second is the current time minus this delay
+    pub aggregate_delay: u64,
+
+    /// Generated traffic, the seed being mixed with the service ID so services differ
+    pub traffic: SynthConfig,
+
+    /// Delay added to every response
+    pub response_delay: Duration,
+
+    /// Fail one request out of `error_every` with `error_status`, 0 to never fail
+    pub error_every: u64,
+
+    pub error_status: u16,
+}
+
+impl Default for FakeConfig {
+    fn default() -> FakeConfig {
+        FakeConfig {
+            api_key: "fake-key".to_string(),
+            aggregate_delay: 3,
+            traffic: SynthConfig::default(),
+            response_delay: Duration::ZERO,
+            error_every: 0,
+            error_status: 503,
+        }
+    }
+}
+
+/// Fault injected into the response of one request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// Respond with an error status, e.g. 401, 429 or 503
+    Status(u16),
+
+    /// Respond normally, after a delay
+    Delay(Duration),
+}
+
+struct State {
+    config: FakeConfig,
+    faults: Mutex<VecDeque<Fault>>,
+    requests: AtomicU64,
+}
+
+/// Local server implementing the real time endpoints of Fastly, for testing without Fastly
+///
+/// `/v1/channel/{service_id}/ts/{timestamp}`, `/ts/h` and `/ts/h/limit/{max_entries}` are served,
+/// and the same under `/v1/origins`, with per second data of a [`Generator`].
+/// The server is shut down when dropped.
+///
+/// ```
+/// use fastly_rt::fake::{FakeConfig, FakeServer};
+/// use fastly_rt::service::ServiceClient;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let config = FakeConfig::default();
+///     let server = FakeServer::start(config.clone()).await.unwrap();
+///     let rt = ServiceClient::with_base_url(&config.api_key, "sid", &server.base_url()).unwrap();
+///
+///     assert_eq!(rt.get_stats_max(3).await.unwrap().data.len(), 3);
+/// }
+/// ```
+pub struct FakeServer {
+    addr: SocketAddr,
+    state: Arc<State>,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl FakeServer {
+    /// Number of seconds of data kept by Fastly
+    pub const MAX_ENTRIES: u64 = 120;
+
+    /// Start a server on a free port of localhost
+    pub async fn start(config: FakeConfig) -> Result<FakeServer> {
+        FakeServer::bind(SocketAddr::from(([127, 0, 0, 1], 0)), config).await
+    }
+
+    /// Start a server listening on `addr`
+    pub async fn bind(addr: SocketAddr, config: FakeConfig) -> Result<FakeServer> {
+        let state = Arc::new(State {
+            config,
+            faults: Mutex::new(VecDeque::new()),
+            requests: AtomicU64::new(0),
+        });
+
+        let service_state = state.clone();
+        let make_service = make_service_fn(move |_| {
+            let state = service_state.clone();
+            async move { Ok::<_, Infallible>(service_fn(move |request| handle(state.clone(), request))) }
+        });
+
+        let server = Server::try_bind(&addr)?.serve(make_service);
+        let addr = server.local_addr();
+        let (shutdown, shutdown_signal) = oneshot::channel::<()>();
+        tokio::spawn(server.with_graceful_shutdown(async {
+            shutdown_signal.await.ok();
+        }));
+
+        Ok(FakeServer {
+            addr,
+            state,
+            shutdown: Some(shutdown),
+        })
+    }
+
+    /// Address the server listens on
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Base URL to give to [`crate::realtime::RtClient::with_base_url`]
+    pub fn base_url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Apply `fault` to one of the next requests, faults are applied in the order they are injected
+    pub fn inject(&self, fault: Fault) {
+        self.state.faults.lock().unwrap().push_back(fault);
+    }
+
+    /// Number of requests received so far
+    pub fn requests(&self) -> u64 {
+        self.state.requests.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for FakeServer {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            shutdown.send(()).ok();
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Envelope<D> {
+    #[serde(rename = "AggregateDelay")]
+    aggregate_delay: u64,
+
+    #[serde(rename = "Data")]
+    data: Vec<D>,
+
+    #[serde(rename = "Timestamp")]
+    timestamp: u64,
+}
+
+async fn handle(state: Arc<State>, request: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let config = &state.config;
+    let count = state.requests.fetch_add(1, Ordering::SeqCst) + 1;
+    let fault = state.faults.lock().unwrap().pop_front();
+
+    let mut delay = config.response_delay;
+    let mut error_status = None;
+    match fault {
+        Some(Fault::Delay(duration)) => delay += duration,
+        Some(Fault::Status(status)) => error_status = Some(status),
+        None if config.error_every > 0 && count.is_multiple_of(config.error_every) => {
+            error_status = Some(config.error_status)
+        }
+        None => {}
+    }
+
+    if !delay.is_zero() {
+        tokio::time::sleep(delay).await;
+    }
+
+    let authorized = request
+        .headers()
+        .get("fastly-key")
+        .map(|key| key.as_bytes() == config.api_key.as_bytes())
+        .unwrap_or(false);
+
+    let response = if !authorized {
+        error_response(StatusCode::UNAUTHORIZED)
+    } else if let Some(status) = error_status {
+        error_response(StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR))
+    } else if request.method() != Method::GET {
+        error_response(StatusCode::METHOD_NOT_ALLOWED)
+    } else {
+        route(config, request.uri().path())
+    };
+
+    Ok(response)
+}
+
+fn route(config: &FakeConfig, path: &str) -> Response<Body> {
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    let latest = now().saturating_sub(config.aggregate_delay);
+
+    let (endpoint, service_id, range) = match segments.as_slice() {
+        ["v1", endpoint, service_id, "ts", rest @ ..] if !service_id.is_empty() => {
+            (*endpoint, *service_id, requested_range(rest, latest))
+        }
+        _ => return error_response(StatusCode::NOT_FOUND),
+    };
+
+    let (first, timestamp) = match range {
+        Some(range) => range,
+        None => return error_response(StatusCode::BAD_REQUEST),
+    };
+    let seconds = first..latest.min(timestamp.saturating_sub(1)) + 1;
+
+    let mut traffic = config.traffic.clone();
+    traffic.seed = service_id.bytes().fold(traffic.seed, |seed, byte| {
+        seed.wrapping_mul(31).wrapping_add(byte as u64)
+    });
+    let generator = Generator::new(traffic);
+
+    match endpoint {
+        "channel" => json_response(&Envelope {
+            aggregate_delay: config.aggregate_delay,
+            data: generator.service_seconds(seconds).collect(),
+            timestamp,
+        }),
+        "origins" => json_response(&Envelope {
+            aggregate_delay: config.aggregate_delay,
+            data: generator.origin_seconds(seconds).collect(),
+            timestamp,
+        }),
+        _ => error_response(StatusCode::NOT_FOUND),
+    }
+}
+
+/// Returns (first second, timestamp to use for the next request) of the path after `/ts/`
+fn requested_range(rest: &[&str], latest: u64) -> Option<(u64, u64)> {
+    let oldest = (latest + 1).saturating_sub(FakeServer::MAX_ENTRIES);
+
+    match rest {
+        ["h"] => Some((oldest, latest + 1)),
+        ["h", "limit", max_entries] => {
+            let max_entries = max_entries
+                .parse::<u64>()
+                .ok()?
+                .min(FakeServer::MAX_ENTRIES);
+            Some(((latest + 1).saturating_sub(max_entries), latest + 1))
+        }
+        ["0"] => Some((latest, latest + 1)),
+        [start_timestamp] => {
+            let start_timestamp = start_timestamp.parse::<u64>().ok()?;
+            Some((start_timestamp.max(oldest), start_timestamp.max(latest + 1)))
+        }
+        _ => None,
+    }
+}
+
+fn json_response<T: Serialize>(body: &T) -> Response<Body> {
+    match serde_json::to_vec(body) {
+        Ok(body) => {
+            let mut response = Response::new(Body::from(body));
+            response
+                .headers_mut()
+                .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+            response
+        }
+        Err(_) => error_response(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+fn error_response(status: StatusCode) -> Response<Body> {
+    let message = status.canonical_reason().unwrap_or("Error");
+    let mut response = json_response(&HashMap::from([("msg", message)]));
+    *response.status_mut() = status;
+
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        response
+            .headers_mut()
+            .insert(RETRY_AFTER, HeaderValue::from_static("1"));
+    }
+
+    response
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
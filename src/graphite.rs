@@ -0,0 +1,171 @@
+use crate::metric::{sorted, MetricFilter, Metrics};
+use crate::origin::OriginDataInSecond;
+use crate::service::ServiceDataInSecond;
+use anyhow::Result;
+use std::fmt::Write as _;
+use std::io::Write;
+use std::net::{TcpStream, ToSocketAddrs};
+
+/// Serializer of real time data to the [Graphite plaintext protocol](https://graphite.readthedocs.io/en/latest/feeding-carbon.html)
+///
+/// Paths are `{prefix}.{service}.{metric}` for service data, `{prefix}.{service}.origins.{origin}.{metric}`
+/// for origin data, and `{prefix}.{service}.pops.{pop}. ...` for metrics allowed by POP by the filter.
+/// Dots and spaces of service, POP and origin names are replaced by `_`.
+///
+/// ```
+/// use fastly_rt::graphite::Plaintext;
+/// use fastly_rt::service::ServiceDataInSecond;
+///
+/// let data = ServiceDataInSecond { recorded: 1, ..Default::default() };
+/// let lines = Plaintext::new().service("www", &data);
+///
+/// assert!(lines.contains("fastly_rt.www.requests 0 1\n"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Plaintext {
+    prefix: String,
+    filter: MetricFilter,
+}
+
+impl Plaintext {
+    pub fn new() -> Plaintext {
+        Plaintext {
+            prefix: "fastly_rt".to_string(),
+            filter: MetricFilter::default(),
+        }
+    }
+
+    /// First node of every path, none when empty
+    pub fn with_prefix(mut self, prefix: &str) -> Plaintext {
+        self.prefix = prefix.to_string();
+        self
+    }
+
+    /// Select the written metrics, and those written by POP
+    pub fn with_filter(mut self, filter: MetricFilter) -> Plaintext {
+        self.filter = filter;
+        self
+    }
+
+    /// Lines of one second of data of `service`
+    pub fn service(&self, service: &str, data: &ServiceDataInSecond) -> String {
+        let mut lines = String::new();
+        let base = self.path(&[service]);
+
+        for (metric, value) in data.aggregated.metrics() {
+            if self.filter.allows(metric.name) && !self.filter.allows_pop(metric.name) {
+                push_line(&mut lines, &base, metric.name, value, data.recorded);
+            }
+        }
+
+        for (pop_name, stats) in sorted(&data.datacenter) {
+            let base = self.path(&[service, "pops", pop_name]);
+            for (metric, value) in stats.metrics() {
+                if self.filter.allows_pop(metric.name) {
+                    push_line(&mut lines, &base, metric.name, value, data.recorded);
+                }
+            }
+        }
+
+        lines
+    }
+
+    /// Lines of one second of data of the origins of `service`
+    pub fn origins(&self, service: &str, data: &OriginDataInSecond) -> String {
+        let mut lines = String::new();
+
+        for (origin_name, stats) in sorted(&data.aggregated) {
+            let base = self.path(&[service, "origins", origin_name]);
+            for (metric, value) in stats.metrics() {
+                let name = format!("origin_{}", metric.name);
+                if self.filter.allows(&name) && !self.filter.allows_pop(&name) {
+                    push_line(&mut lines, &base, metric.name, value, data.recorded);
+                }
+            }
+        }
+
+        for (pop_name, origins) in sorted(&data.datacenter) {
+            for (origin_name, stats) in sorted(origins) {
+                let base = self.path(&[service, "pops", pop_name, "origins", origin_name]);
+                for (metric, value) in stats.metrics() {
+                    if self.filter.allows_pop(&format!("origin_{}", metric.name)) {
+                        push_line(&mut lines, &base, metric.name, value, data.recorded);
+                    }
+                }
+            }
+        }
+
+        lines
+    }
+
+    fn path(&self, nodes: &[&str]) -> String {
+        let mut path = self.prefix.clone();
+        for node in nodes {
+            if !path.is_empty() {
+                path.push('.');
+            }
+            path.push_str(&sanitize(node));
+        }
+        path
+    }
+}
+
+impl Default for Plaintext {
+    fn default() -> Plaintext {
+        Plaintext::new()
+    }
+}
+
+/// Writes plaintext lines to a Graphite carbon receiver over TCP
+pub struct GraphiteWriter {
+    stream: TcpStream,
+    lines: Plaintext,
+}
+
+impl GraphiteWriter {
+    /// Connect to the carbon receiver at `addr`, usually on port 2003
+    pub fn connect<A: ToSocketAddrs>(addr: A, lines: Plaintext) -> Result<GraphiteWriter> {
+        let stream = TcpStream::connect(addr)?;
+        stream.set_nodelay(true)?;
+
+        Ok(GraphiteWriter { stream, lines })
+    }
+
+    /// Write one second of data of `service`
+    pub fn write_service(&mut self, service: &str, data: &ServiceDataInSecond) -> Result<()> {
+        let lines = self.lines.service(service, data);
+        self.write(&lines)
+    }
+
+    /// Write one second of data of the origins of `service`
+    pub fn write_origins(&mut self, service: &str, data: &OriginDataInSecond) -> Result<()> {
+        let lines = self.lines.origins(service, data);
+        self.write(&lines)
+    }
+
+    /// Write lines of plaintext protocol as they are
+    pub fn write(&mut self, lines: &str) -> Result<()> {
+        self.stream.write_all(lines.as_bytes())?;
+        self.stream.flush()?;
+        Ok(())
+    }
+}
+
+fn push_line(lines: &mut String, base: &str, metric: &str, value: f64, recorded: u64) {
+    if value.is_finite() {
+        writeln!(lines, "{}.{} {} {}", base, metric, value, recorded).ok();
+    }
+}
+
+/// Replace characters with a meaning in paths or lines by `_`
+fn sanitize(node: &str) -> String {
+    node.chars()
+        .map(|c| {
+            if c == '.' || c.is_whitespace() {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect()
+}
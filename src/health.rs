@@ -0,0 +1,436 @@
+use crate::origin::{OriginDataInSecond, OriginStats};
+use crate::service::ServiceDataInSecond;
+use crate::timeseries::{Scope, Scoped};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Weights of the parts of a health score, only their proportions matter
+#[derive(Debug, Clone)]
+pub struct HealthWeights {
+    /// Proportion of 5xx responses
+    pub server_errors: f64,
+
+    /// Proportion of 4xx responses
+    pub client_errors: f64,
+
+    /// Proportion of 503 and 504 responses, the origin being unavailable or timing out
+    pub unavailable: f64,
+
+    /// 90th percentile latency, when available
+    pub latency: f64,
+}
+
+impl Default for HealthWeights {
+    fn default() -> HealthWeights {
+        HealthWeights {
+            server_errors: 0.4,
+            client_errors: 0.1,
+            unavailable: 0.3,
+            latency: 0.2,
+        }
+    }
+}
+
+/// Configuration of [`OriginHealth`]
+#[derive(Debug, Clone)]
+pub struct HealthConfig {
+    pub weights: HealthWeights,
+
+    /// Proportion of 5xx responses counted as fully unhealthy
+    pub server_error_limit: f64,
+
+    /// Proportion of 4xx responses counted as fully unhealthy
+    pub client_error_limit: f64,
+
+    /// Proportion of 503 and 504 responses counted as fully unhealthy
+    pub unavailable_limit: f64,
+
+    /// 90th percentile latency in milliseconds under which latency is healthy...
+    pub latency_good_ms: f64,
+
+    /// ...and from which it is fully unhealthy
+    pub latency_limit_ms: f64,
+
+    /// Scores under this are degraded
+    pub degraded_below: f64,
+
+    /// Scores under this are failing
+    pub failing_below: f64,
+
+    /// Number of consecutive seconds in a new state before switching to it
+    pub debounce: u64,
+
+    /// Seconds with fewer responses are not scored
+    pub min_responses: u64,
+}
+
+impl Default for HealthConfig {
+    fn default() -> HealthConfig {
+        HealthConfig {
+            weights: HealthWeights::default(),
+            server_error_limit: 0.1,
+            client_error_limit: 0.5,
+            unavailable_limit: 0.05,
+            latency_good_ms: 500.0,
+            latency_limit_ms: 5000.0,
+            degraded_below: 0.9,
+            failing_below: 0.6,
+            debounce: 3,
+            min_responses: 1,
+        }
+    }
+}
+
+impl HealthConfig {
+    /// Score of one second of an origin, `None` with too few responses
+    ///
+    /// Each part is a penalty from 0 to 1, reaching 1 at its limit, and the score is
+    /// 1 minus their weighted average, so 1 is perfectly healthy and 0 fully unhealthy.
+    pub fn score(&self, stats: &OriginStats, latency_ms: Option<f64>) -> Option<HealthScore> {
+        if stats.responses < self.min_responses.max(1) {
+            return None;
+        }
+        let responses = stats.responses as f64;
+        let weights = &self.weights;
+        let server_errors = stats.status_5xx as f64 / responses;
+        let client_errors = stats.status_4xx as f64 / responses;
+        let unavailable = stats.status_503 + stats.status_504;
+
+        let penalty = |value: f64, limit: f64| {
+            if limit > 0.0 {
+                (value / limit).clamp(0.0, 1.0)
+            } else {
+                f64::from(u8::from(value > 0.0))
+            }
+        };
+        let mut parts = vec![
+            (
+                weights.server_errors,
+                penalty(server_errors, self.server_error_limit),
+            ),
+            (
+                weights.client_errors,
+                penalty(client_errors, self.client_error_limit),
+            ),
+            (
+                weights.unavailable,
+                penalty(unavailable as f64 / responses, self.unavailable_limit),
+            ),
+        ];
+        if let Some(latency_ms) = latency_ms {
+            parts.push((
+                weights.latency,
+                penalty(
+                    latency_ms - self.latency_good_ms,
+                    self.latency_limit_ms - self.latency_good_ms,
+                ),
+            ));
+        }
+
+        let total: f64 = parts.iter().map(|(weight, _)| weight).sum();
+        let penalty: f64 = parts.iter().map(|(weight, penalty)| weight * penalty).sum();
+        let score = if total > 0.0 {
+            1.0 - penalty / total
+        } else {
+            1.0
+        };
+
+        Some(HealthScore {
+            score,
+            responses: stats.responses,
+            server_errors,
+            client_errors,
+            unavailable,
+            latency_ms,
+        })
+    }
+
+    /// State of a score, before debouncing
+    pub fn state(&self, score: f64) -> HealthState {
+        if score < self.failing_below {
+            HealthState::Failing
+        } else if score < self.degraded_below {
+            HealthState::Degraded
+        } else {
+            HealthState::Healthy
+        }
+    }
+}
+
+/// Health of a second of an origin, and what it was computed from
+#[derive(Debug, Clone, PartialEq)]
+pub struct HealthScore {
+    /// From 0, fully unhealthy, to 1, perfectly healthy
+    pub score: f64,
+
+    pub responses: u64,
+
+    /// Proportion of 5xx responses
+    pub server_errors: f64,
+
+    /// Proportion of 4xx responses
+    pub client_errors: f64,
+
+    /// Number of 503 and 504 responses
+    pub unavailable: u64,
+
+    /// 90th percentile latency in milliseconds, when available
+    pub latency_ms: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum HealthState {
+    Healthy,
+    Degraded,
+    Failing,
+}
+
+impl fmt::Display for HealthState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            HealthState::Healthy => "healthy",
+            HealthState::Degraded => "degraded",
+            HealthState::Failing => "failing",
+        })
+    }
+}
+
+/// Change of state of an origin, aggregated or in a POP
+#[derive(Debug, Clone, PartialEq)]
+pub struct HealthEvent {
+    pub service: String,
+
+    /// Origin, aggregated or in a POP
+    pub scope: Scope,
+
+    pub previous: HealthState,
+
+    pub state: HealthState,
+
+    pub recorded: u64,
+
+    /// Score of the second of the change
+    pub score: HealthScore,
+}
+
+impl fmt::Display for HealthEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} of {} {} after {}, score {:.2}",
+            self.scope, self.service, self.state, self.previous, self.score.score
+        )
+    }
+}
+
+/// Health of an origin of a service, with the POPs where it is not healthy
+#[derive(Debug, Clone, PartialEq)]
+pub struct OriginSummary {
+    pub service: String,
+
+    pub origin: String,
+
+    pub state: HealthState,
+
+    /// Latest score, aggregated across POPs
+    pub score: f64,
+
+    /// POPs where the origin is degraded or failing, with their latest score, the worst first
+    pub pops: Vec<(String, HealthState, f64)>,
+}
+
+/// Debounced state of a scope
+struct Tracked {
+    state: HealthState,
+    candidate: HealthState,
+    candidate_seconds: u64,
+    latest: u64,
+    score: Option<HealthScore>,
+}
+
+/// Scores the health of each origin of services on each second, aggregated and in each POP,
+/// and tracks their state
+///
+/// A state changes once scores stayed in the new state for `debounce` consecutive seconds,
+/// so a single bad second does not flap it. Seconds without responses keep the state.
+///
+/// Origin Inspector does not report latency by origin, [`OriginHealth::observe_with_latency`] uses the
+/// `miss_histogram` of the service instead, that of all origins of the POP.
+///
+/// ```
+/// use fastly_rt::health::{HealthConfig, HealthState, OriginHealth};
+/// use fastly_rt::origin::{OriginDataInSecond, OriginStats};
+///
+/// let mut health = OriginHealth::new(HealthConfig::default());
+/// for recorded in 1..=5 {
+///     let mut data = OriginDataInSecond { recorded, ..Default::default() };
+///     let stats = OriginStats { responses: 100, status_5xx: 20, status_503: 20, ..Default::default() };
+///     data.aggregated.insert("s3".to_string(), stats);
+///     health.observe("www", &data);
+/// }
+///
+/// let summary = health.summary(10);
+/// assert_eq!(summary[0].origin, "s3");
+/// assert_eq!(summary[0].state, HealthState::Failing);
+/// ```
+pub struct OriginHealth {
+    config: HealthConfig,
+    tracked: HashMap<(String, Scope), Tracked>,
+}
+
+impl OriginHealth {
+    pub fn new(config: HealthConfig) -> OriginHealth {
+        OriginHealth {
+            config,
+            tracked: HashMap::new(),
+        }
+    }
+
+    pub fn config(&self) -> &HealthConfig {
+        &self.config
+    }
+
+    /// Score a new second of origins of `service`, returns the changes of state by scope
+    pub fn observe(&mut self, service: &str, data: &OriginDataInSecond) -> Vec<HealthEvent> {
+        self.observe_scopes(service, data, |_| None)
+    }
+
+    /// Score a new second of origins of `service`, with the latency of origin fetches of `service_data`
+    pub fn observe_with_latency(
+        &mut self,
+        service: &str,
+        data: &OriginDataInSecond,
+        service_data: &ServiceDataInSecond,
+    ) -> Vec<HealthEvent> {
+        self.observe_scopes(service, data, |scope| {
+            let stats = match &scope.pop {
+                Some(pop_name) => service_data.datacenter.get(pop_name)?,
+                None => &service_data.aggregated,
+            };
+            stats.miss_histogram.p90()
+        })
+    }
+
+    fn observe_scopes<F: Fn(&Scope) -> Option<f64>>(
+        &mut self,
+        service: &str,
+        data: &OriginDataInSecond,
+        latency_ms: F,
+    ) -> Vec<HealthEvent> {
+        let mut scopes = data.scopes();
+        scopes.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let mut events = Vec::new();
+
+        for (scope, stats) in scopes {
+            let Some(score) = self.config.score(stats, latency_ms(&scope)) else {
+                continue;
+            };
+            let state = self.config.state(score.score);
+            let tracked = self
+                .tracked
+                .entry((service.to_string(), scope.clone()))
+                .or_insert(Tracked {
+                    state: HealthState::Healthy,
+                    candidate: HealthState::Healthy,
+                    candidate_seconds: 0,
+                    latest: 0,
+                    score: None,
+                });
+            if data.recorded <= tracked.latest {
+                continue;
+            }
+            tracked.latest = data.recorded;
+
+            if state == tracked.state {
+                tracked.candidate_seconds = 0;
+            } else if state == tracked.candidate && tracked.candidate_seconds > 0 {
+                tracked.candidate_seconds += 1;
+            } else {
+                tracked.candidate = state;
+                tracked.candidate_seconds = 1;
+            }
+
+            if tracked.candidate_seconds >= self.config.debounce.max(1) {
+                events.push(HealthEvent {
+                    service: service.to_string(),
+                    scope,
+                    previous: tracked.state,
+                    state,
+                    recorded: data.recorded,
+                    score: score.clone(),
+                });
+                tracked.state = state;
+                tracked.candidate_seconds = 0;
+            }
+            tracked.score = Some(score);
+        }
+        events
+    }
+
+    /// State of `service` in `scope`, an origin aggregated or in a POP, healthy when never scored
+    pub fn state(&self, service: &str, scope: &Scope) -> HealthState {
+        self.tracked
+            .get(&(service.to_string(), scope.clone()))
+            .map_or(HealthState::Healthy, |tracked| tracked.state)
+    }
+
+    /// Latest score of `service` in `scope`
+    pub fn score(&self, service: &str, scope: &Scope) -> Option<&HealthScore> {
+        self.tracked
+            .get(&(service.to_string(), scope.clone()))?
+            .score
+            .as_ref()
+    }
+
+    /// Up to `limit` origins, the worst first, with the POPs seeing a problem
+    ///
+    /// Origins are ordered by state then latest aggregated score. Healthy origins with
+    /// problems in some POPs are listed too.
+    pub fn summary(&self, limit: usize) -> Vec<OriginSummary> {
+        let mut origins: HashMap<(&str, &str), OriginSummary> = HashMap::new();
+        for ((service, scope), tracked) in &self.tracked {
+            let Some(origin_name) = &scope.origin else {
+                continue;
+            };
+            let summary = origins
+                .entry((service.as_str(), origin_name.as_str()))
+                .or_insert_with(|| OriginSummary {
+                    service: service.clone(),
+                    origin: origin_name.clone(),
+                    state: HealthState::Healthy,
+                    score: 1.0,
+                    pops: Vec::new(),
+                });
+            let score = tracked.score.as_ref().map_or(1.0, |score| score.score);
+            match &scope.pop {
+                None => {
+                    summary.state = tracked.state;
+                    summary.score = score;
+                }
+                Some(pop_name) if tracked.state != HealthState::Healthy => {
+                    summary.pops.push((pop_name.clone(), tracked.state, score));
+                }
+                Some(_) => {}
+            }
+        }
+
+        let mut origins: Vec<OriginSummary> = origins
+            .into_values()
+            .filter(|summary| summary.state != HealthState::Healthy || !summary.pops.is_empty())
+            .collect();
+        for summary in &mut origins {
+            summary
+                .pops
+                .sort_by(|a, b| b.1.cmp(&a.1).then(a.2.total_cmp(&b.2)).then(a.0.cmp(&b.0)));
+        }
+        origins.sort_by(|a, b| {
+            b.state
+                .cmp(&a.state)
+                .then(a.score.total_cmp(&b.score))
+                .then(b.pops.len().cmp(&a.pops.len()))
+                .then((&a.service, &a.origin).cmp(&(&b.service, &b.origin)))
+        });
+        origins.truncate(limit);
+        origins
+    }
+}
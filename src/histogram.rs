@@ -0,0 +1,219 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::iter::Sum;
+
+/// Latency histogram of requests to origin, as reported in `miss_histogram`
+///
+/// Fastly reports the histogram as a map of bucket label --> count, where each label is the
+/// upper bound in milliseconds of its bucket. Buckets widen with latency, see
+/// [`LatencyHistogram::BUCKET_WIDTHS_MS`].
+/// Origin requests taking more than 60 seconds are all counted in the `60000` bucket.
+///
+/// Deserializing skips bucket labels which are not numbers, see [`LatencyHistogram::parse`]
+/// to reject them.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "HashMap<String, u64>", into = "HashMap<String, u64>")]
+pub struct LatencyHistogram {
+    /// Map of bucket upper bound in milliseconds --> count
+    buckets: BTreeMap<u64, u64>,
+}
+
+impl LatencyHistogram {
+    /// Width in milliseconds of the buckets, by largest upper bound of that width
+    pub const BUCKET_WIDTHS_MS: [(u64, u64); 7] = [
+        (10, 1),
+        (250, 10),
+        (1000, 50),
+        (3000, 100),
+        (10_000, 500),
+        (20_000, 1000),
+        (60_000, 5000),
+    ];
+
+    /// Create an empty histogram
+    pub fn new() -> LatencyHistogram {
+        LatencyHistogram::default()
+    }
+
+    /// Parse a raw histogram of bucket label --> count
+    pub fn parse(raw: &HashMap<String, u64>) -> Result<LatencyHistogram> {
+        let mut histogram = LatencyHistogram::new();
+
+        for (label, count) in raw {
+            let upper_ms = label
+                .trim()
+                .parse::<u64>()
+                .map_err(|_| anyhow!("invalid miss_histogram bucket label {:?}", label))?;
+            histogram.add(upper_ms, *count);
+        }
+
+        Ok(histogram)
+    }
+
+    /// Upper bound of the bucket counting a request which took `latency_ms`, see [`Self::BUCKET_WIDTHS_MS`]
+    ///
+    /// Requests taking more than 60 seconds are counted in the `60000` bucket.
+    pub fn upper_bound(latency_ms: f64) -> u64 {
+        let Some((_, width_ms)) = Self::BUCKET_WIDTHS_MS
+            .iter()
+            .find(|(largest_ms, _)| latency_ms <= *largest_ms as f64)
+        else {
+            return 60_000;
+        };
+        ((latency_ms / *width_ms as f64).ceil() as u64).max(1) * width_ms
+    }
+
+    /// Add `count` requests to the bucket whose upper bound is `upper_ms`
+    pub fn add(&mut self, upper_ms: u64, count: u64) {
+        if count > 0 {
+            *self.buckets.entry(upper_ms).or_insert(0) += count;
+        }
+    }
+
+    /// Merge another histogram into this one, e.g. across seconds or POPs
+    pub fn merge(&mut self, other: &LatencyHistogram) {
+        for (upper_ms, count) in &other.buckets {
+            self.add(*upper_ms, *count);
+        }
+    }
+
+    /// Iterate over (bucket upper bound in milliseconds, count), in ascending order of bound
+    pub fn buckets(&self) -> impl Iterator<Item = (u64, u64)> + '_ {
+        self.buckets
+            .iter()
+            .map(|(upper_ms, count)| (*upper_ms, *count))
+    }
+
+    /// Iterate over (bucket lower bound, bucket upper bound in milliseconds, count), in ascending order
+    ///
+    /// The lower bound of a bucket is its upper bound less its width, see [`Self::BUCKET_WIDTHS_MS`],
+    /// but not under the upper bound of the bucket before it.
+    pub fn bounds(&self) -> impl Iterator<Item = (u64, u64, u64)> + '_ {
+        let mut previous_ms = 0;
+        self.buckets().map(move |(upper_ms, count)| {
+            let width_ms = Self::BUCKET_WIDTHS_MS
+                .iter()
+                .find(|(largest_ms, _)| upper_ms <= *largest_ms)
+                .map_or(0, |(_, width_ms)| *width_ms);
+            let lower_ms = upper_ms.saturating_sub(width_ms).max(previous_ms);
+            previous_ms = upper_ms;
+            (lower_ms, upper_ms, count)
+        })
+    }
+
+    /// Total number of requests in the histogram
+    pub fn total(&self) -> u64 {
+        self.buckets.values().sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.total() == 0
+    }
+
+    /// Estimate the sum of latencies in milliseconds, counting each request at the middle of its bucket
+    pub fn sum_ms(&self) -> f64 {
+        self.bounds()
+            .map(|(lower_ms, upper_ms, count)| {
+                let middle_ms = (lower_ms + upper_ms) as f64 / 2.0;
+                middle_ms * count as f64
+            })
+            .sum()
+    }
+
+    /// Estimate the latency in milliseconds below which fraction `q` (0.0 to 1.0) of requests fall
+    /// The value is linearly interpolated inside the bucket holding the quantile.
+    /// Returns `None` when the histogram is empty.
+    pub fn percentile(&self, q: f64) -> Option<f64> {
+        let total = self.total();
+        if total == 0 {
+            return None;
+        }
+
+        let rank = q.clamp(0.0, 1.0) * total as f64;
+        let mut seen = 0u64;
+
+        for (lower_ms, upper_ms, count) in self.bounds() {
+            if count == 0 {
+                continue;
+            }
+
+            if (seen + count) as f64 >= rank {
+                let lower_ms = lower_ms as f64;
+                let inside = (rank - seen as f64).max(0.0) / count as f64;
+                return Some(lower_ms + inside * (upper_ms as f64 - lower_ms));
+            }
+
+            seen += count;
+        }
+
+        self.buckets
+            .keys()
+            .next_back()
+            .map(|upper_ms| *upper_ms as f64)
+    }
+
+    /// Estimated median miss latency in milliseconds
+    pub fn p50(&self) -> Option<f64> {
+        self.percentile(0.5)
+    }
+
+    /// Estimated 90th percentile miss latency in milliseconds
+    pub fn p90(&self) -> Option<f64> {
+        self.percentile(0.9)
+    }
+
+    /// Estimated 99th percentile miss latency in milliseconds
+    pub fn p99(&self) -> Option<f64> {
+        self.percentile(0.99)
+    }
+
+    /// Convert to Prometheus style cumulative buckets
+    /// Returns (`le` upper bound in seconds, cumulative count), the last entry being `+Inf`
+    pub fn cumulative_buckets(&self) -> Vec<(f64, u64)> {
+        let mut cumulative = 0u64;
+        let mut result: Vec<(f64, u64)> = self
+            .buckets()
+            .map(|(upper_ms, count)| {
+                cumulative += count;
+                (upper_ms as f64 / 1000.0, cumulative)
+            })
+            .collect();
+
+        result.push((f64::INFINITY, cumulative));
+        result
+    }
+}
+
+impl From<HashMap<String, u64>> for LatencyHistogram {
+    /// Skips bucket labels which are not numbers
+    fn from(raw: HashMap<String, u64>) -> LatencyHistogram {
+        let mut histogram = LatencyHistogram::new();
+        for (label, count) in raw {
+            if let Ok(upper_ms) = label.trim().parse::<u64>() {
+                histogram.add(upper_ms, count);
+            }
+        }
+        histogram
+    }
+}
+
+impl From<LatencyHistogram> for HashMap<String, u64> {
+    fn from(histogram: LatencyHistogram) -> HashMap<String, u64> {
+        histogram
+            .buckets
+            .into_iter()
+            .map(|(upper_ms, count)| (upper_ms.to_string(), count))
+            .collect()
+    }
+}
+
+impl<'a> Sum<&'a LatencyHistogram> for LatencyHistogram {
+    fn sum<I: Iterator<Item = &'a LatencyHistogram>>(iter: I) -> LatencyHistogram {
+        let mut histogram = LatencyHistogram::new();
+        for other in iter {
+            histogram.merge(other);
+        }
+        histogram
+    }
+}
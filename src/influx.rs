@@ -0,0 +1,287 @@
+use crate::metric::{sorted, MetricFilter, Metrics};
+use crate::origin::{OriginDataInSecond, OriginStats};
+use crate::service::{ServiceDataInSecond, ServiceStats};
+use anyhow::{anyhow, Result};
+use std::fmt::Write;
+
+/// Serializer of real time data to [InfluxDB line protocol](https://docs.influxdata.com/influxdb/v2/reference/syntax/line-protocol/)
+///
+/// Service data is written to the `fastly_rt` measurement with a `service` tag, and origin data
+/// to `fastly_rt_origin` with `service` and `origin` tags.
+/// Fields are the metrics of the stats, all floats, plus `miss_latency_p50`, `miss_latency_p90`
+/// and `miss_latency_p99` in milliseconds when requests to origin were made.
+/// Metrics allowed by POP by the filter are written to lines with a `pop` tag, the others to lines
+/// aggregated across POPs.
+/// Timestamps are `recorded` in nanoseconds.
+///
+/// ```
+/// use fastly_rt::influx::LineProtocol;
+/// use fastly_rt::service::ServiceDataInSecond;
+///
+/// let data = ServiceDataInSecond { recorded: 1, ..Default::default() };
+/// let lines = LineProtocol::new().service("www", &data);
+///
+/// assert!(lines.starts_with("fastly_rt,service=www attack_blocked_req_body_bytes=0,"));
+/// assert!(lines.ends_with(" 1000000000\n"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct LineProtocol {
+    measurement: String,
+    filter: MetricFilter,
+    tags: Vec<(String, String)>,
+}
+
+impl LineProtocol {
+    pub fn new() -> LineProtocol {
+        LineProtocol {
+            measurement: "fastly_rt".to_string(),
+            filter: MetricFilter::default(),
+            tags: Vec::new(),
+        }
+    }
+
+    /// Name of the service measurement, origins being written to `{measurement}_origin`
+    pub fn with_measurement(mut self, measurement: &str) -> LineProtocol {
+        self.measurement = measurement.to_string();
+        self
+    }
+
+    /// Select the written fields, and those written by POP
+    pub fn with_filter(mut self, filter: MetricFilter) -> LineProtocol {
+        self.filter = filter;
+        self
+    }
+
+    /// Add a tag to every line
+    pub fn with_tag(mut self, key: &str, value: &str) -> LineProtocol {
+        self.tags.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Lines of one second of data of `service`
+    pub fn service(&self, service: &str, data: &ServiceDataInSecond) -> String {
+        let mut lines = String::new();
+        let timestamp = data.recorded * 1_000_000_000;
+
+        let fields = self.fields(&data.aggregated, |name| {
+            self.filter.allows(name) && !self.filter.allows_pop(name)
+        });
+        let tags = [("service", service)];
+        self.push_line(&mut lines, &self.measurement, &tags, &fields, timestamp);
+
+        for (pop_name, stats) in sorted(&data.datacenter) {
+            let fields = self.fields(stats, |name| self.filter.allows_pop(name));
+            let tags = [("service", service), ("pop", pop_name)];
+            self.push_line(&mut lines, &self.measurement, &tags, &fields, timestamp);
+        }
+
+        lines
+    }
+
+    /// Lines of one second of data of the origins of `service`
+    pub fn origins(&self, service: &str, data: &OriginDataInSecond) -> String {
+        let mut lines = String::new();
+        let timestamp = data.recorded * 1_000_000_000;
+        let measurement = format!("{}_origin", self.measurement);
+
+        for (origin_name, stats) in sorted(&data.aggregated) {
+            let fields = self.origin_fields(stats, |name| {
+                self.filter.allows(name) && !self.filter.allows_pop(name)
+            });
+            let tags = [("service", service), ("origin", origin_name)];
+            self.push_line(&mut lines, &measurement, &tags, &fields, timestamp);
+        }
+
+        for (pop_name, origins) in sorted(&data.datacenter) {
+            for (origin_name, stats) in sorted(origins) {
+                let fields = self.origin_fields(stats, |name| self.filter.allows_pop(name));
+                let tags = [
+                    ("service", service),
+                    ("pop", pop_name),
+                    ("origin", origin_name),
+                ];
+                self.push_line(&mut lines, &measurement, &tags, &fields, timestamp);
+            }
+        }
+
+        lines
+    }
+
+    fn fields<F: Fn(&str) -> bool>(&self, stats: &ServiceStats, selected: F) -> Vec<(String, f64)> {
+        let mut fields: Vec<(String, f64)> = stats
+            .metrics()
+            .filter(|(metric, _)| selected(metric.name))
+            .map(|(metric, value)| (metric.name.to_string(), value))
+            .collect();
+
+        if selected("miss_histogram") {
+            let percentiles = [
+                ("miss_latency_p50", stats.miss_histogram.p50()),
+                ("miss_latency_p90", stats.miss_histogram.p90()),
+                ("miss_latency_p99", stats.miss_histogram.p99()),
+            ];
+            for (name, percentile) in percentiles {
+                if let Some(percentile) = percentile {
+                    fields.push((name.to_string(), percentile));
+                }
+            }
+        }
+
+        fields
+    }
+
+    fn origin_fields<F: Fn(&str) -> bool>(
+        &self,
+        stats: &OriginStats,
+        selected: F,
+    ) -> Vec<(String, f64)> {
+        stats
+            .metrics()
+            .filter(|(metric, _)| selected(&format!("origin_{}", metric.name)))
+            .map(|(metric, value)| (metric.name.to_string(), value))
+            .collect()
+    }
+
+    /// Append a line, unless it has no field
+    fn push_line(
+        &self,
+        lines: &mut String,
+        measurement: &str,
+        tags: &[(&str, &str)],
+        fields: &[(String, f64)],
+        timestamp: u64,
+    ) {
+        if fields.is_empty() {
+            return;
+        }
+
+        lines.push_str(&escape(measurement, &[',', ' ']));
+        let all_tags = self
+            .tags
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .chain(tags.iter().copied());
+        for (key, value) in all_tags {
+            if !value.is_empty() {
+                write!(lines, ",{}={}", escape_tag(key), escape_tag(value)).ok();
+            }
+        }
+
+        for (index, (name, value)) in fields.iter().enumerate() {
+            let separator = if index == 0 { ' ' } else { ',' };
+            write!(
+                lines,
+                "{}{}={}",
+                separator,
+                escape_tag(name),
+                format_float(*value)
+            )
+            .ok();
+        }
+
+        writeln!(lines, " {}", timestamp).ok();
+    }
+}
+
+impl Default for LineProtocol {
+    fn default() -> LineProtocol {
+        LineProtocol::new()
+    }
+}
+
+/// Writes line protocol to the HTTP write API of InfluxDB
+///
+/// `url` is the full URL of the write endpoint, with precision in nanoseconds,
+/// e.g. `http://localhost:8086/api/v2/write?org=my-org&bucket=fastly&precision=ns` for InfluxDB 2,
+/// or `http://localhost:8086/write?db=fastly` for InfluxDB 1.
+pub struct InfluxWriter {
+    client: reqwest::Client,
+    url: String,
+    token: Option<String>,
+    lines: LineProtocol,
+}
+
+impl InfluxWriter {
+    pub fn new(url: &str, lines: LineProtocol) -> Result<InfluxWriter> {
+        Ok(InfluxWriter {
+            client: reqwest::Client::builder().build()?,
+            url: url.to_string(),
+            token: None,
+            lines,
+        })
+    }
+
+    /// Authenticate with an API token, sent as `Authorization: Token {token}`
+    pub fn with_token(mut self, token: &str) -> InfluxWriter {
+        self.token = Some(token.to_string());
+        self
+    }
+
+    /// Write seconds of data of `service`
+    pub async fn write_service(&self, service: &str, data: &[ServiceDataInSecond]) -> Result<()> {
+        let body: String = data
+            .iter()
+            .map(|data| self.lines.service(service, data))
+            .collect();
+        self.write(body).await
+    }
+
+    /// Write seconds of data of the origins of `service`
+    pub async fn write_origins(&self, service: &str, data: &[OriginDataInSecond]) -> Result<()> {
+        let body: String = data
+            .iter()
+            .map(|data| self.lines.origins(service, data))
+            .collect();
+        self.write(body).await
+    }
+
+    /// Write lines of line protocol as they are, nothing is sent when empty
+    pub async fn write(&self, body: String) -> Result<()> {
+        if body.is_empty() {
+            return Ok(());
+        }
+
+        let mut request = self
+            .client
+            .post(&self.url)
+            .header("content-type", "text/plain; charset=utf-8")
+            .body(body);
+        if let Some(token) = &self.token {
+            request = request.header("authorization", format!("Token {}", token));
+        }
+
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow!("InfluxDB write failed with {}: {}", status, body));
+        }
+
+        Ok(())
+    }
+}
+
+fn escape(text: &str, special: &[char]) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c == '\\' || special.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Escape a tag key, tag value or field key
+fn escape_tag(text: &str) -> String {
+    escape(text, &[',', '=', ' '])
+}
+
+/// Format a float field, non finite values being written as 0 as line protocol has no representation for them
+fn format_float(value: f64) -> String {
+    if value.is_finite() {
+        value.to_string()
+    } else {
+        "0".to_string()
+    }
+}
@@ -6,8 +6,14 @@
 //! Real-time analytics provides statistics of a service.
 //! Related structures are [`service::ServiceResponse`], [`service::ServiceDataInSecond`], [`service::ServiceStats`]
 //!
+//! Latency histogram of requests to origin `miss_histogram` is parsed into [`histogram::LatencyHistogram`],
+//! which can be merged across seconds and POPs and used to estimate percentiles.
+//!
+//! Keys of `datacenter` maps are POP names such as `NRT`, [`pop::Pop`] gives their city, country,
+//! continent, billing region and coordinates from an embedded catalog.
+//!
 //! To get statistic concecutively
-//! ```
+//! ```no_run
 //! use fastly_rt::service::ServiceClient;
 //! use std::env;
 //! use std::{thread, time};
@@ -32,7 +38,7 @@
 //! ```
 //!
 //! To get statistic of last 120 seconds
-//! ```
+//! ```no_run
 //! use fastly_rt::service::ServiceClient;
 //! use std::env;
 //!
@@ -52,7 +58,7 @@
 //! ```
 //!
 //! To get statistic of last 10 seconds
-//! ```
+//! ```no_run
 //! use fastly_rt::service::ServiceClient;
 //! use std::env;
 //!
@@ -75,7 +81,96 @@
 //! Related structures are [`origin::OriginResponse`], [`origin::OriginDataInSecond`], [`origin::OriginStats`]
 //!
 //! Examples are similar to that Real-time origin metrics
+//!
+//! ## Generic tooling
+//! Both clients are a [`realtime::RtClient`] returning a [`realtime::RtResponse`], and implement the
+//! [`realtime::RealtimeClient`] trait, so pollers, exporters and recorders can be written once for all endpoints.
+//!
+//! [`service::ServiceStats`] and [`origin::OriginStats`] implement [`metric::Metrics`], a catalog of their
+//! numeric fields with unit and description, to export them without listing fields one by one.
+//!
+//! The `exporter` feature provides `exporter::Exporter`, which polls services and origins and serves
+//! accumulated counters to Prometheus on `/metrics`, and the `fastly-rt-exporter` binary running it.
+//! [`openmetrics::Encoder`] renders a second, or a window of seconds, to OpenMetrics or Prometheus text
+//! for serving it from an existing HTTP server.
+//!
+//! The `otlp` feature provides `otlp::OtlpExporter`, which pushes the data to an OpenTelemetry collector over OTLP/HTTP.
+//! [`statsd::StatsdSink`] sends it to StatsD or DogStatsD over UDP, [`influx::LineProtocol`] and
+//! [`graphite::Plaintext`] serialize it for InfluxDB and Graphite, with writers over HTTP and TCP.
+//!
+//! [`csv::CsvWriter`] streams the selected metrics of each second to CSV, e.g. for a spreadsheet.
+//! The `arrow` feature provides `arrow::Columnar`, converting seconds of data to Arrow record batches
+//! in wide or long layouts, and `arrow::ParquetWriter`, which writes them to hourly Parquet files.
+//! The `archive` feature provides `archive::Archiver`, keeping every second in rotating and compressed
+//! NDJSON files, and `archive::ArchiveReader` reading them back by time range.
+//! The `sqlite` feature provides `sqlite::Store`, a local history of seconds with minute and hour rollups.
+//!
+//! ## Analysis
+//! [`timeseries::TimeSeriesStore`] keeps the last seconds of services in ring buffers by scope, aggregated,
+//! POP or origin, and answers windowed queries such as sums and rates over the last minutes.
+//! [`downsample::Downsampler`] sums seconds into aligned minutes and hours, waiting for late seconds.
+//! [`alert::AlertEngine`] evaluates rules such as `status_5xx / requests > 0.02 for 30s` on each second,
+//! by service, POP or origin, and notifies their pending, firing and resolved alerts; the `toml` feature
+//! loads rules from TOML with [`alert::AlertConfig`].
+//! [`anomaly::AnomalyMonitor`] attaches detectors to metrics, a moving average, a time of day baseline or
+//! a median absolute deviation, and reports values out of their expected range, whatever the traffic level.
+//! [`outage::OutageDetector`] learns the POPs serving each service and their share of traffic, flags POPs
+//! which vanish, drop or spike in errors, and reports which POPs absorbed their traffic.
+//! [`health::OriginHealth`] scores each origin on each second from its error proportions and latency,
+//! tracks healthy, degraded and failing states, and summarizes the worst origins with the POPs affected.
+//! [`slo::SloTracker`] tracks the error budget of service level objectives over their period and their
+//! multi-window burn rates, alerting with the events of [`alert::AlertEngine`].
+//! [`ranking::Ranker`] ranks the POPs or origins of a window of seconds by a metric or KPI, with their share
+//! of the total and change versus the previous window, and [`ranking::status_codes`] ranks status codes.
+//!
+//! ## Testing without Fastly
+//! [`synth::Generator`] generates realistic and internally consistent service and origin data from a seed,
+//! with a daily traffic curve and scripted incidents.
+//!
+//! The `fake` feature provides `fake::FakeServer`, a local server implementing the real time endpoints
+//! with generated data and injectable faults, and the `fastly-rt-fake` binary running it.
+//! Clients are pointed to it with [`realtime::RtClient::with_base_url`].
+//!
+//! Responses can be recorded to an NDJSON cassette with [`cassette::Recorder`] and replayed later with
+//! [`cassette::Replay`], which implements [`realtime::RealtimeClient`] too.
+//!
+//! Code which only polls consecutive data can depend on [`realtime::RealtimeSource`] instead, and be unit tested
+//! with the scripted [`mock::MockSource`].
 
+pub mod alert;
+pub mod anomaly;
+#[cfg(feature = "archive")]
+pub mod archive;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+pub mod cassette;
 mod client;
+pub mod csv;
+pub mod downsample;
+#[cfg(feature = "exporter")]
+pub mod exporter;
+#[cfg(feature = "fake")]
+pub mod fake;
+pub mod graphite;
+pub mod health;
+pub mod histogram;
+pub mod influx;
+pub mod metric;
+pub mod mock;
+pub mod openmetrics;
 pub mod origin;
+#[cfg(feature = "otlp")]
+pub mod otlp;
+pub mod outage;
+pub mod pop;
+pub mod ranking;
+pub mod realtime;
 pub mod service;
+pub mod slo;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod statsd;
+pub mod synth;
+pub mod timeseries;
+#[cfg(any(feature = "arrow", feature = "archive"))]
+mod utc;
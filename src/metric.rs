@@ -0,0 +1,200 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Unit of the values of a metric
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MetricUnit {
+    /// Number of events, e.g. requests or responses
+    Count,
+
+    Bytes,
+
+    Seconds,
+
+    Milliseconds,
+}
+
+impl MetricUnit {
+    /// Name of the unit as used in metric names and `# UNIT` lines, empty for [`MetricUnit::Count`]
+    pub fn name(&self) -> &'static str {
+        match self {
+            MetricUnit::Count => "",
+            MetricUnit::Bytes => "bytes",
+            MetricUnit::Seconds => "seconds",
+            MetricUnit::Milliseconds => "milliseconds",
+        }
+    }
+}
+
+impl fmt::Display for MetricUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// How values of a metric over several seconds or POPs combine
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MetricKind {
+    /// Sum over the second and the POPs it covers, added up across seconds and POPs
+    Counter,
+
+    /// Level at the time of the second, e.g. `compute_ram_used`, combined by keeping the largest
+    Gauge,
+}
+
+/// A numeric measurement of `S`, e.g. [`crate::service::ServiceStats`] or [`crate::origin::OriginStats`]
+///
+/// Most measurements are counters, see [`MetricKind`].
+pub struct Metric<S> {
+    /// Name of the field, as reported by Fastly
+    pub name: &'static str,
+
+    pub unit: MetricUnit,
+
+    pub kind: MetricKind,
+
+    /// One line description
+    pub help: &'static str,
+
+    /// Value of the metric in a stats
+    pub value: fn(&S) -> f64,
+
+    /// Set the metric in a stats, converting the value to the type of the field
+    pub set: fn(&mut S, f64),
+}
+
+impl<S> Clone for Metric<S> {
+    fn clone(&self) -> Metric<S> {
+        *self
+    }
+}
+
+impl<S> Copy for Metric<S> {}
+
+impl<S> fmt::Debug for Metric<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Metric")
+            .field("name", &self.name)
+            .field("unit", &self.unit)
+            .field("kind", &self.kind)
+            .field("help", &self.help)
+            .finish()
+    }
+}
+
+/// Stats having a catalog of their numeric metrics
+pub trait Metrics: Sized + 'static {
+    /// Every numeric metric, sorted by name
+    const METRICS: &'static [Metric<Self>];
+
+    /// Metric of the catalog named `name`
+    fn metric_info(name: &str) -> Option<&'static Metric<Self>> {
+        Self::METRICS
+            .binary_search_by(|metric| metric.name.cmp(name))
+            .ok()
+            .map(|index| &Self::METRICS[index])
+    }
+
+    /// Value of the metric named `name`, `None` if there is no such metric
+    fn metric(&self, name: &str) -> Option<f64> {
+        Self::metric_info(name).map(|metric| (metric.value)(self))
+    }
+
+    /// Iterate over every metric of the catalog with its value
+    fn metrics(&self) -> impl Iterator<Item = (&'static Metric<Self>, f64)> {
+        Self::METRICS
+            .iter()
+            .map(move |metric| (metric, (metric.value)(self)))
+    }
+}
+
+/// Match `name` against a pattern where `*` matches any sequence of characters
+pub fn matches_pattern(pattern: &str, name: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == name,
+        Some((prefix, rest)) => {
+            let Some(name) = name.strip_prefix(prefix) else {
+                return false;
+            };
+            if rest.is_empty() {
+                return true;
+            }
+            (0..=name.len())
+                .filter(|start| name.is_char_boundary(*start))
+                .any(|start| matches_pattern(rest, &name[start..]))
+        }
+    }
+}
+
+/// Entries of `map` sorted by key, e.g. POPs or origins in a stable order
+pub(crate) fn sorted<V>(map: &HashMap<String, V>) -> Vec<(&str, &V)> {
+    let mut entries: Vec<(&str, &V)> = map
+        .iter()
+        .map(|(key, value)| (key.as_str(), value))
+        .collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+    entries
+}
+
+/// Selection of exported metrics, by patterns of metric names where `*` matches anything
+///
+/// Names are those of the fields of [`crate::service::ServiceStats`] and [`crate::origin::OriginStats`],
+/// the latter prefixed with `origin_`, e.g. `origin_status_5xx`, and `miss_histogram`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MetricFilter {
+    /// Exported metrics, all of them when empty
+    #[serde(default)]
+    pub allow: Vec<String>,
+
+    /// Metrics not exported, even when allowed
+    #[serde(default)]
+    pub deny: Vec<String>,
+
+    /// Metrics exported by POP, with a POP label or tag, none of them when empty
+    #[serde(default)]
+    pub pop_allow: Vec<String>,
+
+    /// Metrics not exported by POP, even when allowed
+    #[serde(default)]
+    pub pop_deny: Vec<String>,
+}
+
+impl MetricFilter {
+    /// Whether the metric `name` is exported
+    pub fn allows(&self, name: &str) -> bool {
+        (self.allow.is_empty() || matching(&self.allow, name)) && !matching(&self.deny, name)
+    }
+
+    /// Whether the metric `name` is exported by POP rather than aggregated across POPs
+    pub fn allows_pop(&self, name: &str) -> bool {
+        self.allows(name) && matching(&self.pop_allow, name) && !matching(&self.pop_deny, name)
+    }
+}
+
+fn matching(patterns: &[String], name: &str) -> bool {
+    patterns
+        .iter()
+        .any(|pattern| matches_pattern(pattern, name))
+}
+
+/// Builds a `&[Metric<$stats>]` out of `name: Unit, "help";` entries, `name: Unit gauge, "help";` for gauges
+macro_rules! metric_catalog {
+    (@kind) => { $crate::metric::MetricKind::Counter };
+    (@kind gauge) => { $crate::metric::MetricKind::Gauge };
+    ($stats:ty; $($name:ident: $unit:ident $($gauge:ident)?, $help:literal;)*) => {
+        &[$(
+            $crate::metric::Metric {
+                name: stringify!($name),
+                unit: $crate::metric::MetricUnit::$unit,
+                kind: metric_catalog!(@kind $($gauge)?),
+                help: $help,
+                value: |stats: &$stats| stats.$name as f64,
+                set: |stats: &mut $stats, value: f64| stats.$name = value as _,
+            },
+        )*]
+    };
+}
+
+pub(crate) use metric_catalog;
@@ -0,0 +1,163 @@
+use crate::realtime::{RealtimeClient, RtResponse};
+use anyhow::{anyhow, Result};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// A call received by a [`MockSource`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MockCall {
+    ResetStatsConsecutive,
+    GetStatsConsecutive,
+    GetStatsFrom(u64),
+    GetStats120s,
+    GetStatsMax(u64),
+}
+
+enum Step<D> {
+    Response(RtResponse<D>),
+    Error(String),
+    Delay(Duration),
+}
+
+/// Scripted real time source for unit testing code depending on [`crate::realtime::RealtimeSource`]
+/// or [`RealtimeClient`]
+///
+/// Each query, whichever method is called, waits for the scripted delays and then serves the next
+/// scripted response or error, in the order they were added.
+/// Once the script is exhausted every query fails.
+///
+/// ```
+/// use fastly_rt::mock::MockSource;
+/// use fastly_rt::realtime::{RealtimeSource, RtResponse};
+/// use fastly_rt::service::ServiceDataInSecond;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let mut source = MockSource::<ServiceDataInSecond>::new()
+///         .respond(RtResponse { aggregate_delay: 0, data: vec![], timestamp: 1 })
+///         .fail("429 Too Many Requests");
+///
+///     assert_eq!(source.next_stats().await.unwrap().timestamp, 1);
+///     assert!(source.next_stats().await.is_err());
+/// }
+/// ```
+pub struct MockSource<D> {
+    script: Mutex<VecDeque<Step<D>>>,
+    calls: Mutex<Vec<MockCall>>,
+}
+
+impl<D: Send + 'static> MockSource<D> {
+    /// Create a source with an empty script
+    pub fn new() -> MockSource<D> {
+        MockSource {
+            script: Mutex::new(VecDeque::new()),
+            calls: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Serve `response` to the next query
+    pub fn respond(self, response: RtResponse<D>) -> MockSource<D> {
+        self.push(Step::Response(response))
+    }
+
+    /// Fail the next query with `message`
+    pub fn fail(self, message: &str) -> MockSource<D> {
+        self.push(Step::Error(message.to_string()))
+    }
+
+    /// Delay the next response or error by `delay`
+    pub fn delay(self, delay: Duration) -> MockSource<D> {
+        self.push(Step::Delay(delay))
+    }
+
+    /// Number of scripted responses and errors not served yet
+    pub fn remaining(&self) -> usize {
+        self.script
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|step| !matches!(step, Step::Delay(_)))
+            .count()
+    }
+
+    /// Calls received so far, in order
+    pub fn calls(&self) -> Vec<MockCall> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    fn push(self, step: Step<D>) -> MockSource<D> {
+        self.script.lock().unwrap().push_back(step);
+        self
+    }
+
+    fn serve(&self, call: MockCall) -> impl Future<Output = Result<RtResponse<D>>> + Send {
+        self.calls.lock().unwrap().push(call);
+
+        let mut delay = Duration::ZERO;
+        let mut result = Err(anyhow!("mock source script exhausted"));
+        {
+            let mut script = self.script.lock().unwrap();
+            while let Some(step) = script.pop_front() {
+                match step {
+                    Step::Delay(duration) => delay += duration,
+                    Step::Response(response) => {
+                        result = Ok(response);
+                        break;
+                    }
+                    Step::Error(message) => {
+                        result = Err(anyhow!(message));
+                        break;
+                    }
+                }
+            }
+        }
+
+        async move {
+            if !delay.is_zero() {
+                tokio::time::sleep(delay).await;
+            }
+            result
+        }
+    }
+}
+
+impl<D: Send + 'static> Default for MockSource<D> {
+    fn default() -> MockSource<D> {
+        MockSource::new()
+    }
+}
+
+impl<D: Send + 'static> RealtimeClient for MockSource<D> {
+    type Data = D;
+
+    fn reset_stats_consecutive(&mut self) {
+        self.calls
+            .lock()
+            .unwrap()
+            .push(MockCall::ResetStatsConsecutive);
+    }
+
+    fn get_stats_consecutive(&mut self) -> impl Future<Output = Result<RtResponse<D>>> + Send {
+        self.serve(MockCall::GetStatsConsecutive)
+    }
+
+    fn get_stats_from(
+        &mut self,
+        start_timestamp: u64,
+    ) -> impl Future<Output = Result<RtResponse<D>>> + Send {
+        self.serve(MockCall::GetStatsFrom(start_timestamp))
+    }
+
+    fn get_stats_120s(&self) -> impl Future<Output = Result<RtResponse<D>>> + Send {
+        self.serve(MockCall::GetStats120s)
+    }
+
+    fn get_stats_max(
+        &self,
+        max_entries: u64,
+    ) -> impl Future<Output = Result<RtResponse<D>>> + Send {
+        self.serve(MockCall::GetStatsMax(max_entries))
+    }
+}
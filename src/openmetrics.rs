@@ -0,0 +1,349 @@
+use crate::histogram::LatencyHistogram;
+use crate::metric::{Metric, MetricUnit, Metrics};
+use crate::origin::{OriginDataInSecond, OriginStats};
+use crate::service::{ServiceDataInSecond, ServiceStats};
+use std::fmt::Write;
+
+/// Exposition format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// [OpenMetrics](https://github.com/OpenObservability/OpenMetrics/blob/main/specification/OpenMetrics.md),
+    /// with `# UNIT` lines, timestamps in seconds and a final `# EOF`
+    OpenMetrics,
+
+    /// Prometheus text format 0.0.4, timestamps in milliseconds
+    Prometheus,
+}
+
+impl Format {
+    /// Value of the `Content-Type` header of a response holding the text
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Format::OpenMetrics => "application/openmetrics-text; version=1.0.0; charset=utf-8",
+            Format::Prometheus => "text/plain; version=0.0.4; charset=utf-8",
+        }
+    }
+}
+
+/// Encoder of real time data to exposition text, for serving it from an existing HTTP server
+///
+/// Every field of the stats becomes a gauge of the value in the encoded second, or window of
+/// seconds merged with [`ServiceDataInSecond::merge`], named `{prefix}_{field}` and
+/// `{prefix}_origin_{field}` for origins.
+/// Names end with the unit, milliseconds being converted to seconds, e.g. `fastly_rt_hits_time_seconds`.
+/// `miss_histogram` becomes the `{prefix}_miss_latency_seconds` histogram.
+///
+/// ```
+/// use fastly_rt::openmetrics::Encoder;
+/// use fastly_rt::service::ServiceDataInSecond;
+///
+/// let data = ServiceDataInSecond::default();
+/// let text = Encoder::new().with_label("service", "www").encode_service(&data);
+///
+/// assert!(text.contains("fastly_rt_requests{service=\"www\"} 0 0\n"));
+/// assert!(text.ends_with("# EOF\n"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Encoder {
+    format: Format,
+    prefix: String,
+    labels: Vec<(String, String)>,
+    by_pop: bool,
+    timestamps: bool,
+}
+
+impl Encoder {
+    /// OpenMetrics encoder of measurements aggregated across POPs, prefixed with `fastly_rt`
+    pub fn new() -> Encoder {
+        Encoder {
+            format: Format::OpenMetrics,
+            prefix: "fastly_rt".to_string(),
+            labels: Vec::new(),
+            by_pop: false,
+            timestamps: true,
+        }
+    }
+
+    pub fn with_format(mut self, format: Format) -> Encoder {
+        self.format = format;
+        self
+    }
+
+    /// Prefix of metric names
+    pub fn with_prefix(mut self, prefix: &str) -> Encoder {
+        self.prefix = prefix.to_string();
+        self
+    }
+
+    /// Add a label to every sample, e.g. `service`
+    pub fn with_label(mut self, name: &str, value: &str) -> Encoder {
+        self.labels.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Encode measurements by POP with a `pop` label, instead of aggregated across POPs
+    pub fn by_pop(mut self, by_pop: bool) -> Encoder {
+        self.by_pop = by_pop;
+        self
+    }
+
+    /// Stamp samples with `recorded`, true by default
+    pub fn with_timestamps(mut self, timestamps: bool) -> Encoder {
+        self.timestamps = timestamps;
+        self
+    }
+
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Encode service data, as a complete text
+    pub fn encode_service(&self, data: &ServiceDataInSecond) -> String {
+        let mut text = String::new();
+        self.write_service(&mut text, data);
+        self.finish(&mut text);
+        text
+    }
+
+    /// Encode origin data, as a complete text
+    pub fn encode_origins(&self, data: &OriginDataInSecond) -> String {
+        let mut text = String::new();
+        self.write_origins(&mut text, data);
+        self.finish(&mut text);
+        text
+    }
+
+    /// Append the metric families of service data to `text`
+    /// A text must not hold the same family twice, so each kind of data is written once per text
+    pub fn write_service(&self, text: &mut String, data: &ServiceDataInSecond) {
+        let mut pops: Vec<(&String, &ServiceStats)> = data.datacenter.iter().collect();
+        pops.sort_by(|a, b| a.0.cmp(b.0));
+
+        let series: Vec<(Vec<(&str, &str)>, &ServiceStats)> = if self.by_pop {
+            pops.iter()
+                .map(|(pop_name, stats)| (vec![("pop", pop_name.as_str())], *stats))
+                .collect()
+        } else {
+            vec![(vec![], &data.aggregated)]
+        };
+
+        for metric in ServiceStats::METRICS {
+            self.write_gauge(text, "", metric, &series, data.recorded);
+        }
+
+        let histograms: Vec<(Vec<(&str, &str)>, &LatencyHistogram)> = series
+            .iter()
+            .map(|(labels, stats)| (labels.clone(), &stats.miss_histogram))
+            .collect();
+        self.write_histogram(text, &histograms, data.recorded);
+    }
+
+    /// Append the metric families of origin data to `text`, with an `origin` label
+    /// A text must not hold the same family twice, so each kind of data is written once per text
+    pub fn write_origins(&self, text: &mut String, data: &OriginDataInSecond) {
+        let mut series: Vec<(Vec<(&str, &str)>, &OriginStats)> = Vec::new();
+
+        if self.by_pop {
+            for (pop_name, origins) in &data.datacenter {
+                for (origin_name, stats) in origins {
+                    let labels = vec![("pop", pop_name.as_str()), ("origin", origin_name.as_str())];
+                    series.push((labels, stats));
+                }
+            }
+        } else {
+            for (origin_name, stats) in &data.aggregated {
+                series.push((vec![("origin", origin_name.as_str())], stats));
+            }
+        }
+        series.sort_by(|a, b| a.0.cmp(&b.0));
+
+        for metric in OriginStats::METRICS {
+            self.write_gauge(text, "origin_", metric, &series, data.recorded);
+        }
+    }
+
+    /// Terminate a text, with `# EOF` for OpenMetrics
+    pub fn finish(&self, text: &mut String) {
+        if self.format == Format::OpenMetrics {
+            text.push_str("# EOF\n");
+        }
+    }
+
+    fn write_gauge<S>(
+        &self,
+        text: &mut String,
+        infix: &str,
+        metric: &Metric<S>,
+        series: &[(Vec<(&str, &str)>, &S)],
+        recorded: u64,
+    ) {
+        let (field, unit, divisor) = match metric.unit {
+            MetricUnit::Milliseconds => (
+                metric.name.strip_suffix("_ms").unwrap_or(metric.name),
+                MetricUnit::Seconds,
+                1000.0,
+            ),
+            unit => (metric.name, unit, 1.0),
+        };
+        let mut name = format!("{}_{}{}", self.prefix, infix, field);
+        if unit != MetricUnit::Count && !name.ends_with(unit.name()) {
+            write!(name, "_{}", unit.name()).ok();
+        }
+
+        self.write_header(text, &name, "gauge", unit, metric.help);
+        for (labels, stats) in series {
+            let value = (metric.value)(stats) / divisor;
+            self.write_sample(text, &name, labels, None, value, recorded);
+        }
+    }
+
+    fn write_histogram(
+        &self,
+        text: &mut String,
+        series: &[(Vec<(&str, &str)>, &LatencyHistogram)],
+        recorded: u64,
+    ) {
+        let name = format!("{}_miss_latency_seconds", self.prefix);
+        let help = "Latency of requests to origin";
+
+        self.write_header(text, &name, "histogram", MetricUnit::Seconds, help);
+        for (labels, histogram) in series {
+            let bucket_name = format!("{}_bucket", name);
+            for (le, count) in histogram.cumulative_buckets() {
+                let le = format_value(le);
+                let count = count as f64;
+                self.write_sample(text, &bucket_name, labels, Some(&le), count, recorded);
+            }
+            let count = histogram.total() as f64;
+            let sum = histogram.sum_ms() / 1000.0;
+            self.write_sample(
+                text,
+                &format!("{}_count", name),
+                labels,
+                None,
+                count,
+                recorded,
+            );
+            self.write_sample(text, &format!("{}_sum", name), labels, None, sum, recorded);
+        }
+    }
+
+    fn write_header(
+        &self,
+        text: &mut String,
+        name: &str,
+        kind: &str,
+        unit: MetricUnit,
+        help: &str,
+    ) {
+        match self.format {
+            Format::OpenMetrics => {
+                writeln!(text, "# TYPE {} {}", name, kind).ok();
+                if unit != MetricUnit::Count {
+                    writeln!(text, "# UNIT {} {}", name, unit.name()).ok();
+                }
+                writeln!(text, "# HELP {} {}", name, escape_help(help, self.format)).ok();
+            }
+            Format::Prometheus => write_header(text, name, kind, help),
+        }
+    }
+
+    fn write_sample(
+        &self,
+        text: &mut String,
+        name: &str,
+        labels: &[(&str, &str)],
+        le: Option<&str>,
+        value: f64,
+        recorded: u64,
+    ) {
+        let mut all_labels: Vec<(&str, &str)> = self
+            .labels
+            .iter()
+            .map(|(label, label_value)| (label.as_str(), label_value.as_str()))
+            .collect();
+        all_labels.extend_from_slice(labels);
+        all_labels.extend(le.map(|le| ("le", le)));
+
+        let timestamp = match (self.timestamps, self.format) {
+            (false, _) => None,
+            (true, Format::OpenMetrics) => Some(recorded),
+            (true, Format::Prometheus) => Some(recorded * 1000),
+        };
+        write_sample(text, name, &all_labels, value, timestamp);
+    }
+}
+
+impl Default for Encoder {
+    fn default() -> Encoder {
+        Encoder::new()
+    }
+}
+
+/// Write `# HELP` and `# TYPE` lines of the Prometheus text format
+pub(crate) fn write_header(text: &mut String, name: &str, kind: &str, help: &str) {
+    writeln!(
+        text,
+        "# HELP {} {}",
+        name,
+        escape_help(help, Format::Prometheus)
+    )
+    .ok();
+    writeln!(text, "# TYPE {} {}", name, kind).ok();
+}
+
+/// Write a sample line, the timestamp being in the unit of the format
+pub(crate) fn write_sample(
+    text: &mut String,
+    name: &str,
+    labels: &[(&str, &str)],
+    value: f64,
+    timestamp: Option<u64>,
+) {
+    text.push_str(name);
+
+    if !labels.is_empty() {
+        text.push('{');
+        for (index, (label, label_value)) in labels.iter().enumerate() {
+            if index > 0 {
+                text.push(',');
+            }
+            write!(text, "{}=\"{}\"", label, escape_label_value(label_value)).ok();
+        }
+        text.push('}');
+    }
+
+    write!(text, " {}", format_value(value)).ok();
+    if let Some(timestamp) = timestamp {
+        write!(text, " {}", timestamp).ok();
+    }
+    text.push('\n');
+}
+
+/// Format a sample value, infinities as `+Inf` and `-Inf`
+pub fn format_value(value: f64) -> String {
+    if value == f64::INFINITY {
+        "+Inf".to_string()
+    } else if value == f64::NEG_INFINITY {
+        "-Inf".to_string()
+    } else if value.is_nan() {
+        "NaN".to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+/// Escape a label value, backslashes, double quotes and line feeds being escaped
+pub fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn escape_help(help: &str, format: Format) -> String {
+    match format {
+        Format::OpenMetrics => escape_label_value(help),
+        Format::Prometheus => help.replace('\\', "\\\\").replace('\n', "\\n"),
+    }
+}
@@ -1,33 +1,20 @@
-use crate::client::CliObj;
-use crate::client::TimestampHolder;
-use anyhow::Result;
+use crate::metric::{metric_catalog, Metric, Metrics};
+use crate::pop::{Pop, Region};
+use crate::realtime::{RealtimeData, RtClient, RtResponse};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::hash::Hash;
+use std::ops::AddAssign;
 
 /// Response of real time data of origins of a service
-#[derive(Debug, Serialize, Deserialize)]
-pub struct OriginResponse {
-    /// Offset of entry timestamps from the current time due to processing time.
-    #[serde(alias = "AggregateDelay")]
-    pub aggregate_delay: u64,
-
-    /// A list of report entries, each representing one second of time.
-    #[serde(alias = "Data")]
-    pub data: Vec<OriginDataInSecond>,
-
-    /// Timestamp value to use for subsequent requests.
-    #[serde(alias = "Timestamp")]
-    pub timestamp: u64,
-}
+pub type OriginResponse = RtResponse<OriginDataInSecond>;
 
-impl TimestampHolder for OriginResponse {
-    fn get_timestamp(&self) -> u64 {
-        self.timestamp
-    }
+impl RealtimeData for OriginDataInSecond {
+    const PATH: &'static str = "/v1/origins";
 }
 
 /// Hold data of all origins in one second of a service
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct OriginDataInSecond {
     /// The Unix timestamp at which this record's data was generated
     #[serde(default)]
@@ -44,9 +31,81 @@ pub struct OriginDataInSecond {
     pub datacenter: HashMap<String, HashMap<String, OriginStats>>,
 }
 
+impl OriginDataInSecond {
+    /// Add measurements of another second to this one, e.g. to sum up a window of seconds
+    /// `recorded` becomes the latest of both
+    pub fn merge(&mut self, other: &OriginDataInSecond) {
+        self.recorded = self.recorded.max(other.recorded);
+
+        for (origin_name, stats) in &other.aggregated {
+            self.aggregated
+                .entry(origin_name.clone())
+                .or_default()
+                .merge(stats);
+        }
+
+        for (pop_name, origins) in &other.datacenter {
+            let pop_origins = self.datacenter.entry(pop_name.clone()).or_default();
+            for (origin_name, stats) in origins {
+                pop_origins
+                    .entry(origin_name.clone())
+                    .or_default()
+                    .merge(stats);
+            }
+        }
+    }
+
+    /// Iterate over measurements by POP, with POP names parsed into [`Pop`]
+    pub fn pops(&self) -> impl Iterator<Item = (Pop, &HashMap<String, OriginStats>)> {
+        self.datacenter
+            .iter()
+            .map(|(pop_name, origins)| (Pop::new(pop_name), origins))
+    }
+
+    /// Roll up measurements by POP into Fastly billing regions
+    /// Map of [`Region`] --> (map of `origin_name` --> [`OriginStats`])
+    /// POPs missing from the catalog are rolled up into [`Region::Unknown`]
+    pub fn by_region(&self) -> HashMap<Region, HashMap<String, OriginStats>> {
+        self.group_by(|pop| Some(pop.region()))
+    }
+
+    /// Roll up measurements by POP into groups of a user supplied map of `pop_name` --> `group_name`
+    /// POPs missing from the map are left out
+    pub fn by_group(
+        &self,
+        groups: &HashMap<String, String>,
+    ) -> HashMap<String, HashMap<String, OriginStats>> {
+        self.group_by(|pop| groups.get(pop.code()).cloned())
+    }
+
+    /// Roll up measurements by POP into the group returned by `group_of` for each POP
+    /// POPs for which `group_of` returns `None` are left out
+    pub fn group_by<K, F>(&self, group_of: F) -> HashMap<K, HashMap<String, OriginStats>>
+    where
+        K: Eq + Hash,
+        F: Fn(&Pop) -> Option<K>,
+    {
+        let mut groups: HashMap<K, HashMap<String, OriginStats>> = HashMap::new();
+
+        for (pop, origins) in self.pops() {
+            if let Some(group) = group_of(&pop) {
+                let group_origins = groups.entry(group).or_default();
+                for (origin_name, stats) in origins {
+                    group_origins
+                        .entry(origin_name.clone())
+                        .or_default()
+                        .merge(stats);
+                }
+            }
+        }
+
+        groups
+    }
+}
+
 /// Statistics of origin
 /// See explanation of members [here](https://developer.fastly.com/reference/api/metrics-stats/origin-inspector/real-time/#measurements-data-model)
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct OriginStats {
     #[serde(default)]
     pub resp_body_bytes: u64,
@@ -58,114 +117,144 @@ pub struct OriginStats {
     pub responses: u64,
 
     #[serde(default)]
-    status_1xx: u64,
+    pub status_1xx: u64,
 
     #[serde(default)]
-    status_200: u64,
+    pub status_200: u64,
 
     #[serde(default)]
-    status_204: u64,
+    pub status_204: u64,
 
     #[serde(default)]
-    status_206: u64,
+    pub status_206: u64,
 
     #[serde(default)]
-    status_2xx: u64,
+    pub status_2xx: u64,
 
     #[serde(default)]
-    status_301: u64,
+    pub status_301: u64,
 
     #[serde(default)]
-    status_302: u64,
+    pub status_302: u64,
 
     #[serde(default)]
-    status_304: u64,
+    pub status_304: u64,
 
     #[serde(default)]
-    status_3xx: u64,
+    pub status_3xx: u64,
 
     #[serde(default)]
-    status_400: u64,
+    pub status_400: u64,
 
     #[serde(default)]
-    status_401: u64,
+    pub status_401: u64,
 
     #[serde(default)]
-    status_403: u64,
+    pub status_403: u64,
 
     #[serde(default)]
-    status_404: u64,
+    pub status_404: u64,
 
     #[serde(default)]
-    status_416: u64,
+    pub status_416: u64,
 
     #[serde(default)]
-    status_429: u64,
+    pub status_429: u64,
 
     #[serde(default)]
-    status_4xx: u64,
+    pub status_4xx: u64,
 
     #[serde(default)]
-    status_500: u64,
+    pub status_500: u64,
 
     #[serde(default)]
-    status_501: u64,
+    pub status_501: u64,
 
     #[serde(default)]
-    status_502: u64,
+    pub status_502: u64,
 
     #[serde(default)]
-    status_503: u64,
+    pub status_503: u64,
 
     #[serde(default)]
-    status_504: u64,
+    pub status_504: u64,
 
     #[serde(default)]
-    status_505: u64,
+    pub status_505: u64,
 
     #[serde(default)]
-    status_5xx: u64,
-}
-
-/// Client to get origin real time data
-pub struct OriginClient {
-    cli: CliObj,
+    pub status_5xx: u64,
 }
 
-impl OriginClient {
-    const ENDPOINT: &'static str = "https://rt.fastly.com/v1/origins";
-
-    /// Create an OriginClient object
-    pub fn new(api_key: &str, service_id: &str) -> Result<OriginClient> {
-        Ok(OriginClient {
-            cli: CliObj::new(api_key, service_id, OriginClient::ENDPOINT)?,
-        })
-    }
-
-    /// Reset internal timestamp which used to track consecutive stats to 0
-    /// After calling this function, calling get_stats_consecutive function will be the first call
-    pub fn reset_stats_consecutive(&mut self) {
-        self.cli.reset_stats_consecutive();
-    }
-
-    /// The first call of the function will get data of latest one second
-    /// The consecutive call of the function will get consecutive data of last call to last second
-    pub async fn get_stats_consecutive(&mut self) -> Result<OriginResponse> {
-        self.cli.get_stats_consecutive().await
-    }
-
-    /// Get stats from start_timestamp to latest timestamp available for a service
-    pub async fn get_stats_from(&mut self, start_timestamp: u64) -> Result<OriginResponse> {
-        self.cli.get_stats_from(start_timestamp).await
+impl OriginStats {
+    /// Add measurements of another [`OriginStats`] to this one, e.g. to sum up POPs or seconds
+    pub fn merge(&mut self, other: &OriginStats) {
+        self.resp_body_bytes += other.resp_body_bytes;
+        self.resp_header_bytes += other.resp_header_bytes;
+        self.responses += other.responses;
+        self.status_1xx += other.status_1xx;
+        self.status_200 += other.status_200;
+        self.status_204 += other.status_204;
+        self.status_206 += other.status_206;
+        self.status_2xx += other.status_2xx;
+        self.status_301 += other.status_301;
+        self.status_302 += other.status_302;
+        self.status_304 += other.status_304;
+        self.status_3xx += other.status_3xx;
+        self.status_400 += other.status_400;
+        self.status_401 += other.status_401;
+        self.status_403 += other.status_403;
+        self.status_404 += other.status_404;
+        self.status_416 += other.status_416;
+        self.status_429 += other.status_429;
+        self.status_4xx += other.status_4xx;
+        self.status_500 += other.status_500;
+        self.status_501 += other.status_501;
+        self.status_502 += other.status_502;
+        self.status_503 += other.status_503;
+        self.status_504 += other.status_504;
+        self.status_505 += other.status_505;
+        self.status_5xx += other.status_5xx;
     }
+}
 
-    /// Get data for the 120 seconds preceding the latest timestamp available for a service.
-    pub async fn get_stats_120s(&self) -> Result<OriginResponse> {
-        self.cli.get_stats_120s().await
+impl AddAssign<&OriginStats> for OriginStats {
+    fn add_assign(&mut self, other: &OriginStats) {
+        self.merge(other);
     }
+}
 
-    /// Get data for the 120 seconds preceding the latest timestamp available for a service, up to a maximum of max_entries entries.
-    pub async fn get_stats_max(&self, max_entries: u64) -> Result<OriginResponse> {
-        self.cli.get_stats_max(max_entries).await
-    }
+impl Metrics for OriginStats {
+    const METRICS: &'static [Metric<OriginStats>] = metric_catalog! { OriginStats;
+        resp_body_bytes: Bytes, "Total body bytes received from origin";
+        resp_header_bytes: Bytes, "Total header bytes received from origin";
+        responses: Count, "Number of responses received from origin";
+        status_1xx: Count, "Number of \"Informational\" category status codes received from origin";
+        status_200: Count, "Number of responses received from origin with status code 200";
+        status_204: Count, "Number of responses received from origin with status code 204";
+        status_206: Count, "Number of responses received from origin with status code 206";
+        status_2xx: Count, "Number of \"Success\" category status codes received from origin";
+        status_301: Count, "Number of responses received from origin with status code 301";
+        status_302: Count, "Number of responses received from origin with status code 302";
+        status_304: Count, "Number of responses received from origin with status code 304";
+        status_3xx: Count, "Number of \"Redirection\" category status codes received from origin";
+        status_400: Count, "Number of responses received from origin with status code 400";
+        status_401: Count, "Number of responses received from origin with status code 401";
+        status_403: Count, "Number of responses received from origin with status code 403";
+        status_404: Count, "Number of responses received from origin with status code 404";
+        status_416: Count, "Number of responses received from origin with status code 416";
+        status_429: Count, "Number of responses received from origin with status code 429";
+        status_4xx: Count, "Number of \"Client Error\" category status codes received from origin";
+        status_500: Count, "Number of responses received from origin with status code 500";
+        status_501: Count, "Number of responses received from origin with status code 501";
+        status_502: Count, "Number of responses received from origin with status code 502";
+        status_503: Count, "Number of responses received from origin with status code 503";
+        status_504: Count, "Number of responses received from origin with status code 504";
+        status_505: Count, "Number of responses received from origin with status code 505";
+        status_5xx: Count, "Number of \"Server Error\" category status codes received from origin";
+    };
 }
+
+/// Client to get origin real time data
+/// Query methods are those of [`RtClient`], which also implements [`crate::realtime::RealtimeClient`]
+pub type OriginClient = RtClient<OriginDataInSecond>;
@@ -0,0 +1,360 @@
+use crate::histogram::LatencyHistogram;
+use crate::metric::{Metric, MetricKind, MetricUnit, Metrics};
+use crate::origin::{OriginDataInSecond, OriginResponse, OriginStats};
+use crate::realtime::RtResponse;
+use crate::service::{ServiceDataInSecond, ServiceResponse, ServiceStats};
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+/// `AGGREGATION_TEMPORALITY_DELTA`, each data point holds the measurements of its own second
+const DELTA: u64 = 1;
+
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
+
+/// `recorded` of a second, and the attributes and stats of each of its series
+type Second<'a, S> = (u64, Vec<(Vec<Value>, &'a S)>);
+
+/// Configuration of an [`OtlpExporter`]
+#[derive(Debug, Clone)]
+pub struct OtlpConfig {
+    /// Base URL of the collector, metrics are posted to `{endpoint}/v1/metrics`
+    pub endpoint: String,
+
+    /// Headers added to every request, e.g. for authentication
+    pub headers: Vec<(String, String)>,
+
+    /// Export measurements by POP with a `fastly.pop` attribute, instead of aggregated across POPs
+    pub by_pop: bool,
+
+    pub timeout: Duration,
+}
+
+impl Default for OtlpConfig {
+    fn default() -> OtlpConfig {
+        OtlpConfig {
+            endpoint: "http://localhost:4318".to_string(),
+            headers: Vec::new(),
+            by_pop: false,
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Resource the exported metrics belong to, a Fastly service
+#[derive(Debug, Clone)]
+pub struct Resource {
+    pub service_id: String,
+
+    pub service_name: Option<String>,
+
+    /// Additional resource attributes, e.g. `deployment.environment`
+    pub attributes: Vec<(String, String)>,
+}
+
+impl Resource {
+    pub fn service(service_id: &str) -> Resource {
+        Resource {
+            service_id: service_id.to_string(),
+            service_name: None,
+            attributes: Vec::new(),
+        }
+    }
+
+    pub fn with_name(mut self, service_name: &str) -> Resource {
+        self.service_name = Some(service_name.to_string());
+        self
+    }
+
+    pub fn with_attribute(mut self, key: &str, value: &str) -> Resource {
+        self.attributes.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    fn to_json(&self) -> Value {
+        let mut attributes = vec![attribute("fastly.service.id", &self.service_id)];
+        if let Some(service_name) = &self.service_name {
+            attributes.push(attribute("fastly.service.name", service_name));
+        }
+        for (key, value) in &self.attributes {
+            attributes.push(attribute(key, value));
+        }
+
+        json!({ "attributes": attributes })
+    }
+}
+
+/// Pushes real time data to an OpenTelemetry collector over OTLP/HTTP, with the JSON encoding
+///
+/// ```no_run
+/// use fastly_rt::otlp::{OtlpConfig, OtlpExporter, Resource};
+/// use fastly_rt::service::ServiceClient;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let mut rt = ServiceClient::new("api_key", "service_id").unwrap();
+///     let otlp = OtlpExporter::new(OtlpConfig::default()).unwrap();
+///     let resource = Resource::service("service_id").with_name("www");
+///
+///     loop {
+///         let rt_data = rt.get_stats_consecutive().await.unwrap();
+///         otlp.export_service(&resource, &rt_data).await.unwrap();
+///     }
+/// }
+/// ```
+pub struct OtlpExporter {
+    config: OtlpConfig,
+    client: reqwest::Client,
+}
+
+impl OtlpExporter {
+    pub fn new(config: OtlpConfig) -> Result<OtlpExporter> {
+        let client = reqwest::Client::builder().timeout(config.timeout).build()?;
+
+        Ok(OtlpExporter { config, client })
+    }
+
+    /// Push the service data of `response`, nothing is sent when it holds no data
+    pub async fn export_service(
+        &self,
+        resource: &Resource,
+        response: &ServiceResponse,
+    ) -> Result<()> {
+        if response.data.is_empty() {
+            return Ok(());
+        }
+        self.send(service_request(resource, response, self.config.by_pop))
+            .await
+    }
+
+    /// Push the origin data of `response`, nothing is sent when it holds no data
+    pub async fn export_origins(
+        &self,
+        resource: &Resource,
+        response: &OriginResponse,
+    ) -> Result<()> {
+        if response.data.is_empty() {
+            return Ok(());
+        }
+        self.send(origin_request(resource, response, self.config.by_pop))
+            .await
+    }
+
+    async fn send(&self, request: Value) -> Result<()> {
+        let url = format!("{}/v1/metrics", self.config.endpoint.trim_end_matches('/'));
+        let mut builder = self.client.post(url).json(&request);
+        for (name, value) in &self.config.headers {
+            builder = builder.header(name, value);
+        }
+
+        let response = builder.send().await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow!("OTLP export failed with {}: {}", status, body));
+        }
+
+        Ok(())
+    }
+}
+
+/// Convert service data to the JSON of an OTLP `ExportMetricsServiceRequest`
+///
+/// Every field of [`ServiceStats`] becomes a delta sum named `fastly.rt.{field}`, or a gauge for
+/// gauge metrics such as `compute_ram_used`, `miss_histogram` the `fastly.rt.miss_latency` histogram
+/// in seconds, and `aggregate_delay` the `fastly.rt.aggregate_delay` gauge.
+pub fn service_request<'a>(
+    resource: &Resource,
+    response: &'a ServiceResponse,
+    by_pop: bool,
+) -> Value {
+    let series = |data: &'a ServiceDataInSecond| -> Vec<(Vec<Value>, &'a ServiceStats)> {
+        if by_pop {
+            let mut pops: Vec<_> = data.datacenter.iter().collect();
+            pops.sort_by(|a, b| a.0.cmp(b.0));
+            pops.into_iter()
+                .map(|(pop_name, stats)| (vec![attribute("fastly.pop", pop_name)], stats))
+                .collect()
+        } else {
+            vec![(vec![], &data.aggregated)]
+        }
+    };
+    let seconds: Vec<Second<ServiceStats>> = response
+        .data
+        .iter()
+        .map(|data| (data.recorded, series(data)))
+        .collect();
+
+    let mut metrics = numbers("fastly.rt.", &seconds);
+
+    let data_points: Vec<Value> = seconds
+        .iter()
+        .flat_map(|(recorded, series)| {
+            series.iter().map(|(attributes, stats)| {
+                histogram_point(*recorded, attributes, &stats.miss_histogram)
+            })
+        })
+        .collect();
+    metrics.push(json!({
+        "name": "fastly.rt.miss_latency",
+        "description": "Latency of requests to origin",
+        "unit": "s",
+        "histogram": { "aggregationTemporality": DELTA, "dataPoints": data_points },
+    }));
+
+    metrics.push(aggregate_delay(response));
+    request(resource, metrics)
+}
+
+/// Convert origin data to the JSON of an OTLP `ExportMetricsServiceRequest`
+///
+/// Every field of [`OriginStats`] becomes a delta sum named `fastly.rt.origin.{field}`,
+/// with a `fastly.origin` attribute.
+pub fn origin_request<'a>(
+    resource: &Resource,
+    response: &'a OriginResponse,
+    by_pop: bool,
+) -> Value {
+    let series = |data: &'a OriginDataInSecond| -> Vec<(Vec<Value>, &'a OriginStats)> {
+        let mut series: Vec<(Option<&String>, &String, &OriginStats)> = if by_pop {
+            data.datacenter
+                .iter()
+                .flat_map(|(pop_name, origins)| {
+                    origins
+                        .iter()
+                        .map(move |(origin_name, stats)| (Some(pop_name), origin_name, stats))
+                })
+                .collect()
+        } else {
+            data.aggregated
+                .iter()
+                .map(|(origin_name, stats)| (None, origin_name, stats))
+                .collect()
+        };
+        series.sort_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)));
+
+        series
+            .into_iter()
+            .map(|(pop_name, origin_name, stats)| {
+                let mut attributes: Vec<Value> = pop_name
+                    .map(|pop_name| attribute("fastly.pop", pop_name))
+                    .into_iter()
+                    .collect();
+                attributes.push(attribute("fastly.origin", origin_name));
+                (attributes, stats)
+            })
+            .collect()
+    };
+    let seconds: Vec<Second<OriginStats>> = response
+        .data
+        .iter()
+        .map(|data| (data.recorded, series(data)))
+        .collect();
+
+    let mut metrics = numbers("fastly.rt.origin.", &seconds);
+    metrics.push(aggregate_delay(response));
+    request(resource, metrics)
+}
+
+fn request(resource: &Resource, metrics: Vec<Value>) -> Value {
+    json!({
+        "resourceMetrics": [{
+            "resource": resource.to_json(),
+            "scopeMetrics": [{
+                "scope": { "name": env!("CARGO_PKG_NAME"), "version": env!("CARGO_PKG_VERSION") },
+                "metrics": metrics,
+            }],
+        }],
+    })
+}
+
+/// One monotonic delta sum, or gauge, per metric of `S`, with a data point per second and series
+fn numbers<S: Metrics>(prefix: &str, seconds: &[Second<S>]) -> Vec<Value> {
+    S::METRICS
+        .iter()
+        .map(|metric| {
+            let data_points: Vec<Value> = seconds
+                .iter()
+                .flat_map(|(recorded, series)| {
+                    series.iter().map(move |(attributes, stats)| {
+                        number_point(*recorded, attributes, metric, stats)
+                    })
+                })
+                .collect();
+
+            let mut json = json!({
+                "name": format!("{}{}", prefix, metric.name),
+                "description": metric.help,
+                "unit": unit(metric.unit),
+            });
+            match metric.kind {
+                MetricKind::Counter => {
+                    json["sum"] = json!({
+                        "aggregationTemporality": DELTA,
+                        "isMonotonic": true,
+                        "dataPoints": data_points,
+                    })
+                }
+                MetricKind::Gauge => json["gauge"] = json!({ "dataPoints": data_points }),
+            }
+            json
+        })
+        .collect()
+}
+
+fn number_point<S>(recorded: u64, attributes: &[Value], metric: &Metric<S>, stats: &S) -> Value {
+    json!({
+        "attributes": attributes,
+        "startTimeUnixNano": (recorded * NANOS_PER_SECOND).to_string(),
+        "timeUnixNano": ((recorded + 1) * NANOS_PER_SECOND).to_string(),
+        "asDouble": (metric.value)(stats),
+    })
+}
+
+fn histogram_point(recorded: u64, attributes: &[Value], histogram: &LatencyHistogram) -> Value {
+    let mut bounds = Vec::new();
+    let mut counts = Vec::new();
+    for (upper_ms, count) in histogram.buckets() {
+        bounds.push(upper_ms as f64 / 1000.0);
+        counts.push(count.to_string());
+    }
+    counts.push("0".to_string());
+
+    json!({
+        "attributes": attributes,
+        "startTimeUnixNano": (recorded * NANOS_PER_SECOND).to_string(),
+        "timeUnixNano": ((recorded + 1) * NANOS_PER_SECOND).to_string(),
+        "count": histogram.total().to_string(),
+        "sum": histogram.sum_ms() / 1000.0,
+        "bucketCounts": counts,
+        "explicitBounds": bounds,
+    })
+}
+
+fn aggregate_delay<D>(response: &RtResponse<D>) -> Value {
+    json!({
+        "name": "fastly.rt.aggregate_delay",
+        "description": "Offset of the latest data from the current time due to processing time",
+        "unit": "s",
+        "gauge": {
+            "dataPoints": [{
+                "timeUnixNano": (response.timestamp * NANOS_PER_SECOND).to_string(),
+                "asInt": response.aggregate_delay.to_string(),
+            }],
+        },
+    })
+}
+
+/// UCUM unit of a metric, as used by OpenTelemetry
+fn unit(unit: MetricUnit) -> &'static str {
+    match unit {
+        MetricUnit::Count => "1",
+        MetricUnit::Bytes => "By",
+        MetricUnit::Seconds => "s",
+        MetricUnit::Milliseconds => "ms",
+    }
+}
+
+fn attribute(key: &str, value: &str) -> Value {
+    json!({ "key": key, "value": { "stringValue": value } })
+}
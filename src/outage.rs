@@ -0,0 +1,378 @@
+use crate::service::{ServiceDataInSecond, ServiceStats};
+use crate::timeseries::{Scope, TimeSeriesStore};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+
+/// Configuration of an [`OutageDetector`]
+#[derive(Debug, Clone)]
+pub struct OutageConfig {
+    /// Weight of each second in the baseline shares and error ratios, from 0 to 1
+    pub alpha: f64,
+
+    /// Number of seconds of a service learned before flagging its POPs
+    pub warmup: u64,
+
+    /// Number of seconds summed into the current share of each POP
+    pub window: u64,
+
+    /// A POP missing from `datacenter` for this many seconds has vanished
+    pub vanish_after: u64,
+
+    /// A POP whose share falls under this fraction of its baseline share has dropped
+    pub drop_ratio: f64,
+
+    /// POPs with a smaller baseline share are not flagged for traffic, their share being noise
+    pub min_share: f64,
+
+    /// Ratio of `status_5xx` to `requests` from which the errors of a POP spike...
+    pub error_ratio: f64,
+
+    /// ...provided it is also this many times its baseline ratio
+    pub error_increase: f64,
+
+    /// POPs with fewer requests over the window are not flagged for errors, their ratio being noise
+    pub min_requests: u64,
+
+    /// A POP missing for this many seconds is forgotten, e.g. once decommissioned, ending its problems
+    /// without a recovery event, and learned again if it comes back
+    pub forget_after: u64,
+}
+
+impl Default for OutageConfig {
+    fn default() -> OutageConfig {
+        OutageConfig {
+            alpha: 0.01,
+            warmup: 300,
+            window: 10,
+            vanish_after: 5,
+            drop_ratio: 0.5,
+            min_share: 0.01,
+            error_ratio: 0.05,
+            error_increase: 5.0,
+            min_requests: 100,
+            forget_after: 3600,
+        }
+    }
+}
+
+/// What happens to a POP
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum OutageKind {
+    /// The POP is missing from `datacenter`
+    Vanished,
+
+    /// The share of the traffic of the POP fell sharply
+    Dropped,
+
+    /// The ratio of 5xx responses of the POP rose sharply
+    Errors,
+}
+
+/// Start or end of a problem of a POP
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutageEvent {
+    pub service: String,
+
+    pub pop: String,
+
+    pub kind: OutageKind,
+
+    /// The problem ended
+    pub recovered: bool,
+
+    pub recorded: u64,
+
+    /// Share of the traffic of the service the POP normally serves
+    pub baseline_share: f64,
+
+    /// Share over the last seconds of the window
+    pub share: f64,
+
+    /// Ratio of 5xx responses over the last seconds of the window
+    pub error_ratio: f64,
+
+    /// POPs which took the traffic, with their gain of share, the largest first,
+    /// when a traffic problem starts
+    pub absorbed_by: Vec<(String, f64)>,
+}
+
+impl fmt::Display for OutageEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = if self.recovered {
+            "recovered"
+        } else {
+            "started"
+        };
+        write!(
+            f,
+            "{:?} {} of {} {}, share {:.3} of {:.3}, 5xx {:.3}",
+            self.kind,
+            state,
+            self.pop,
+            self.service,
+            self.share,
+            self.baseline_share,
+            self.error_ratio
+        )?;
+        for (index, (pop_name, gain)) in self.absorbed_by.iter().enumerate() {
+            let separator = if index == 0 { ", absorbed by " } else { ", " };
+            write!(f, "{}{} +{:.3}", separator, pop_name, gain)?;
+        }
+        Ok(())
+    }
+}
+
+/// Normal traffic of a POP
+#[derive(Debug, Clone, Default)]
+struct Baseline {
+    share: f64,
+    error_ratio: f64,
+    last_seen: u64,
+
+    /// First second the POP was seen since it last went missing
+    seen_since: u64,
+}
+
+/// What was learned of a service
+#[derive(Default)]
+struct ServiceState {
+    learned: u64,
+    latest: u64,
+    pops: HashMap<String, Baseline>,
+    problems: BTreeSet<(String, OutageKind)>,
+}
+
+/// Learns the POPs of services and their share of traffic, and flags POPs which vanish,
+/// drop sharply or spike in errors
+///
+/// While a POP has a problem, its baseline is not updated, so an outage is not learned as normal,
+/// until the POP has been missing long enough to be forgotten, see [`OutageConfig::forget_after`].
+///
+/// ```
+/// use fastly_rt::outage::{OutageConfig, OutageDetector, OutageKind};
+/// use fastly_rt::synth::{Generator, Incident, IncidentKind, SynthConfig};
+///
+/// let generator = Generator::new(SynthConfig {
+///     incidents: vec![Incident {
+///         start: 1_700_000_600,
+///         duration: 60,
+///         kind: IncidentKind::PopOutage { pop: "NRT".to_string() },
+///     }],
+///     ..Default::default()
+/// });
+/// let mut detector = OutageDetector::new(OutageConfig::default());
+///
+/// let mut events = Vec::new();
+/// for recorded in 1_700_000_000..1_700_000_700 {
+///     events.extend(detector.observe("www", &generator.service_second(recorded)));
+/// }
+///
+/// assert_eq!(events[0].pop, "NRT");
+/// assert_eq!(events[0].kind, OutageKind::Vanished);
+/// assert_eq!(events[0].absorbed_by[0].0, "HKG");
+/// ```
+pub struct OutageDetector {
+    config: OutageConfig,
+    store: TimeSeriesStore<ServiceDataInSecond>,
+    services: HashMap<String, ServiceState>,
+}
+
+impl OutageDetector {
+    pub fn new(config: OutageConfig) -> OutageDetector {
+        OutageDetector {
+            store: TimeSeriesStore::new(config.window.max(1) as usize),
+            config,
+            services: HashMap::new(),
+        }
+    }
+
+    pub fn config(&self) -> &OutageConfig {
+        &self.config
+    }
+
+    /// Learn a new second of `service`, returns the problems ending then those starting, by POP
+    ///
+    /// Seconds not later than the last one observed are ignored. A POP coming back is not flagged
+    /// as dropped before it has been seen for a whole window.
+    pub fn observe(&mut self, service: &str, data: &ServiceDataInSecond) -> Vec<OutageEvent> {
+        let config = &self.config;
+        let state = self.services.entry(service.to_string()).or_default();
+        if data.recorded <= state.latest {
+            return Vec::new();
+        }
+        state.latest = data.recorded;
+        self.store.insert(service, data);
+
+        // Shares and error ratios over the window
+        let total = self
+            .store
+            .sum(service, &Scope::aggregated(), config.window)
+            .map_or(0, |stats| stats.requests);
+        let window: HashMap<String, ServiceStats> = state
+            .pops
+            .keys()
+            .map(String::as_str)
+            .chain(data.datacenter.keys().map(String::as_str))
+            .map(|pop_name| {
+                let stats = self
+                    .store
+                    .sum(service, &Scope::pop(pop_name), config.window)
+                    .unwrap_or_default();
+                (pop_name.to_string(), stats)
+            })
+            .collect();
+        let share = |pop_name: &str| match (window.get(pop_name), total) {
+            (Some(stats), 1..) => stats.requests as f64 / total as f64,
+            _ => 0.0,
+        };
+        let error_ratio = |pop_name: &str| match window.get(pop_name) {
+            Some(stats) if stats.requests > 0 => stats.status_5xx as f64 / stats.requests as f64,
+            _ => 0.0,
+        };
+
+        for pop_name in data.datacenter.keys() {
+            let baseline = state.pops.entry(pop_name.to_string()).or_default();
+            if baseline.last_seen + 1 < data.recorded {
+                baseline.seen_since = data.recorded;
+            }
+            baseline.last_seen = data.recorded;
+        }
+        state
+            .pops
+            .retain(|_, baseline| data.recorded - baseline.last_seen < config.forget_after);
+        let pops = &state.pops;
+        state
+            .problems
+            .retain(|(pop_name, _)| pops.contains_key(pop_name));
+
+        // Problems of each POP
+        let mut problems = BTreeSet::new();
+        if state.learned >= config.warmup && total > 0 {
+            for (pop_name, baseline) in &state.pops {
+                let share = share(pop_name);
+                if baseline.share >= config.min_share {
+                    if data.recorded - baseline.last_seen >= config.vanish_after {
+                        problems.insert((pop_name.clone(), OutageKind::Vanished));
+                    } else if data.recorded - baseline.seen_since >= config.window
+                        && share < config.drop_ratio * baseline.share
+                    {
+                        problems.insert((pop_name.clone(), OutageKind::Dropped));
+                    }
+                }
+
+                let requests = window.get(pop_name).map_or(0, |stats| stats.requests);
+                let errors = error_ratio(pop_name);
+                if requests >= config.min_requests
+                    && errors >= config.error_ratio
+                    && errors >= config.error_increase * baseline.error_ratio
+                {
+                    problems.insert((pop_name.clone(), OutageKind::Errors));
+                }
+            }
+        }
+
+        let mut events = Vec::new();
+        let event = |pop_name: &str, kind: OutageKind, recovered: bool| {
+            let baseline_share = state
+                .pops
+                .get(pop_name)
+                .map_or(0.0, |baseline| baseline.share);
+            let absorbed_by = if recovered || kind == OutageKind::Errors {
+                Vec::new()
+            } else {
+                let mut gains: Vec<(String, f64)> = state
+                    .pops
+                    .iter()
+                    .filter(|(other, _)| *other != pop_name)
+                    .map(|(other, baseline)| (other.clone(), share(other) - baseline.share))
+                    .filter(|(_, gain)| *gain >= config.min_share)
+                    .collect();
+                gains.sort_by(|(a, a_gain), (b, b_gain)| b_gain.total_cmp(a_gain).then(a.cmp(b)));
+                gains
+            };
+
+            OutageEvent {
+                service: service.to_string(),
+                pop: pop_name.to_string(),
+                kind,
+                recovered,
+                recorded: data.recorded,
+                baseline_share,
+                share: share(pop_name),
+                error_ratio: error_ratio(pop_name),
+                absorbed_by,
+            }
+        };
+        for (pop_name, kind) in state.problems.difference(&problems) {
+            events.push(event(pop_name, *kind, true));
+        }
+        for (pop_name, kind) in problems.difference(&state.problems) {
+            events.push(event(pop_name, *kind, false));
+        }
+
+        // Learn the POPs without problems from this second
+        let requests = data.aggregated.requests;
+        if requests > 0 {
+            for (pop_name, baseline) in &mut state.pops {
+                if problems
+                    .iter()
+                    .any(|(problem_pop, _)| problem_pop == pop_name)
+                {
+                    continue;
+                }
+                let stats = data.datacenter.get(pop_name);
+                let second_share =
+                    stats.map_or(0.0, |stats| stats.requests as f64 / requests as f64);
+                let second_errors = match stats {
+                    Some(stats) if stats.requests > 0 => {
+                        stats.status_5xx as f64 / stats.requests as f64
+                    }
+                    _ => 0.0,
+                };
+                if state.learned == 0 {
+                    baseline.share = second_share;
+                    baseline.error_ratio = second_errors;
+                } else {
+                    baseline.share += config.alpha * (second_share - baseline.share);
+                    baseline.error_ratio += config.alpha * (second_errors - baseline.error_ratio);
+                }
+            }
+            state.learned += 1;
+        }
+        state.problems = problems;
+
+        events
+    }
+
+    /// POPs normally serving `service` with their share of traffic, the largest first
+    pub fn baseline(&self, service: &str) -> Vec<(&str, f64)> {
+        let mut pops: Vec<(&str, f64)> = self
+            .services
+            .get(service)
+            .map(|state| {
+                state
+                    .pops
+                    .iter()
+                    .filter(|(_, baseline)| baseline.share >= self.config.min_share)
+                    .map(|(pop_name, baseline)| (pop_name.as_str(), baseline.share))
+                    .collect()
+            })
+            .unwrap_or_default();
+        pops.sort_by(|(a, a_share), (b, b_share)| b_share.total_cmp(a_share).then(a.cmp(b)));
+        pops
+    }
+
+    /// Current problems of `service`, by POP
+    pub fn problems(&self, service: &str) -> Vec<(&str, OutageKind)> {
+        self.services
+            .get(service)
+            .map(|state| {
+                state
+                    .problems
+                    .iter()
+                    .map(|(pop_name, kind)| (pop_name.as_str(), *kind))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,314 @@
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::fmt;
+use std::str::FromStr;
+
+/// Continent where a POP is located
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Continent {
+    Africa,
+    Asia,
+    Europe,
+    NorthAmerica,
+    Oceania,
+    SouthAmerica,
+    /// POP code not found in the catalog
+    Unknown,
+}
+
+/// Fastly billing region of a POP
+/// See [pricing regions](https://docs.fastly.com/en/guides/how-fastlys-pricing-works)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Region {
+    NorthAmerica,
+    Europe,
+    Asia,
+    India,
+    SouthKorea,
+    AustraliaNewZealand,
+    SouthAmerica,
+    Africa,
+    /// POP code not found in the catalog
+    Unknown,
+}
+
+impl Region {
+    /// All regions, in declaration order
+    pub const ALL: [Region; 9] = [
+        Region::NorthAmerica,
+        Region::Europe,
+        Region::Asia,
+        Region::India,
+        Region::SouthKorea,
+        Region::AustraliaNewZealand,
+        Region::SouthAmerica,
+        Region::Africa,
+        Region::Unknown,
+    ];
+
+    /// Human readable name of the region
+    pub fn name(&self) -> &'static str {
+        match self {
+            Region::NorthAmerica => "North America",
+            Region::Europe => "Europe",
+            Region::Asia => "Asia",
+            Region::India => "India",
+            Region::SouthKorea => "South Korea",
+            Region::AustraliaNewZealand => "Australia & New Zealand",
+            Region::SouthAmerica => "South America",
+            Region::Africa => "Africa",
+            Region::Unknown => "Unknown",
+        }
+    }
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Geographic metadata of a Fastly POP
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PopInfo {
+    /// POP code as used in `datacenter` maps, e.g. `NRT`
+    pub code: &'static str,
+
+    pub city: &'static str,
+
+    /// ISO 3166-1 alpha-2 country code
+    pub country: &'static str,
+
+    pub continent: Continent,
+
+    pub region: Region,
+
+    pub latitude: f64,
+
+    pub longitude: f64,
+}
+
+/// Identifier of a Fastly POP (datacenter)
+///
+/// Codes missing from the embedded catalog are still valid `Pop` values,
+/// their metadata is simply unknown.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub struct Pop {
+    code: String,
+}
+
+impl Pop {
+    /// Create a POP from its code, the code is case insensitive
+    pub fn new(code: &str) -> Pop {
+        Pop {
+            code: code.trim().to_ascii_uppercase(),
+        }
+    }
+
+    /// The POP code, e.g. `NRT`
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    /// Metadata of the POP, `None` when the code is not in the catalog
+    pub fn info(&self) -> Option<&'static PopInfo> {
+        CATALOG
+            .binary_search_by(|info| info.code.cmp(self.code.as_str()))
+            .ok()
+            .map(|index| &CATALOG[index])
+    }
+
+    pub fn is_known(&self) -> bool {
+        self.info().is_some()
+    }
+
+    pub fn city(&self) -> Option<&'static str> {
+        self.info().map(|info| info.city)
+    }
+
+    pub fn country(&self) -> Option<&'static str> {
+        self.info().map(|info| info.country)
+    }
+
+    pub fn continent(&self) -> Continent {
+        self.info()
+            .map(|info| info.continent)
+            .unwrap_or(Continent::Unknown)
+    }
+
+    pub fn region(&self) -> Region {
+        self.info()
+            .map(|info| info.region)
+            .unwrap_or(Region::Unknown)
+    }
+
+    /// (latitude, longitude) of the POP
+    pub fn coordinates(&self) -> Option<(f64, f64)> {
+        self.info().map(|info| (info.latitude, info.longitude))
+    }
+
+    /// Great circle distance to another POP in kilometers
+    pub fn distance_km(&self, other: &Pop) -> Option<f64> {
+        const EARTH_RADIUS_KM: f64 = 6371.0;
+
+        let (lat1, lon1) = self.coordinates()?;
+        let (lat2, lon2) = other.coordinates()?;
+        let (lat1, lat2) = (lat1.to_radians(), lat2.to_radians());
+        let d_lat = lat2 - lat1;
+        let d_lon = (lon2 - lon1).to_radians();
+
+        let a = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+        Some(2.0 * EARTH_RADIUS_KM * a.sqrt().asin())
+    }
+
+    /// All POPs of the embedded catalog, sorted by code
+    pub fn catalog() -> &'static [PopInfo] {
+        CATALOG
+    }
+}
+
+impl fmt::Display for Pop {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.code)
+    }
+}
+
+impl FromStr for Pop {
+    type Err = Infallible;
+
+    fn from_str(code: &str) -> Result<Pop, Infallible> {
+        Ok(Pop::new(code))
+    }
+}
+
+impl From<&str> for Pop {
+    fn from(code: &str) -> Pop {
+        Pop::new(code)
+    }
+}
+
+impl From<String> for Pop {
+    fn from(code: String) -> Pop {
+        Pop::new(&code)
+    }
+}
+
+impl From<Pop> for String {
+    fn from(pop: Pop) -> String {
+        pop.code
+    }
+}
+
+macro_rules! pops {
+    ($(($code:literal, $city:literal, $country:literal, $continent:ident, $region:ident, $lat:literal, $lon:literal)),* $(,)?) => {
+        &[$(PopInfo {
+            code: $code,
+            city: $city,
+            country: $country,
+            continent: Continent::$continent,
+            region: Region::$region,
+            latitude: $lat,
+            longitude: $lon,
+        }),*]
+    };
+}
+
+/// Catalog of Fastly POPs, must stay sorted by code
+#[rustfmt::skip]
+static CATALOG: &[PopInfo] = pops![
+    ("ACC", "Accra", "GH", Africa, Africa, 5.603, -0.187),
+    ("ADL", "Adelaide", "AU", Oceania, AustraliaNewZealand, -34.929, 138.601),
+    ("AKL", "Auckland", "NZ", Oceania, AustraliaNewZealand, -36.849, 174.763),
+    ("AMS", "Amsterdam", "NL", Europe, Europe, 52.370, 4.895),
+    ("ATL", "Atlanta", "US", NorthAmerica, NorthAmerica, 33.749, -84.388),
+    ("BKK", "Bangkok", "TH", Asia, Asia, 13.756, 100.502),
+    ("BMA", "Stockholm", "SE", Europe, Europe, 59.329, 18.069),
+    ("BNE", "Brisbane", "AU", Oceania, AustraliaNewZealand, -27.470, 153.026),
+    ("BOG", "Bogota", "CO", SouthAmerica, SouthAmerica, 4.711, -74.072),
+    ("BOM", "Mumbai", "IN", Asia, India, 19.076, 72.878),
+    ("BOS", "Boston", "US", NorthAmerica, NorthAmerica, 42.360, -71.059),
+    ("BRU", "Brussels", "BE", Europe, Europe, 50.850, 4.352),
+    ("BUR", "Burbank", "US", NorthAmerica, NorthAmerica, 34.181, -118.309),
+    ("CCU", "Kolkata", "IN", Asia, India, 22.573, 88.364),
+    ("CDG", "Paris", "FR", Europe, Europe, 48.857, 2.352),
+    ("CGK", "Jakarta", "ID", Asia, Asia, -6.208, 106.846),
+    ("CHC", "Christchurch", "NZ", Oceania, AustraliaNewZealand, -43.532, 172.636),
+    ("CHI", "Chicago", "US", NorthAmerica, NorthAmerica, 41.878, -87.630),
+    ("CMH", "Columbus", "US", NorthAmerica, NorthAmerica, 39.961, -82.999),
+    ("CPH", "Copenhagen", "DK", Europe, Europe, 55.676, 12.568),
+    ("CPT", "Cape Town", "ZA", Africa, Africa, -33.925, 18.424),
+    ("DAL", "Dallas", "US", NorthAmerica, NorthAmerica, 32.777, -96.797),
+    ("DEL", "Delhi", "IN", Asia, India, 28.704, 77.102),
+    ("DEN", "Denver", "US", NorthAmerica, NorthAmerica, 39.739, -104.990),
+    ("DTW", "Detroit", "US", NorthAmerica, NorthAmerica, 42.331, -83.046),
+    ("DUB", "Dublin", "IE", Europe, Europe, 53.350, -6.260),
+    ("DUR", "Durban", "ZA", Africa, Africa, -29.858, 31.022),
+    ("DXB", "Dubai", "AE", Asia, Asia, 25.205, 55.271),
+    ("EWR", "Newark", "US", NorthAmerica, NorthAmerica, 40.736, -74.172),
+    ("EZE", "Buenos Aires", "AR", SouthAmerica, SouthAmerica, -34.604, -58.382),
+    ("FCO", "Rome", "IT", Europe, Europe, 41.903, 12.496),
+    ("FJR", "Fujairah", "AE", Asia, Asia, 25.129, 56.326),
+    ("FOR", "Fortaleza", "BR", SouthAmerica, SouthAmerica, -3.732, -38.527),
+    ("FRA", "Frankfurt", "DE", Europe, Europe, 50.110, 8.682),
+    ("FTY", "Atlanta", "US", NorthAmerica, NorthAmerica, 33.779, -84.521),
+    ("GIG", "Rio de Janeiro", "BR", SouthAmerica, SouthAmerica, -22.907, -43.173),
+    ("GNV", "Gainesville", "US", NorthAmerica, NorthAmerica, 29.652, -82.325),
+    ("GRU", "Sao Paulo", "BR", SouthAmerica, SouthAmerica, -23.551, -46.633),
+    ("HEL", "Helsinki", "FI", Europe, Europe, 60.170, 24.938),
+    ("HHN", "Frankfurt", "DE", Europe, Europe, 49.949, 7.264),
+    ("HKG", "Hong Kong", "HK", Asia, Asia, 22.320, 114.169),
+    ("HND", "Tokyo", "JP", Asia, Asia, 35.549, 139.780),
+    ("HNL", "Honolulu", "US", Oceania, NorthAmerica, 21.307, -157.858),
+    ("HYD", "Hyderabad", "IN", Asia, India, 17.385, 78.487),
+    ("IAD", "Ashburn", "US", NorthAmerica, NorthAmerica, 39.044, -77.487),
+    ("IAH", "Houston", "US", NorthAmerica, NorthAmerica, 29.760, -95.370),
+    ("ICN", "Seoul", "KR", Asia, SouthKorea, 37.566, 126.978),
+    ("ITM", "Osaka", "JP", Asia, Asia, 34.694, 135.502),
+    ("JAX", "Jacksonville", "US", NorthAmerica, NorthAmerica, 30.332, -81.656),
+    ("JNB", "Johannesburg", "ZA", Africa, Africa, -26.204, 28.047),
+    ("KUL", "Kuala Lumpur", "MY", Asia, Asia, 3.139, 101.687),
+    ("LAX", "Los Angeles", "US", NorthAmerica, NorthAmerica, 34.052, -118.244),
+    ("LCK", "Columbus", "US", NorthAmerica, NorthAmerica, 39.814, -82.928),
+    ("LCY", "London", "GB", Europe, Europe, 51.505, 0.055),
+    ("LGA", "New York", "US", NorthAmerica, NorthAmerica, 40.713, -74.006),
+    ("LHR", "London", "GB", Europe, Europe, 51.507, -0.128),
+    ("LIM", "Lima", "PE", SouthAmerica, SouthAmerica, -12.046, -77.043),
+    ("LIS", "Lisbon", "PT", Europe, Europe, 38.722, -9.139),
+    ("MAA", "Chennai", "IN", Asia, India, 13.083, 80.271),
+    ("MAD", "Madrid", "ES", Europe, Europe, 40.417, -3.704),
+    ("MAN", "Manchester", "GB", Europe, Europe, 53.481, -2.243),
+    ("MCI", "Kansas City", "US", NorthAmerica, NorthAmerica, 39.100, -94.579),
+    ("MDW", "Chicago", "US", NorthAmerica, NorthAmerica, 41.786, -87.752),
+    ("MEL", "Melbourne", "AU", Oceania, AustraliaNewZealand, -37.814, 144.963),
+    ("MIA", "Miami", "US", NorthAmerica, NorthAmerica, 25.762, -80.192),
+    ("MNL", "Manila", "PH", Asia, Asia, 14.600, 120.984),
+    ("MRS", "Marseille", "FR", Europe, Europe, 43.297, 5.370),
+    ("MSP", "Minneapolis", "US", NorthAmerica, NorthAmerica, 44.978, -93.265),
+    ("MXP", "Milan", "IT", Europe, Europe, 45.464, 9.190),
+    ("NRT", "Tokyo", "JP", Asia, Asia, 35.676, 139.650),
+    ("ORD", "Chicago", "US", NorthAmerica, NorthAmerica, 41.978, -87.905),
+    ("OSL", "Oslo", "NO", Europe, Europe, 59.914, 10.752),
+    ("PAO", "Palo Alto", "US", NorthAmerica, NorthAmerica, 37.442, -122.143),
+    ("PDK", "Atlanta", "US", NorthAmerica, NorthAmerica, 33.876, -84.302),
+    ("PDX", "Portland", "US", NorthAmerica, NorthAmerica, 45.505, -122.675),
+    ("PER", "Perth", "AU", Oceania, AustraliaNewZealand, -31.951, 115.861),
+    ("PHX", "Phoenix", "US", NorthAmerica, NorthAmerica, 33.448, -112.074),
+    ("QRO", "Queretaro", "MX", NorthAmerica, NorthAmerica, 20.588, -100.390),
+    ("SCL", "Santiago", "CL", SouthAmerica, SouthAmerica, -33.449, -70.669),
+    ("SEA", "Seattle", "US", NorthAmerica, NorthAmerica, 47.606, -122.332),
+    ("SIN", "Singapore", "SG", Asia, Asia, 1.352, 103.820),
+    ("SJC", "San Jose", "US", NorthAmerica, NorthAmerica, 37.339, -121.895),
+    ("SOF", "Sofia", "BG", Europe, Europe, 42.698, 23.322),
+    ("STP", "St. Paul", "US", NorthAmerica, NorthAmerica, 44.954, -93.090),
+    ("SYD", "Sydney", "AU", Oceania, AustraliaNewZealand, -33.869, 151.209),
+    ("TPE", "Taipei", "TW", Asia, Asia, 25.033, 121.565),
+    ("TYO", "Tokyo", "JP", Asia, Asia, 35.690, 139.692),
+    ("VIE", "Vienna", "AT", Europe, Europe, 48.208, 16.374),
+    ("WAW", "Warsaw", "PL", Europe, Europe, 52.230, 21.012),
+    ("WLG", "Wellington", "NZ", Oceania, AustraliaNewZealand, -41.287, 174.776),
+    ("YUL", "Montreal", "CA", NorthAmerica, NorthAmerica, 45.502, -73.567),
+    ("YVR", "Vancouver", "CA", NorthAmerica, NorthAmerica, 49.283, -123.121),
+    ("YYZ", "Toronto", "CA", NorthAmerica, NorthAmerica, 43.653, -79.383),
+    ("ZRH", "Zurich", "CH", Europe, Europe, 47.377, 8.542),
+];
@@ -0,0 +1,322 @@
+use crate::alert::Expression;
+use crate::metric::Metrics;
+use crate::origin::OriginDataInSecond;
+use crate::service::ServiceDataInSecond;
+use crate::timeseries::{Scope, Scoped};
+use anyhow::Result;
+use std::collections::{BTreeMap, BTreeSet};
+use std::marker::PhantomData;
+
+/// An entry of a [`Ranking`], a POP, an origin or a status code
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ranked {
+    pub name: String,
+
+    /// Value over the window
+    pub value: f64,
+
+    /// Value relative to that of all entries together, e.g. the share of requests of a POP,
+    /// or its error ratio relative to that of the service
+    pub share: f64,
+
+    /// Value over the previous window, `None` when no second of it was given
+    pub previous: Option<f64>,
+}
+
+impl Ranked {
+    /// Difference with the previous window, `None` without it
+    pub fn change(&self) -> Option<f64> {
+        self.previous.map(|previous| self.value - previous)
+    }
+
+    /// Relative difference with the previous window, e.g. 0.5 for 50% more, infinite from 0
+    pub fn change_ratio(&self) -> Option<f64> {
+        let previous = self.previous?;
+        Some(if previous != 0.0 {
+            (self.value - previous) / previous.abs()
+        } else if self.value == 0.0 {
+            0.0
+        } else {
+            self.value.signum() * f64::INFINITY
+        })
+    }
+}
+
+/// Top entries over a window of seconds
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ranking {
+    /// First second of the window
+    pub start: u64,
+
+    /// Last second of the window
+    pub end: u64,
+
+    /// Value of all entries together
+    pub total: f64,
+
+    /// Top entries, the first ranked first
+    pub entries: Vec<Ranked>,
+}
+
+/// Order of the entries of a ranking
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    /// Largest value first
+    Value,
+
+    /// Largest change versus the previous window first, increase or decrease,
+    /// e.g. to find a POP which lost its traffic
+    Change,
+}
+
+/// Ranks the POPs or origins of the seconds of a service by a metric or KPI over a window
+///
+/// The window is the last `window` seconds up to the latest second given, and the previous window the
+/// `window` seconds before it. The expression, see [`Expression`], is evaluated on the stats of each
+/// entry summed over each window, so ratios are those of the whole window. Entries missing from
+/// a window count as zero in it.
+///
+/// ```
+/// use fastly_rt::ranking::Ranker;
+/// use fastly_rt::service::ServiceDataInSecond;
+///
+/// let seconds: Vec<ServiceDataInSecond> = (1..=120)
+///     .map(|recorded| {
+///         let mut data = ServiceDataInSecond { recorded, ..Default::default() };
+///         for (pop_name, requests) in [("NRT", 300), ("LHR", 100)] {
+///             data.datacenter.entry(pop_name.to_string()).or_default().requests = requests;
+///         }
+///         data
+///     })
+///     .collect();
+///
+/// let ranking = Ranker::<ServiceDataInSecond>::new("requests").unwrap().window(60).pops(&seconds);
+/// assert_eq!(ranking.entries[0].name, "NRT");
+/// assert_eq!(ranking.entries[0].value, 18000.0);
+/// assert_eq!(ranking.entries[0].share, 0.75);
+/// assert_eq!(ranking.entries[0].change(), Some(0.0));
+/// ```
+pub struct Ranker<D: Scoped> {
+    expression: Expression,
+    window: u64,
+    limit: usize,
+    order: Order,
+    data: PhantomData<D>,
+}
+
+impl<D: Scoped> Ranker<D> {
+    /// Rank by `expression`, e.g. `status_5xx` or `status_5xx / requests`, failing when it uses
+    /// a metric unknown to the stats of `D`
+    pub fn new(expression: &str) -> Result<Ranker<D>> {
+        let expression = Expression::parse(expression)?;
+        expression.validate::<D::Stats>()?;
+
+        Ok(Ranker {
+            expression,
+            window: 60,
+            limit: 10,
+            order: Order::Value,
+            data: PhantomData,
+        })
+    }
+
+    /// Length of the window in seconds, 60 by default
+    pub fn window(mut self, secs: u64) -> Ranker<D> {
+        self.window = secs.max(1);
+        self
+    }
+
+    /// Keep the `limit` first entries, 10 by default
+    pub fn top(mut self, limit: usize) -> Ranker<D> {
+        self.limit = limit;
+        self
+    }
+
+    pub fn order(mut self, order: Order) -> Ranker<D> {
+        self.order = order;
+        self
+    }
+
+    /// Rank the scopes of `seconds` named by `name`, skipping those it returns `None` for
+    fn rank<F: Fn(Scope) -> Option<String>>(&self, seconds: &[D], name: F) -> Ranking {
+        let (start, end) = bounds(seconds, self.window);
+        let mut current: BTreeMap<String, D::Stats> = BTreeMap::new();
+        let mut previous: BTreeMap<String, D::Stats> = BTreeMap::new();
+        let mut total = D::Stats::default();
+        let mut has_previous = false;
+
+        for data in seconds {
+            let recorded = data.recorded();
+            let window = if recorded >= start && recorded <= end {
+                &mut current
+            } else if recorded < start && recorded + self.window >= start {
+                has_previous = true;
+                &mut previous
+            } else {
+                continue;
+            };
+            for (scope, stats) in data.scopes() {
+                let Some(entry_name) = name(scope) else {
+                    continue;
+                };
+                *window.entry(entry_name).or_default() += stats;
+                if recorded >= start {
+                    total += stats;
+                }
+            }
+        }
+
+        let total = self.expression.eval(&total);
+        let names: BTreeSet<&String> = current.keys().chain(previous.keys()).collect();
+        let mut entries: Vec<Ranked> = names
+            .into_iter()
+            .map(|entry_name| {
+                let value = self
+                    .expression
+                    .eval(&current.get(entry_name).cloned().unwrap_or_default());
+                let before = previous.get(entry_name).cloned().unwrap_or_default();
+                Ranked {
+                    name: entry_name.clone(),
+                    value,
+                    share: value / total,
+                    previous: has_previous.then(|| self.expression.eval(&before)),
+                }
+            })
+            .collect();
+        sort(&mut entries, self.order);
+        entries.truncate(self.limit);
+
+        Ranking {
+            start,
+            end,
+            total,
+            entries,
+        }
+    }
+}
+
+impl Ranker<ServiceDataInSecond> {
+    /// Rank the POPs of `datacenter`
+    pub fn pops(&self, seconds: &[ServiceDataInSecond]) -> Ranking {
+        self.rank(seconds, |scope| scope.pop)
+    }
+}
+
+impl Ranker<OriginDataInSecond> {
+    /// Rank the origins of `aggregated`
+    pub fn origins(&self, seconds: &[OriginDataInSecond]) -> Ranking {
+        self.rank(seconds, |scope| match scope.pop {
+            None => scope.origin,
+            Some(_) => None,
+        })
+    }
+
+    /// Rank the origins of `datacenter` in the POP `pop_name`
+    pub fn origins_in(&self, seconds: &[OriginDataInSecond], pop_name: &str) -> Ranking {
+        self.rank(seconds, |scope| match scope.pop {
+            Some(pop) if pop == pop_name => scope.origin,
+            _ => None,
+        })
+    }
+
+    /// Rank the POPs of `datacenter` fetching from the origin `origin_name`
+    pub fn pops_of(&self, seconds: &[OriginDataInSecond], origin_name: &str) -> Ranking {
+        self.rank(seconds, |scope| match scope.origin {
+            Some(origin) if origin == origin_name => scope.pop,
+            _ => None,
+        })
+    }
+}
+
+/// Rank the detailed status codes of `scope`, e.g. `status_503`, by number of responses
+/// over the last `window` seconds, the share being that of all detailed status codes
+///
+/// Status classes such as `status_5xx` are not ranked.
+pub fn status_codes<D: Scoped>(seconds: &[D], scope: &Scope, window: u64, limit: usize) -> Ranking {
+    let window = window.max(1);
+    let (start, end) = bounds(seconds, window);
+    let mut current = D::Stats::default();
+    let mut previous = D::Stats::default();
+    let mut has_previous = false;
+
+    for data in seconds {
+        let recorded = data.recorded();
+        let sum = if recorded >= start && recorded <= end {
+            &mut current
+        } else if recorded < start && recorded + window >= start {
+            has_previous = true;
+            &mut previous
+        } else {
+            continue;
+        };
+        for (data_scope, stats) in data.scopes() {
+            if &data_scope == scope {
+                *sum += stats;
+            }
+        }
+    }
+
+    let codes: Vec<(&str, f64, f64)> = D::Stats::METRICS
+        .iter()
+        .filter(|metric| {
+            metric
+                .name
+                .strip_prefix("status_")
+                .is_some_and(|code| code.len() == 3 && code.bytes().all(|c| c.is_ascii_digit()))
+        })
+        .map(|metric| {
+            (
+                metric.name,
+                (metric.value)(&current),
+                (metric.value)(&previous),
+            )
+        })
+        .collect();
+    let total: f64 = codes.iter().map(|(_, value, _)| value).sum();
+
+    let mut entries: Vec<Ranked> = codes
+        .into_iter()
+        .filter(|(_, value, before)| *value > 0.0 || (has_previous && *before > 0.0))
+        .map(|(name, value, before)| Ranked {
+            name: name.trim_start_matches("status_").to_string(),
+            value,
+            share: value / total,
+            previous: has_previous.then_some(before),
+        })
+        .collect();
+    sort(&mut entries, Order::Value);
+    entries.truncate(limit);
+
+    Ranking {
+        start,
+        end,
+        total,
+        entries,
+    }
+}
+
+/// First and last second of the window ending at the latest of `seconds`
+fn bounds<D: Scoped>(seconds: &[D], window: u64) -> (u64, u64) {
+    let end = seconds
+        .iter()
+        .map(|data| data.recorded())
+        .max()
+        .unwrap_or(0);
+    ((end + 1).saturating_sub(window), end)
+}
+
+/// Sort by `order`, then by name, NaN values last
+fn sort(entries: &mut [Ranked], order: Order) {
+    let key = |entry: &Ranked| match order {
+        Order::Value => entry.value,
+        Order::Change => entry.change().map_or(0.0, f64::abs),
+    };
+    entries.sort_by(|a, b| {
+        let (a_key, b_key) = (key(a), key(b));
+        a_key
+            .is_nan()
+            .cmp(&b_key.is_nan())
+            .then(b_key.total_cmp(&a_key))
+            .then(a.name.cmp(&b.name))
+    });
+}
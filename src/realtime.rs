@@ -0,0 +1,195 @@
+use crate::client::CliObj;
+use crate::client::TimestampHolder;
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::marker::PhantomData;
+
+/// Response of a real time endpoint, `D` being the data of one second
+/// e.g. [`crate::service::ServiceResponse`] is `RtResponse<ServiceDataInSecond>`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RtResponse<D> {
+    /// Offset of entry timestamps from the current time due to processing time.
+    #[serde(alias = "AggregateDelay")]
+    pub aggregate_delay: u64,
+
+    /// A list of report entries, each representing one second of time.
+    #[serde(alias = "Data")]
+    pub data: Vec<D>,
+
+    /// Timestamp value to use for subsequent requests.
+    #[serde(alias = "Timestamp")]
+    pub timestamp: u64,
+}
+
+impl<D> TimestampHolder for RtResponse<D> {
+    fn get_timestamp(&self) -> u64 {
+        self.timestamp
+    }
+}
+
+/// Query of a real time endpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "method", content = "arg", rename_all = "snake_case")]
+pub enum Query {
+    /// Data following the data of the previous consecutive query
+    Consecutive,
+
+    /// Data from the start timestamp to latest
+    From(u64),
+
+    /// Data of the last 120 seconds
+    Last120s,
+
+    /// Data of the last 120 seconds, up to a maximum number of entries
+    Max(u64),
+}
+
+/// Data of one second of a real time endpoint
+/// Implementing it for a new type is enough to get a [`RtClient`] for the endpoint
+pub trait RealtimeData: DeserializeOwned + Send + 'static {
+    /// Path of the endpoint, without the trailing `/{service_id}/ts/...` part, e.g. `/v1/channel`
+    const PATH: &'static str;
+}
+
+/// Common interface of real time clients, so tooling can be written once for all endpoints
+pub trait RealtimeClient {
+    /// Data of one second returned by the endpoint
+    type Data;
+
+    /// Reset internal timestamp which used to track consecutive stats to 0
+    fn reset_stats_consecutive(&mut self);
+
+    /// Get data following the data of the previous call
+    fn get_stats_consecutive(
+        &mut self,
+    ) -> impl Future<Output = Result<RtResponse<Self::Data>>> + Send;
+
+    /// Get stats from start_timestamp to latest
+    fn get_stats_from(
+        &mut self,
+        start_timestamp: u64,
+    ) -> impl Future<Output = Result<RtResponse<Self::Data>>> + Send;
+
+    /// Get data for the 120 seconds preceding the latest timestamp available
+    fn get_stats_120s(&self) -> impl Future<Output = Result<RtResponse<Self::Data>>> + Send;
+
+    /// Get data for the 120 seconds preceding the latest timestamp available, up to a maximum of max_entries entries
+    fn get_stats_max(
+        &self,
+        max_entries: u64,
+    ) -> impl Future<Output = Result<RtResponse<Self::Data>>> + Send;
+}
+
+/// Client to get real time data of type `D`
+/// See [`crate::service::ServiceClient`] and [`crate::origin::OriginClient`]
+pub struct RtClient<D> {
+    cli: CliObj,
+    data: PhantomData<fn() -> D>,
+}
+
+impl<D: RealtimeData> RtClient<D> {
+    /// Base URL of Fastly real time API
+    pub const BASE_URL: &'static str = "https://rt.fastly.com";
+
+    /// Create a client
+    pub fn new(api_key: &str, service_id: &str) -> Result<RtClient<D>> {
+        RtClient::with_base_url(api_key, service_id, RtClient::<D>::BASE_URL)
+    }
+
+    /// Create a client sending requests to `base_url` instead of Fastly, e.g. to the server of the `fake` feature
+    pub fn with_base_url(api_key: &str, service_id: &str, base_url: &str) -> Result<RtClient<D>> {
+        let endpoint = format!("{}{}", base_url.trim_end_matches('/'), D::PATH);
+
+        Ok(RtClient {
+            cli: CliObj::new(api_key, service_id, &endpoint)?,
+            data: PhantomData,
+        })
+    }
+
+    /// ID of the service the client gets data of
+    pub fn service_id(&self) -> &str {
+        self.cli.service_id()
+    }
+
+    /// Reset internal timestamp which used to track consecutive stats to 0
+    /// After calling this function, calling get_stats_consecutive function will be the first call
+    pub fn reset_stats_consecutive(&mut self) {
+        self.cli.reset_stats_consecutive();
+    }
+
+    /// The first call of the function will get data of latest one second
+    /// The consecutive call of the function will get consecutive data of last call to last second
+    pub async fn get_stats_consecutive(&mut self) -> Result<RtResponse<D>> {
+        self.cli.get_stats_consecutive().await
+    }
+
+    /// Get stats from start_timestamp to latest timestamp available for a service
+    pub async fn get_stats_from(&mut self, start_timestamp: u64) -> Result<RtResponse<D>> {
+        self.cli.get_stats_from(start_timestamp).await
+    }
+
+    /// Get data for the 120 seconds preceding the latest timestamp available for a service.
+    pub async fn get_stats_120s(&self) -> Result<RtResponse<D>> {
+        self.cli.get_stats_120s().await
+    }
+
+    /// Get data for the 120 seconds preceding the latest timestamp available for a service, up to a maximum of max_entries entries.
+    pub async fn get_stats_max(&self, max_entries: u64) -> Result<RtResponse<D>> {
+        self.cli.get_stats_max(max_entries).await
+    }
+
+    /// Run `query`, returning the raw JSON body along with the parsed response
+    pub async fn query_raw(&mut self, query: Query) -> Result<(String, RtResponse<D>)> {
+        self.cli.query_raw(query).await
+    }
+}
+
+impl<D: RealtimeData> RealtimeClient for RtClient<D> {
+    type Data = D;
+
+    fn reset_stats_consecutive(&mut self) {
+        RtClient::reset_stats_consecutive(self)
+    }
+
+    fn get_stats_consecutive(&mut self) -> impl Future<Output = Result<RtResponse<D>>> + Send {
+        RtClient::get_stats_consecutive(self)
+    }
+
+    fn get_stats_from(
+        &mut self,
+        start_timestamp: u64,
+    ) -> impl Future<Output = Result<RtResponse<D>>> + Send {
+        RtClient::get_stats_from(self, start_timestamp)
+    }
+
+    fn get_stats_120s(&self) -> impl Future<Output = Result<RtResponse<D>>> + Send {
+        RtClient::get_stats_120s(self)
+    }
+
+    fn get_stats_max(
+        &self,
+        max_entries: u64,
+    ) -> impl Future<Output = Result<RtResponse<D>>> + Send {
+        RtClient::get_stats_max(self, max_entries)
+    }
+}
+
+/// Source of consecutive real time data, the interface polling code should depend on
+/// Implemented by every [`RealtimeClient`], and by [`crate::mock::MockSource`] for unit tests
+pub trait RealtimeSource {
+    /// Data of one second returned by the source
+    type Data;
+
+    /// Get data following the data of the previous call, see [`RealtimeClient::get_stats_consecutive`]
+    fn next_stats(&mut self) -> impl Future<Output = Result<RtResponse<Self::Data>>> + Send;
+}
+
+impl<C: RealtimeClient> RealtimeSource for C {
+    type Data = C::Data;
+
+    fn next_stats(&mut self) -> impl Future<Output = Result<RtResponse<C::Data>>> + Send {
+        self.get_stats_consecutive()
+    }
+}
@@ -1,33 +1,21 @@
-use crate::client::CliObj;
-use crate::client::TimestampHolder;
-use anyhow::Result;
+use crate::histogram::LatencyHistogram;
+use crate::metric::{metric_catalog, Metric, Metrics};
+use crate::pop::{Pop, Region};
+use crate::realtime::{RealtimeData, RtClient, RtResponse};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::hash::Hash;
+use std::ops::AddAssign;
 
 /// Response of real time data of service
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ServiceResponse {
-    /// Offset of entry timestamps from the current time due to processing time.
-    #[serde(alias = "AggregateDelay")]
-    pub aggregate_delay: u64,
-
-    /// A list of report entries, each representing one second of time.
-    #[serde(alias = "Data")]
-    pub data: Vec<ServiceDataInSecond>,
-
-    /// Timestamp value to use for subsequent requests.
-    #[serde(alias = "Timestamp")]
-    pub timestamp: u64,
-}
+pub type ServiceResponse = RtResponse<ServiceDataInSecond>;
 
-impl TimestampHolder for ServiceResponse {
-    fn get_timestamp(&self) -> u64 {
-        self.timestamp
-    }
+impl RealtimeData for ServiceDataInSecond {
+    const PATH: &'static str = "/v1/channel";
 }
 
 /// Data of service in one seconds
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ServiceDataInSecond {
     /// The Unix timestamp at which this record's data was generated
     #[serde(default)]
@@ -43,9 +31,62 @@ pub struct ServiceDataInSecond {
     pub datacenter: HashMap<String, ServiceStats>,
 }
 
+impl ServiceDataInSecond {
+    /// Add measurements of another second to this one, e.g. to sum up a window of seconds
+    /// `recorded` becomes the latest of both
+    pub fn merge(&mut self, other: &ServiceDataInSecond) {
+        self.recorded = self.recorded.max(other.recorded);
+        self.aggregated.merge(&other.aggregated);
+
+        for (pop_name, stats) in &other.datacenter {
+            self.datacenter
+                .entry(pop_name.clone())
+                .or_default()
+                .merge(stats);
+        }
+    }
+
+    /// Iterate over measurements by POP, with POP names parsed into [`Pop`]
+    pub fn pops(&self) -> impl Iterator<Item = (Pop, &ServiceStats)> {
+        self.datacenter
+            .iter()
+            .map(|(pop_name, stats)| (Pop::new(pop_name), stats))
+    }
+
+    /// Roll up measurements by POP into Fastly billing regions
+    /// POPs missing from the catalog are rolled up into [`Region::Unknown`]
+    pub fn by_region(&self) -> HashMap<Region, ServiceStats> {
+        self.group_by(|pop| Some(pop.region()))
+    }
+
+    /// Roll up measurements by POP into groups of a user supplied map of `pop_name` --> `group_name`
+    /// POPs missing from the map are left out
+    pub fn by_group(&self, groups: &HashMap<String, String>) -> HashMap<String, ServiceStats> {
+        self.group_by(|pop| groups.get(pop.code()).cloned())
+    }
+
+    /// Roll up measurements by POP into the group returned by `group_of` for each POP
+    /// POPs for which `group_of` returns `None` are left out
+    pub fn group_by<K, F>(&self, group_of: F) -> HashMap<K, ServiceStats>
+    where
+        K: Eq + Hash,
+        F: Fn(&Pop) -> Option<K>,
+    {
+        let mut groups: HashMap<K, ServiceStats> = HashMap::new();
+
+        for (pop, stats) in self.pops() {
+            if let Some(group) = group_of(&pop) {
+                groups.entry(group).or_default().merge(stats);
+            }
+        }
+
+        groups
+    }
+}
+
 /// Statistics of service
 /// See explanation of members [here](https://developer.fastly.com/reference/api/metrics-stats/realtime/#measurements-data-model)
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ServiceStats {
     #[serde(default)]
     pub attack_blocked_req_body_bytes: u64,
@@ -296,8 +337,9 @@ pub struct ServiceStats {
     #[serde(default)]
     pub miss: u64,
 
+    /// Latency histogram of requests to origin, see [`LatencyHistogram`]
     #[serde(default)]
-    pub miss_histogram: HashMap<String, u64>,
+    pub miss_histogram: LatencyHistogram,
 
     #[serde(default)]
     pub miss_resp_body_bytes: u64,
@@ -588,45 +630,383 @@ pub struct ServiceStats {
     pub waf_passed: u64,
 }
 
-/// Client to get service real time data
-pub struct ServiceClient {
-    cli: CliObj,
-}
-
-impl ServiceClient {
-    const ENDPOINT: &'static str = "https://rt.fastly.com/v1/channel";
-
-    /// Create a Service Client
-    pub fn new(api_key: &str, service_id: &str) -> Result<ServiceClient> {
-        Ok(ServiceClient {
-            cli: CliObj::new(api_key, service_id, ServiceClient::ENDPOINT)?,
-        })
-    }
-
-    /// Reset internal timestamp which used to track consecutive stats to 0
-    /// After calling this function, calling get_stats_consecutive function will be the first call
-    pub fn reset_stats_consecutive(&mut self) {
-        self.cli.reset_stats_consecutive();
-    }
-
-    /// The first call of the function will get data of latest one second
-    /// The consecutive call of the function will get consecutive data of last call to last second
-    pub async fn get_stats_consecutive(&mut self) -> Result<ServiceResponse> {
-        self.cli.get_stats_consecutive().await
-    }
-
-    /// Get stats from start_timestamp to latest
-    pub async fn get_stats_from(&mut self, start_timestamp: u64) -> Result<ServiceResponse> {
-        self.cli.get_stats_from(start_timestamp).await
+impl ServiceStats {
+    /// Add measurements of another [`ServiceStats`] to this one, e.g. to sum up POPs or seconds,
+    /// keeping the largest value of gauges such as `compute_ram_used`
+    pub fn merge(&mut self, other: &ServiceStats) {
+        self.attack_blocked_req_body_bytes += other.attack_blocked_req_body_bytes;
+        self.attack_blocked_req_header_bytes += other.attack_blocked_req_header_bytes;
+        self.attack_logged_req_body_bytes += other.attack_logged_req_body_bytes;
+        self.attack_logged_req_header_bytes += other.attack_logged_req_header_bytes;
+        self.attack_passed_req_body_bytes += other.attack_passed_req_body_bytes;
+        self.attack_passed_req_header_bytes += other.attack_passed_req_header_bytes;
+        self.attack_req_body_bytes += other.attack_req_body_bytes;
+        self.attack_req_header_bytes += other.attack_req_header_bytes;
+        self.attack_resp_synth_bytes += other.attack_resp_synth_bytes;
+        self.bereq_body_bytes += other.bereq_body_bytes;
+        self.bereq_header_bytes += other.bereq_header_bytes;
+        self.body_size += other.body_size;
+        self.compute_bereq_body_bytes += other.compute_bereq_body_bytes;
+        self.compute_bereq_errors += other.compute_bereq_errors;
+        self.compute_bereq_header_bytes += other.compute_bereq_header_bytes;
+        self.compute_bereqs += other.compute_bereqs;
+        self.compute_beresp_body_bytes += other.compute_beresp_body_bytes;
+        self.compute_beresp_header_bytes += other.compute_beresp_header_bytes;
+        self.compute_execution_time_ms += other.compute_execution_time_ms;
+        self.compute_globals_limit_exceeded += other.compute_globals_limit_exceeded;
+        self.compute_guest_errors += other.compute_guest_errors;
+        self.compute_heap_limit_exceeded += other.compute_heap_limit_exceeded;
+        self.compute_ram_used = self.compute_ram_used.max(other.compute_ram_used);
+        self.compute_req_body_bytes += other.compute_req_body_bytes;
+        self.compute_req_header_bytes += other.compute_req_header_bytes;
+        self.compute_request_time_ms += other.compute_request_time_ms;
+        self.compute_requests += other.compute_requests;
+        self.compute_resource_limit_exceeded += other.compute_resource_limit_exceeded;
+        self.compute_resp_body_bytes += other.compute_resp_body_bytes;
+        self.compute_resp_header_bytes += other.compute_resp_header_bytes;
+        self.compute_resp_status_1xx += other.compute_resp_status_1xx;
+        self.compute_resp_status_2xx += other.compute_resp_status_2xx;
+        self.compute_resp_status_3xx += other.compute_resp_status_3xx;
+        self.compute_resp_status_4xx += other.compute_resp_status_4xx;
+        self.compute_resp_status_5xx += other.compute_resp_status_5xx;
+        self.compute_runtime_errors += other.compute_runtime_errors;
+        self.compute_stack_limit_exceeded += other.compute_stack_limit_exceeded;
+        self.deliver_sub_count += other.deliver_sub_count;
+        self.deliver_sub_time += other.deliver_sub_time;
+        self.edge_hit_requests += other.edge_hit_requests;
+        self.edge_hit_resp_body_bytes += other.edge_hit_resp_body_bytes;
+        self.edge_hit_resp_header_bytes += other.edge_hit_resp_header_bytes;
+        self.edge_miss_requests += other.edge_miss_requests;
+        self.edge_miss_resp_body_bytes += other.edge_miss_resp_body_bytes;
+        self.edge_miss_resp_header_bytes += other.edge_miss_resp_header_bytes;
+        self.edge_requests += other.edge_requests;
+        self.edge_resp_body_bytes += other.edge_resp_body_bytes;
+        self.edge_resp_header_bytes += other.edge_resp_header_bytes;
+        self.error_sub_count += other.error_sub_count;
+        self.error_sub_time += other.error_sub_time;
+        self.errors += other.errors;
+        self.fetch_sub_count += other.fetch_sub_count;
+        self.fetch_sub_time += other.fetch_sub_time;
+        self.hash_sub_count += other.hash_sub_count;
+        self.hash_sub_time += other.hash_sub_time;
+        self.header_size += other.header_size;
+        self.hit_resp_body_bytes += other.hit_resp_body_bytes;
+        self.hit_sub_count += other.hit_sub_count;
+        self.hit_sub_time += other.hit_sub_time;
+        self.hits += other.hits;
+        self.hits_time += other.hits_time;
+        self.http2 += other.http2;
+        self.http3 += other.http3;
+        self.imgopto += other.imgopto;
+        self.imgopto_resp_body_bytes += other.imgopto_resp_body_bytes;
+        self.imgopto_resp_header_bytes += other.imgopto_resp_header_bytes;
+        self.imgopto_shield += other.imgopto_shield;
+        self.imgopto_shield_resp_body_bytes += other.imgopto_shield_resp_body_bytes;
+        self.imgopto_shield_resp_header_bytes += other.imgopto_shield_resp_header_bytes;
+        self.imgopto_transforms += other.imgopto_transforms;
+        self.imgvideo += other.imgvideo;
+        self.imgvideo_frames += other.imgvideo_frames;
+        self.imgvideo_resp_body_bytes += other.imgvideo_resp_body_bytes;
+        self.imgvideo_resp_header_bytes += other.imgvideo_resp_header_bytes;
+        self.imgvideo_shield += other.imgvideo_shield;
+        self.imgvideo_shield_frames += other.imgvideo_shield_frames;
+        self.imgvideo_shield_resp_body_bytes += other.imgvideo_shield_resp_body_bytes;
+        self.imgvideo_shield_resp_header_bytes += other.imgvideo_shield_resp_header_bytes;
+        self.ipv6 += other.ipv6;
+        self.log += other.log;
+        self.log_bytes += other.log_bytes;
+        self.logging += other.logging;
+        self.miss += other.miss;
+        self.miss_histogram.merge(&other.miss_histogram);
+        self.miss_resp_body_bytes += other.miss_resp_body_bytes;
+        self.miss_sub_count += other.miss_sub_count;
+        self.miss_sub_time += other.miss_sub_time;
+        self.miss_time += other.miss_time;
+        self.object_size_100k += other.object_size_100k;
+        self.object_size_100m += other.object_size_100m;
+        self.object_size_10k += other.object_size_10k;
+        self.object_size_10m += other.object_size_10m;
+        self.object_size_1g += other.object_size_1g;
+        self.object_size_1k += other.object_size_1k;
+        self.object_size_1m += other.object_size_1m;
+        self.object_size_other += other.object_size_other;
+        self.origin_cache_fetch_resp_body_bytes += other.origin_cache_fetch_resp_body_bytes;
+        self.origin_cache_fetch_resp_header_bytes += other.origin_cache_fetch_resp_header_bytes;
+        self.origin_cache_fetches += other.origin_cache_fetches;
+        self.origin_fetch_body_bytes += other.origin_fetch_body_bytes;
+        self.origin_fetch_header_bytes += other.origin_fetch_header_bytes;
+        self.origin_fetch_resp_body_bytes += other.origin_fetch_resp_body_bytes;
+        self.origin_fetch_resp_header_bytes += other.origin_fetch_resp_header_bytes;
+        self.origin_fetches += other.origin_fetches;
+        self.origin_revalidations += other.origin_revalidations;
+        self.otfp += other.otfp;
+        self.otfp_deliver_time += other.otfp_deliver_time;
+        self.otfp_manifests += other.otfp_manifests;
+        self.otfp_resp_body_bytes += other.otfp_resp_body_bytes;
+        self.otfp_resp_header_bytes += other.otfp_resp_header_bytes;
+        self.otfp_shield += other.otfp_shield;
+        self.otfp_shield_resp_body_bytes += other.otfp_shield_resp_body_bytes;
+        self.otfp_shield_resp_header_bytes += other.otfp_shield_resp_header_bytes;
+        self.otfp_shield_time += other.otfp_shield_time;
+        self.pass += other.pass;
+        self.pass_resp_body_bytes += other.pass_resp_body_bytes;
+        self.pass_sub_count += other.pass_sub_count;
+        self.pass_sub_time += other.pass_sub_time;
+        self.pass_time += other.pass_time;
+        self.pci += other.pci;
+        self.pipe_sub_count += other.pipe_sub_count;
+        self.pipe_sub_time += other.pipe_sub_time;
+        self.predeliver_sub_count += other.predeliver_sub_count;
+        self.predeliver_sub_time += other.predeliver_sub_time;
+        self.prehash_sub_count += other.prehash_sub_count;
+        self.prehash_sub_time += other.prehash_sub_time;
+        self.recv_sub_count += other.recv_sub_count;
+        self.recv_sub_time += other.recv_sub_time;
+        self.req_body_bytes += other.req_body_bytes;
+        self.req_header_bytes += other.req_header_bytes;
+        self.requests += other.requests;
+        self.resp_body_bytes += other.resp_body_bytes;
+        self.resp_header_bytes += other.resp_header_bytes;
+        self.restarts += other.restarts;
+        self.segblock_origin_fetches += other.segblock_origin_fetches;
+        self.segblock_shield_fetches += other.segblock_shield_fetches;
+        self.shield += other.shield;
+        self.shield_cache_fetches += other.shield_cache_fetches;
+        self.shield_fetch_body_bytes += other.shield_fetch_body_bytes;
+        self.shield_fetch_header_bytes += other.shield_fetch_header_bytes;
+        self.shield_fetch_resp_body_bytes += other.shield_fetch_resp_body_bytes;
+        self.shield_fetch_resp_header_bytes += other.shield_fetch_resp_header_bytes;
+        self.shield_fetches += other.shield_fetches;
+        self.shield_resp_body_bytes += other.shield_resp_body_bytes;
+        self.shield_resp_header_bytes += other.shield_resp_header_bytes;
+        self.shield_revalidations += other.shield_revalidations;
+        self.status_1xx += other.status_1xx;
+        self.status_200 += other.status_200;
+        self.status_204 += other.status_204;
+        self.status_206 += other.status_206;
+        self.status_2xx += other.status_2xx;
+        self.status_301 += other.status_301;
+        self.status_302 += other.status_302;
+        self.status_304 += other.status_304;
+        self.status_3xx += other.status_3xx;
+        self.status_400 += other.status_400;
+        self.status_401 += other.status_401;
+        self.status_403 += other.status_403;
+        self.status_404 += other.status_404;
+        self.status_416 += other.status_416;
+        self.status_429 += other.status_429;
+        self.status_4xx += other.status_4xx;
+        self.status_500 += other.status_500;
+        self.status_501 += other.status_501;
+        self.status_502 += other.status_502;
+        self.status_503 += other.status_503;
+        self.status_504 += other.status_504;
+        self.status_505 += other.status_505;
+        self.status_5xx += other.status_5xx;
+        self.synth += other.synth;
+        self.tls += other.tls;
+        self.tls_v10 += other.tls_v10;
+        self.tls_v11 += other.tls_v11;
+        self.tls_v12 += other.tls_v12;
+        self.tls_v13 += other.tls_v13;
+        self.uncacheable += other.uncacheable;
+        self.video += other.video;
+        self.waf_blocked += other.waf_blocked;
+        self.waf_logged += other.waf_logged;
+        self.waf_passed += other.waf_passed;
     }
+}
 
-    /// Get data for the 120 seconds preceding the latest timestamp available for a service.
-    pub async fn get_stats_120s(&self) -> Result<ServiceResponse> {
-        self.cli.get_stats_120s().await
+impl AddAssign<&ServiceStats> for ServiceStats {
+    fn add_assign(&mut self, other: &ServiceStats) {
+        self.merge(other);
     }
+}
 
-    /// Get data for the 120 seconds preceding the latest timestamp available for a service, up to a maximum of max_entries entries.
-    pub async fn get_stats_max(&self, max_entries: u64) -> Result<ServiceResponse> {
-        self.cli.get_stats_max(max_entries).await
-    }
+impl Metrics for ServiceStats {
+    const METRICS: &'static [Metric<ServiceStats>] = metric_catalog! { ServiceStats;
+        attack_blocked_req_body_bytes: Bytes, "Request body bytes received from requests that triggered a WAF rule that was blocked";
+        attack_blocked_req_header_bytes: Bytes, "Request header bytes received from requests that triggered a WAF rule that was blocked";
+        attack_logged_req_body_bytes: Bytes, "Request body bytes received from requests that triggered a WAF rule that was logged";
+        attack_logged_req_header_bytes: Bytes, "Request header bytes received from requests that triggered a WAF rule that was logged";
+        attack_passed_req_body_bytes: Bytes, "Request body bytes received from requests that triggered a WAF rule that was passed";
+        attack_passed_req_header_bytes: Bytes, "Request header bytes received from requests that triggered a WAF rule that was passed";
+        attack_req_body_bytes: Bytes, "Request body bytes received from requests that triggered a WAF rule";
+        attack_req_header_bytes: Bytes, "Request header bytes received from requests that triggered a WAF rule";
+        attack_resp_synth_bytes: Bytes, "Bytes delivered for requests that triggered a WAF rule and returned a synthetic response";
+        bereq_body_bytes: Bytes, "Total body bytes sent to origin";
+        bereq_header_bytes: Bytes, "Total header bytes sent to origin";
+        body_size: Bytes, "Total body bytes delivered";
+        compute_bereq_body_bytes: Bytes, "Total body bytes sent to backends (origins) by Compute";
+        compute_bereq_errors: Count, "Number of backend request errors, including timeouts";
+        compute_bereq_header_bytes: Bytes, "Total header bytes sent to backends (origins) by Compute";
+        compute_bereqs: Count, "Number of backend requests started";
+        compute_beresp_body_bytes: Bytes, "Total body bytes received from backends (origins) by Compute";
+        compute_beresp_header_bytes: Bytes, "Total header bytes received from backends (origins) by Compute";
+        compute_execution_time_ms: Milliseconds, "Amount of active CPU time used to process requests";
+        compute_globals_limit_exceeded: Count, "Number of times a guest exceeded its globals limit";
+        compute_guest_errors: Count, "Number of times a service experienced a guest code error";
+        compute_heap_limit_exceeded: Count, "Number of times a guest exceeded its heap limit";
+        compute_ram_used: Bytes gauge, "Amount of RAM used for the service by Fastly";
+        compute_req_body_bytes: Bytes, "Total body bytes received by Compute";
+        compute_req_header_bytes: Bytes, "Total header bytes received by Compute";
+        compute_request_time_ms: Milliseconds, "Total actual amount of time used to process requests, including active CPU time";
+        compute_requests: Count, "Total number of requests received by Compute";
+        compute_resource_limit_exceeded: Count, "Number of times a guest exceeded its resource limit, includes heap, stack, globals and code execution timeout";
+        compute_resp_body_bytes: Bytes, "Total body bytes sent from Compute to end user";
+        compute_resp_header_bytes: Bytes, "Total header bytes sent from Compute to end user";
+        compute_resp_status_1xx: Count, "Number of \"Informational\" category status codes delivered by Compute";
+        compute_resp_status_2xx: Count, "Number of \"Success\" category status codes delivered by Compute";
+        compute_resp_status_3xx: Count, "Number of \"Redirection\" category status codes delivered by Compute";
+        compute_resp_status_4xx: Count, "Number of \"Client Error\" category status codes delivered by Compute";
+        compute_resp_status_5xx: Count, "Number of \"Server Error\" category status codes delivered by Compute";
+        compute_runtime_errors: Count, "Number of times a service experienced a guest runtime error";
+        compute_stack_limit_exceeded: Count, "Number of times a guest exceeded its stack limit";
+        deliver_sub_count: Count, "Number of executions of the vcl_deliver Varnish subroutine";
+        deliver_sub_time: Seconds, "Time spent inside the vcl_deliver Varnish subroutine";
+        edge_hit_requests: Count, "Number of requests sent by end users to Fastly that resulted in a hit at the edge";
+        edge_hit_resp_body_bytes: Bytes, "Body bytes delivered for edge hits";
+        edge_hit_resp_header_bytes: Bytes, "Header bytes delivered for edge hits";
+        edge_miss_requests: Count, "Number of requests sent by end users to Fastly that resulted in a miss at the edge";
+        edge_miss_resp_body_bytes: Bytes, "Body bytes delivered for edge misses";
+        edge_miss_resp_header_bytes: Bytes, "Header bytes delivered for edge misses";
+        edge_requests: Count, "Number of requests sent by end users to Fastly";
+        edge_resp_body_bytes: Bytes, "Total body bytes delivered from Fastly to the end user";
+        edge_resp_header_bytes: Bytes, "Total header bytes delivered from Fastly to the end user";
+        error_sub_count: Count, "Number of executions of the vcl_error Varnish subroutine";
+        error_sub_time: Seconds, "Time spent inside the vcl_error Varnish subroutine";
+        errors: Count, "Number of cache errors";
+        fetch_sub_count: Count, "Number of executions of the vcl_fetch Varnish subroutine";
+        fetch_sub_time: Seconds, "Time spent inside the vcl_fetch Varnish subroutine";
+        hash_sub_count: Count, "Number of executions of the vcl_hash Varnish subroutine";
+        hash_sub_time: Seconds, "Time spent inside the vcl_hash Varnish subroutine";
+        header_size: Bytes, "Total header bytes delivered";
+        hit_resp_body_bytes: Bytes, "Total body bytes delivered for cache hits";
+        hit_sub_count: Count, "Number of executions of the vcl_hit Varnish subroutine";
+        hit_sub_time: Seconds, "Time spent inside the vcl_hit Varnish subroutine";
+        hits: Count, "Number of cache hits";
+        hits_time: Seconds, "Total amount of time spent processing cache hits";
+        http2: Count, "Number of requests received over HTTP/2";
+        http3: Count, "Number of requests received over HTTP/3";
+        imgopto: Count, "Number of responses that came from the Fastly Image Optimizer service";
+        imgopto_resp_body_bytes: Bytes, "Total body bytes delivered from the Fastly Image Optimizer service";
+        imgopto_resp_header_bytes: Bytes, "Total header bytes delivered from the Fastly Image Optimizer service";
+        imgopto_shield: Count, "Number of responses delivered from the Fastly Image Optimizer service via a shield";
+        imgopto_shield_resp_body_bytes: Bytes, "Total body bytes delivered via a shield from the Fastly Image Optimizer service";
+        imgopto_shield_resp_header_bytes: Bytes, "Total header bytes delivered via a shield from the Fastly Image Optimizer service";
+        imgopto_transforms: Count, "Number of transforms performed by the Fastly Image Optimizer service";
+        imgvideo: Count, "Number of video responses that came via the Fastly Image Optimizer service";
+        imgvideo_frames: Count, "Number of video frames that came via the Fastly Image Optimizer service";
+        imgvideo_resp_body_bytes: Bytes, "Total body bytes of video delivered via the Fastly Image Optimizer service";
+        imgvideo_resp_header_bytes: Bytes, "Total header bytes of video delivered via the Fastly Image Optimizer service";
+        imgvideo_shield: Count, "Number of video responses delivered via a shield that came via the Fastly Image Optimizer service";
+        imgvideo_shield_frames: Count, "Number of video frames delivered via a shield that came via the Fastly Image Optimizer service";
+        imgvideo_shield_resp_body_bytes: Bytes, "Total body bytes of video delivered via a shield from the Fastly Image Optimizer service";
+        imgvideo_shield_resp_header_bytes: Bytes, "Total header bytes of video delivered via a shield from the Fastly Image Optimizer service";
+        ipv6: Count, "Number of requests that were received over IPv6";
+        log: Count, "Number of log lines sent";
+        log_bytes: Bytes, "Total log bytes sent";
+        logging: Count, "Number of log lines sent";
+        miss: Count, "Number of cache misses";
+        miss_resp_body_bytes: Bytes, "Total body bytes delivered for cache misses";
+        miss_sub_count: Count, "Number of executions of the vcl_miss Varnish subroutine";
+        miss_sub_time: Seconds, "Time spent inside the vcl_miss Varnish subroutine";
+        miss_time: Seconds, "Total amount of time spent processing cache misses";
+        object_size_100k: Count, "Number of objects served that were between 10KB and 100KB in size";
+        object_size_100m: Count, "Number of objects served that were between 10MB and 100MB in size";
+        object_size_10k: Count, "Number of objects served that were between 1KB and 10KB in size";
+        object_size_10m: Count, "Number of objects served that were between 1MB and 10MB in size";
+        object_size_1g: Count, "Number of objects served that were between 100MB and 1GB in size";
+        object_size_1k: Count, "Number of objects served that were under 1KB in size";
+        object_size_1m: Count, "Number of objects served that were between 100KB and 1MB in size";
+        object_size_other: Count, "Number of objects served that were larger than 1GB in size";
+        origin_cache_fetch_resp_body_bytes: Bytes, "Body bytes received from origin for cacheable content";
+        origin_cache_fetch_resp_header_bytes: Bytes, "Header bytes received from an origin for cacheable content";
+        origin_cache_fetches: Count, "Number of cache misses at the edge that resulted in a fetch from origin";
+        origin_fetch_body_bytes: Bytes, "Total request body bytes sent to origin";
+        origin_fetch_header_bytes: Bytes, "Total request header bytes sent to origin";
+        origin_fetch_resp_body_bytes: Bytes, "Total body bytes received from origin";
+        origin_fetch_resp_header_bytes: Bytes, "Total header bytes received from origin";
+        origin_fetches: Count, "Number of requests sent to origin";
+        origin_revalidations: Count, "Number of responses received from origin with a 304 status code in response to an If-Modified-Since or If-None-Match request";
+        otfp: Count, "Number of responses that came from the Fastly On-the-Fly Packager for On Demand Streaming service for video-on-demand";
+        otfp_deliver_time: Seconds, "Total amount of time spent delivering a response from the Fastly On-the-Fly Packager for On Demand Streaming service for video-on-demand";
+        otfp_manifests: Count, "Number of responses that were manifest files from the Fastly On-the-Fly Packager for On Demand Streaming service for video-on-demand";
+        otfp_resp_body_bytes: Bytes, "Total body bytes delivered from the Fastly On-the-Fly Packager for On Demand Streaming service for video-on-demand";
+        otfp_resp_header_bytes: Bytes, "Total header bytes delivered from the Fastly On-the-Fly Packager for On Demand Streaming service for video-on-demand";
+        otfp_shield: Count, "Number of responses delivered from the Fastly On-the-Fly Packager for On Demand Streaming service for video-on-demand via a shield";
+        otfp_shield_resp_body_bytes: Bytes, "Total body bytes delivered via a shield for the Fastly On-the-Fly Packager for On Demand Streaming service for video-on-demand";
+        otfp_shield_resp_header_bytes: Bytes, "Total header bytes delivered via a shield for the Fastly On-the-Fly Packager for On Demand Streaming service for video-on-demand";
+        otfp_shield_time: Seconds, "Total amount of time spent delivering a response via a shield from the Fastly On-the-Fly Packager for On Demand Streaming service for video-on-demand";
+        pass: Count, "Number of requests that passed through the CDN without being cached";
+        pass_resp_body_bytes: Bytes, "Total body bytes delivered for cache passes";
+        pass_sub_count: Count, "Number of executions of the vcl_pass Varnish subroutine";
+        pass_sub_time: Seconds, "Time spent inside the vcl_pass Varnish subroutine";
+        pass_time: Seconds, "Total amount of time spent processing cache passes";
+        pci: Count, "Number of responses with the PCI flag turned on";
+        pipe_sub_count: Count, "Number of executions of the vcl_pipe Varnish subroutine";
+        pipe_sub_time: Seconds, "Time spent inside the vcl_pipe Varnish subroutine";
+        predeliver_sub_count: Count, "Number of executions of the vcl_predeliver Varnish subroutine";
+        predeliver_sub_time: Seconds, "Time spent inside the vcl_predeliver Varnish subroutine";
+        prehash_sub_count: Count, "Number of executions of the vcl_prehash Varnish subroutine";
+        prehash_sub_time: Seconds, "Time spent inside the vcl_prehash Varnish subroutine";
+        recv_sub_count: Count, "Number of executions of the vcl_recv Varnish subroutine";
+        recv_sub_time: Seconds, "Time spent inside the vcl_recv Varnish subroutine";
+        req_body_bytes: Bytes, "Total body bytes received";
+        req_header_bytes: Bytes, "Total header bytes received";
+        requests: Count, "Number of requests processed";
+        resp_body_bytes: Bytes, "Total body bytes delivered";
+        resp_header_bytes: Bytes, "Total header bytes delivered";
+        restarts: Count, "Number of restarts performed";
+        segblock_origin_fetches: Count, "Number of Range requests to origin for segments of resources when using segmented caching";
+        segblock_shield_fetches: Count, "Number of Range requests to a shield for segments of resources when using segmented caching";
+        shield: Count, "Number of requests from edge to the shield POP";
+        shield_cache_fetches: Count, "Number of cache misses at the shield that resulted in a fetch from origin";
+        shield_fetch_body_bytes: Bytes, "Total request body bytes sent to a shield";
+        shield_fetch_header_bytes: Bytes, "Total request header bytes sent to a shield";
+        shield_fetch_resp_body_bytes: Bytes, "Total response body bytes sent from a shield to the edge";
+        shield_fetch_resp_header_bytes: Bytes, "Total response header bytes sent from a shield to the edge";
+        shield_fetches: Count, "Number of requests made from one Fastly POP to another, as part of shielding";
+        shield_resp_body_bytes: Bytes, "Total body bytes delivered via a shield";
+        shield_resp_header_bytes: Bytes, "Total header bytes delivered via a shield";
+        shield_revalidations: Count, "Number of responses received from origin with a 304 status code, in response to an If-Modified-Since or If-None-Match request to a shield";
+        status_1xx: Count, "Number of \"Informational\" category status codes delivered";
+        status_200: Count, "Number of responses delivered with status code 200";
+        status_204: Count, "Number of responses delivered with status code 204";
+        status_206: Count, "Number of responses delivered with status code 206";
+        status_2xx: Count, "Number of \"Success\" category status codes delivered";
+        status_301: Count, "Number of responses delivered with status code 301";
+        status_302: Count, "Number of responses delivered with status code 302";
+        status_304: Count, "Number of responses delivered with status code 304";
+        status_3xx: Count, "Number of \"Redirection\" category status codes delivered";
+        status_400: Count, "Number of responses delivered with status code 400";
+        status_401: Count, "Number of responses delivered with status code 401";
+        status_403: Count, "Number of responses delivered with status code 403";
+        status_404: Count, "Number of responses delivered with status code 404";
+        status_416: Count, "Number of responses delivered with status code 416";
+        status_429: Count, "Number of responses delivered with status code 429";
+        status_4xx: Count, "Number of \"Client Error\" category status codes delivered";
+        status_500: Count, "Number of responses delivered with status code 500";
+        status_501: Count, "Number of responses delivered with status code 501";
+        status_502: Count, "Number of responses delivered with status code 502";
+        status_503: Count, "Number of responses delivered with status code 503";
+        status_504: Count, "Number of responses delivered with status code 504";
+        status_505: Count, "Number of responses delivered with status code 505";
+        status_5xx: Count, "Number of \"Server Error\" category status codes delivered";
+        synth: Count, "Number of synthetic responses";
+        tls: Count, "Number of requests that were received over TLS";
+        tls_v10: Count, "Number of requests received over TLS 1.0";
+        tls_v11: Count, "Number of requests received over TLS 1.1";
+        tls_v12: Count, "Number of requests received over TLS 1.2";
+        tls_v13: Count, "Number of requests received over TLS 1.3";
+        uncacheable: Count, "Number of requests that were designated uncachable";
+        video: Count, "Number of responses with the video segment or video manifest MIME type";
+        waf_blocked: Count, "Number of requests that triggered a WAF rule and were blocked";
+        waf_logged: Count, "Number of requests that triggered a WAF rule and were logged";
+        waf_passed: Count, "Number of requests that triggered a WAF rule and were passed";
+    };
 }
+
+/// Client to get service real time data
+/// Query methods are those of [`RtClient`], which also implements [`crate::realtime::RealtimeClient`]
+pub type ServiceClient = RtClient<ServiceDataInSecond>;
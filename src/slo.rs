@@ -0,0 +1,440 @@
+use crate::alert::{AlertEvent, AlertState, Expression, Notifier};
+use crate::realtime::RtResponse;
+use crate::service::{ServiceDataInSecond, ServiceStats};
+use crate::timeseries::{Scope, ScopeFilter};
+use anyhow::Result;
+use std::collections::{HashMap, HashSet, VecDeque};
+use tokio::sync::mpsc::UnboundedReceiver;
+
+/// Service level indicator, the good and total events of a second
+#[derive(Debug, Clone)]
+pub enum Sli {
+    /// Ratio of two expressions over the metrics of [`ServiceStats`], see [`Expression`]
+    Ratio { good: Expression, total: Expression },
+
+    /// Origin fetches of `miss_histogram` faster than `threshold_ms` milliseconds
+    Latency { threshold_ms: u64 },
+}
+
+impl Sli {
+    /// Fails when an expression does not parse or uses a metric unknown to [`ServiceStats`]
+    pub fn ratio(good: &str, total: &str) -> Result<Sli> {
+        let good = Expression::parse(good)?;
+        let total = Expression::parse(total)?;
+        good.validate::<ServiceStats>()?;
+        total.validate::<ServiceStats>()?;
+        Ok(Sli::Ratio { good, total })
+    }
+
+    /// Requests not answered with a 5xx
+    pub fn availability() -> Sli {
+        Sli::ratio("requests - status_5xx", "requests").unwrap()
+    }
+
+    pub fn latency(threshold_ms: u64) -> Sli {
+        Sli::Latency { threshold_ms }
+    }
+
+    /// Good and total events of `stats`, good being within 0 and total
+    pub fn measure(&self, stats: &ServiceStats) -> (f64, f64) {
+        let (good, total) = match self {
+            Sli::Ratio { good, total } => (good.eval(stats), total.eval(stats)),
+            Sli::Latency { threshold_ms } => {
+                let histogram = &stats.miss_histogram;
+                let good: u64 = histogram
+                    .buckets()
+                    .filter(|(upper_ms, _)| upper_ms <= threshold_ms)
+                    .map(|(_, count)| count)
+                    .sum();
+                (good as f64, histogram.total() as f64)
+            }
+        };
+        let total = if total.is_finite() {
+            total.max(0.0)
+        } else {
+            0.0
+        };
+        let good = if good.is_finite() {
+            good.clamp(0.0, total)
+        } else {
+            0.0
+        };
+        (good, total)
+    }
+}
+
+/// Pair of windows over which the error budget must burn faster than `factor` to alert
+///
+/// The long window makes the alert significant, the short one makes it resolve soon after the burn stops.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BurnWindow {
+    /// Long window in seconds
+    pub long: u64,
+
+    /// Short window in seconds
+    pub short: u64,
+
+    /// Burn rate from which to alert, 1 spending the budget exactly over the period
+    pub factor: f64,
+}
+
+impl BurnWindow {
+    pub fn new(long: u64, short: u64, factor: f64) -> BurnWindow {
+        BurnWindow {
+            long: long.max(1),
+            short: short.max(1),
+            factor,
+        }
+    }
+
+    /// Name of the windows, e.g. `1h/5m`
+    pub fn name(&self) -> String {
+        format!("{}/{}", duration(self.long), duration(self.short))
+    }
+}
+
+fn duration(secs: u64) -> String {
+    if secs.is_multiple_of(3600) {
+        format!("{}h", secs / 3600)
+    } else if secs.is_multiple_of(60) {
+        format!("{}m", secs / 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+/// Service level objective: the share of good events of an [`Sli`] to reach over a period
+///
+/// ```
+/// use fastly_rt::slo::{Sli, Slo};
+///
+/// let slo = Slo::new("availability", Sli::availability(), 0.999, 30 * 86400).service("www");
+/// assert_eq!(slo.windows[0].name(), "1h/5m");
+/// assert_eq!(slo.windows[1].name(), "6h/30m");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Slo {
+    pub name: String,
+
+    pub sli: Sli,
+
+    /// Share of good events to reach, e.g. 0.999
+    pub target: f64,
+
+    /// Rolling period of the objective in seconds
+    pub period: u64,
+
+    /// Burn rate alerts, by default 1h/5m burning 14.4 times and 6h/30m burning 6 times,
+    /// spending 2% and 5% of a 30 day budget
+    pub windows: Vec<BurnWindow>,
+
+    /// Services the objective applies to, measured aggregated across POPs
+    pub filter: ScopeFilter,
+}
+
+impl Slo {
+    pub fn new(name: &str, sli: Sli, target: f64, period: u64) -> Slo {
+        Slo {
+            name: name.to_string(),
+            sli,
+            target,
+            period: period.max(1),
+            windows: vec![
+                BurnWindow::new(3600, 300, 14.4),
+                BurnWindow::new(6 * 3600, 1800, 6.0),
+            ],
+            filter: ScopeFilter::default(),
+        }
+    }
+
+    pub fn with_windows(mut self, windows: Vec<BurnWindow>) -> Slo {
+        self.windows = windows;
+        self
+    }
+
+    /// Apply the objective to services matching `pattern` only, may be called several times
+    pub fn service(mut self, pattern: &str) -> Slo {
+        self.filter.services.push(pattern.to_string());
+        self
+    }
+
+    /// Name of the alert rule of burn window `window`, e.g. `availability burn 1h/5m`
+    pub fn rule_name(&self, window: &BurnWindow) -> String {
+        format!("{} burn {}", self.name, window.name())
+    }
+
+    /// Error budget burn rate of `good` of `total` events, 0 without events
+    pub fn burn_rate(&self, good: f64, total: f64) -> f64 {
+        if total <= 0.0 {
+            return 0.0;
+        }
+        let allowed = 1.0 - self.target;
+        let bad = (total - good) / total;
+        if allowed > 0.0 {
+            bad / allowed
+        } else if bad > 0.0 {
+            f64::INFINITY
+        } else {
+            0.0
+        }
+    }
+}
+
+/// State of an objective for a service
+#[derive(Debug, Clone, PartialEq)]
+pub struct SloStatus {
+    pub slo: String,
+
+    pub service: String,
+
+    /// Good events over the period
+    pub good: f64,
+
+    /// Events over the period
+    pub total: f64,
+
+    /// Share of good events over the period, 1 without events
+    pub compliance: f64,
+
+    /// Share of the error budget of the period left, negative once overspent
+    pub budget_remaining: f64,
+
+    /// Burn rates of each burn window, long then short
+    pub burn_rates: Vec<(BurnWindow, f64, f64)>,
+}
+
+/// Good and total events of a service, by second for the burn windows and by minute for the period
+#[derive(Default)]
+struct Ledger {
+    latest: u64,
+    seconds: VecDeque<(u64, f64, f64)>,
+    minutes: VecDeque<(u64, f64, f64)>,
+    firing: HashSet<usize>,
+}
+
+impl Ledger {
+    fn add(&mut self, recorded: u64, good: f64, total: f64, slo: &Slo, keep_secs: u64) {
+        self.latest = recorded;
+        self.seconds.push_back((recorded, good, total));
+        while matches!(self.seconds.front(), Some((first, _, _)) if first + keep_secs <= recorded) {
+            self.seconds.pop_front();
+        }
+
+        let minute = recorded - recorded % 60;
+        match self.minutes.back_mut() {
+            Some((start, minute_good, minute_total)) if *start == minute => {
+                *minute_good += good;
+                *minute_total += total;
+            }
+            _ => self.minutes.push_back((minute, good, total)),
+        }
+        // Minutes ending before the period
+        while matches!(self.minutes.front(), Some((start, _, _)) if start + 60 + slo.period <= recorded + 1)
+        {
+            self.minutes.pop_front();
+        }
+    }
+
+    /// Good and total events of the last `secs` seconds
+    fn window(&self, secs: u64) -> (f64, f64) {
+        self.seconds
+            .iter()
+            .rev()
+            .take_while(|(recorded, _, _)| recorded + secs > self.latest)
+            .fold(
+                (0.0, 0.0),
+                |(good, total), (_, second_good, second_total)| {
+                    (good + second_good, total + second_total)
+                },
+            )
+    }
+
+    /// Good and total events of the period, by whole minutes, so up to a minute more
+    fn period(&self) -> (f64, f64) {
+        self.minutes.iter().fold(
+            (0.0, 0.0),
+            |(good, total), (_, minute_good, minute_total)| {
+                (good + minute_good, total + minute_total)
+            },
+        )
+    }
+}
+
+/// Tracks objectives of services on the real time stream: error budget left over the period,
+/// and burn rates over multiple windows
+///
+/// A burn window fires once the budget burns faster than its factor over both its long and short
+/// windows, and resolves once it does not. Alerts are [`AlertEvent`] of the aggregated scope,
+/// the rule being named after the objective and window, see [`Slo::rule_name`], and the value
+/// being the burn rate of the long window, so they can be handled as those of an
+/// [`crate::alert::AlertEngine`].
+///
+/// Seconds not later than the last one of a service are ignored.
+///
+/// ```
+/// use fastly_rt::alert::AlertState;
+/// use fastly_rt::slo::{BurnWindow, Sli, Slo, SloTracker};
+/// use fastly_rt::service::ServiceDataInSecond;
+///
+/// let slo = Slo::new("availability", Sli::availability(), 0.99, 86400)
+///     .with_windows(vec![BurnWindow::new(60, 10, 10.0)]);
+/// let mut tracker = SloTracker::new(vec![slo]);
+///
+/// let mut events = Vec::new();
+/// for recorded in 1..=60 {
+///     let mut data = ServiceDataInSecond { recorded, ..Default::default() };
+///     data.aggregated.requests = 100;
+///     data.aggregated.status_5xx = if recorded > 25 { 20 } else { 0 };
+///     events.extend(tracker.observe("www", &data));
+/// }
+///
+/// assert_eq!(events.len(), 1);
+/// assert_eq!(events[0].rule, "availability burn 1m/10s");
+/// assert_eq!(events[0].state, AlertState::Firing);
+/// assert_eq!(events[0].recorded, 51);
+/// ```
+pub struct SloTracker {
+    slos: Vec<Slo>,
+    ledgers: HashMap<(usize, String), Ledger>,
+    notifier: Notifier,
+}
+
+impl SloTracker {
+    pub fn new(slos: Vec<Slo>) -> SloTracker {
+        SloTracker {
+            slos,
+            ledgers: HashMap::new(),
+            notifier: Notifier::default(),
+        }
+    }
+
+    pub fn slos(&self) -> &[Slo] {
+        &self.slos
+    }
+
+    /// Call `callback` on every alert event
+    pub fn on_event<F: FnMut(&AlertEvent) + Send + 'static>(mut self, callback: F) -> SloTracker {
+        self.notifier.on_event(callback);
+        self
+    }
+
+    /// Receive every alert event from now on
+    pub fn subscribe(&mut self) -> UnboundedReceiver<AlertEvent> {
+        self.notifier.subscribe()
+    }
+
+    /// Account a new second of `service`, returns the alert events of burn windows, also notified
+    pub fn observe(&mut self, service: &str, data: &ServiceDataInSecond) -> Vec<AlertEvent> {
+        let mut events = Vec::new();
+
+        for (index, slo) in self.slos.iter().enumerate() {
+            if !slo.filter.applies(service, &Scope::aggregated()) {
+                continue;
+            }
+            let ledger = self
+                .ledgers
+                .entry((index, service.to_string()))
+                .or_default();
+            if data.recorded <= ledger.latest {
+                continue;
+            }
+
+            let (good, total) = slo.sli.measure(&data.aggregated);
+            let keep_secs = slo
+                .windows
+                .iter()
+                .map(|window| window.long.max(window.short))
+                .max();
+            ledger.add(data.recorded, good, total, slo, keep_secs.unwrap_or(1));
+
+            for (window_index, window) in slo.windows.iter().enumerate() {
+                let (long_good, long_total) = ledger.window(window.long);
+                let (short_good, short_total) = ledger.window(window.short);
+                let long = slo.burn_rate(long_good, long_total);
+                let short = slo.burn_rate(short_good, short_total);
+                let burning = long > window.factor && short > window.factor;
+
+                let state = match (burning, ledger.firing.contains(&window_index)) {
+                    (true, false) => {
+                        ledger.firing.insert(window_index);
+                        AlertState::Firing
+                    }
+                    (false, true) => {
+                        ledger.firing.remove(&window_index);
+                        AlertState::Resolved
+                    }
+                    _ => continue,
+                };
+                events.push(AlertEvent {
+                    rule: slo.rule_name(window),
+                    service: service.to_string(),
+                    scope: Scope::aggregated(),
+                    state,
+                    value: long,
+                    recorded: data.recorded,
+                    since: data.recorded,
+                });
+            }
+        }
+
+        self.notifier.notify(&events);
+        events
+    }
+
+    /// Account every second of `response`
+    pub fn observe_response(
+        &mut self,
+        service: &str,
+        response: &RtResponse<ServiceDataInSecond>,
+    ) -> Vec<AlertEvent> {
+        response
+            .data
+            .iter()
+            .flat_map(|data| self.observe(service, data))
+            .collect()
+    }
+
+    /// Status of each objective of `service`, in the order of the objectives
+    pub fn status(&self, service: &str) -> Vec<SloStatus> {
+        self.slos
+            .iter()
+            .enumerate()
+            .filter_map(|(index, slo)| {
+                let ledger = self.ledgers.get(&(index, service.to_string()))?;
+                let (good, total) = ledger.period();
+                let budget = (1.0 - slo.target) * total;
+                let budget_remaining = if total <= 0.0 {
+                    1.0
+                } else if budget > 0.0 {
+                    1.0 - (total - good) / budget
+                } else if good < total {
+                    f64::NEG_INFINITY
+                } else {
+                    1.0
+                };
+
+                Some(SloStatus {
+                    slo: slo.name.clone(),
+                    service: service.to_string(),
+                    good,
+                    total,
+                    compliance: if total > 0.0 { good / total } else { 1.0 },
+                    budget_remaining,
+                    burn_rates: slo
+                        .windows
+                        .iter()
+                        .map(|window| {
+                            let (long_good, long_total) = ledger.window(window.long);
+                            let (short_good, short_total) = ledger.window(window.short);
+                            (
+                                window.clone(),
+                                slo.burn_rate(long_good, long_total),
+                                slo.burn_rate(short_good, short_total),
+                            )
+                        })
+                        .collect(),
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,429 @@
+use crate::histogram::LatencyHistogram;
+use crate::metric::{MetricKind, Metrics};
+use crate::origin::{OriginDataInSecond, OriginStats};
+use crate::service::{ServiceDataInSecond, ServiceStats};
+use anyhow::Result;
+use rusqlite::{params, Connection, Transaction};
+use std::collections::BTreeSet;
+use std::path::Path;
+
+const SCHEMA: &str = "
+PRAGMA foreign_keys = ON;
+
+CREATE TABLE IF NOT EXISTS scopes (
+    id INTEGER PRIMARY KEY,
+    kind TEXT NOT NULL,
+    service TEXT NOT NULL,
+    resolution INTEGER NOT NULL,
+    recorded INTEGER NOT NULL,
+    pop TEXT NOT NULL,
+    origin TEXT NOT NULL,
+    UNIQUE (kind, service, resolution, recorded, pop, origin)
+);
+
+CREATE TABLE IF NOT EXISTS metric_values (
+    scope_id INTEGER NOT NULL REFERENCES scopes (id) ON DELETE CASCADE,
+    metric TEXT NOT NULL,
+    value REAL NOT NULL,
+    PRIMARY KEY (scope_id, metric)
+) WITHOUT ROWID;
+
+CREATE TABLE IF NOT EXISTS miss_histogram (
+    scope_id INTEGER NOT NULL REFERENCES scopes (id) ON DELETE CASCADE,
+    upper_ms INTEGER NOT NULL,
+    count INTEGER NOT NULL,
+    PRIMARY KEY (scope_id, upper_ms)
+) WITHOUT ROWID;
+";
+
+/// Re-aggregate the scopes of resolution `?3` into the window of resolution `?4` starting at `?5`,
+/// summing metrics but for the `{gauges}`, which keep their largest value
+const ROLLUP: &[&str] = &[
+    "DELETE FROM scopes WHERE kind = ?1 AND service = ?2 AND resolution = ?4 AND recorded = ?5",
+    "INSERT INTO scopes (kind, service, resolution, recorded, pop, origin)
+        SELECT DISTINCT kind, service, ?4, ?5, pop, origin FROM scopes
+        WHERE kind = ?1 AND service = ?2 AND resolution = ?3 AND recorded >= ?5 AND recorded < ?5 + ?4",
+    "INSERT INTO metric_values (scope_id, metric, value)
+        SELECT rollup.id, v.metric,
+            CASE WHEN v.metric IN ({gauges}) THEN MAX(v.value) ELSE SUM(v.value) END FROM scopes s
+        JOIN metric_values v ON v.scope_id = s.id
+        JOIN scopes rollup ON rollup.kind = s.kind AND rollup.service = s.service
+            AND rollup.resolution = ?4 AND rollup.recorded = ?5 AND rollup.pop = s.pop AND rollup.origin = s.origin
+        WHERE s.kind = ?1 AND s.service = ?2 AND s.resolution = ?3 AND s.recorded >= ?5 AND s.recorded < ?5 + ?4
+        GROUP BY rollup.id, v.metric",
+    "INSERT INTO miss_histogram (scope_id, upper_ms, count)
+        SELECT rollup.id, h.upper_ms, SUM(h.count) FROM scopes s
+        JOIN miss_histogram h ON h.scope_id = s.id
+        JOIN scopes rollup ON rollup.kind = s.kind AND rollup.service = s.service
+            AND rollup.resolution = ?4 AND rollup.recorded = ?5 AND rollup.pop = s.pop AND rollup.origin = s.origin
+        WHERE s.kind = ?1 AND s.service = ?2 AND s.resolution = ?3 AND s.recorded >= ?5 AND s.recorded < ?5 + ?4
+        GROUP BY rollup.id, h.upper_ms",
+];
+
+const SERVICE: &str = "service";
+
+const ORIGIN: &str = "origin";
+
+/// Duration covered by each row of a query
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    /// Seconds as polled
+    Second,
+
+    /// Sums over minutes, or largest values of gauges, aligned on the minute
+    Minute,
+
+    /// Sums over hours, or largest values of gauges, aligned on the hour
+    Hour,
+}
+
+impl Resolution {
+    pub fn seconds(&self) -> u64 {
+        match self {
+            Resolution::Second => 1,
+            Resolution::Minute => 60,
+            Resolution::Hour => 3600,
+        }
+    }
+}
+
+/// Part of the data of a service, or of an origin
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Scope {
+    /// Aggregated across POPs
+    Aggregated,
+
+    /// One POP, e.g. `NRT`
+    Pop(String),
+}
+
+impl Scope {
+    fn pop(&self) -> &str {
+        match self {
+            Scope::Aggregated => "",
+            Scope::Pop(pop_name) => pop_name,
+        }
+    }
+}
+
+/// History of real time data in a SQLite database
+///
+/// Every second is stored as one scope per POP, origin or aggregation, with the metrics of the catalog
+/// which are not zero, and `miss_histogram` buckets. Minute and hour rollups are stored in the same tables
+/// with a resolution of 60 and 3600 seconds, and updated along with the seconds they cover.
+/// Storing a second again replaces it, so overlapping polls can be stored as they are.
+///
+/// ```
+/// use fastly_rt::service::ServiceDataInSecond;
+/// use fastly_rt::sqlite::{Resolution, Scope, Store};
+///
+/// let mut store = Store::in_memory().unwrap();
+/// let mut data = ServiceDataInSecond { recorded: 60, ..Default::default() };
+/// data.aggregated.requests = 3;
+/// store.upsert_service("www", &[data.clone(), data]).unwrap();
+///
+/// let series = store.service("www", &Scope::Aggregated, Resolution::Minute, 0, 3600).unwrap();
+/// assert_eq!(series.len(), 1);
+/// assert_eq!(series[0].1.requests, 3);
+/// ```
+pub struct Store {
+    connection: Connection,
+}
+
+impl Store {
+    /// Open the database at `path`, created if missing
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Store> {
+        Store::new(Connection::open(path)?)
+    }
+
+    pub fn in_memory() -> Result<Store> {
+        Store::new(Connection::open_in_memory()?)
+    }
+
+    fn new(connection: Connection) -> Result<Store> {
+        connection.execute_batch(SCHEMA)?;
+        Ok(Store { connection })
+    }
+
+    /// Insert or replace seconds of data of `service`, and update their rollups
+    pub fn upsert_service(&mut self, service: &str, seconds: &[ServiceDataInSecond]) -> Result<()> {
+        let tx = self.connection.transaction()?;
+        for data in seconds {
+            delete_second(&tx, SERVICE, service, data.recorded)?;
+
+            let id = insert_scope(
+                &tx,
+                SERVICE,
+                service,
+                data.recorded,
+                "",
+                "",
+                &data.aggregated,
+            )?;
+            insert_histogram(&tx, id, &data.aggregated.miss_histogram)?;
+            for (pop_name, stats) in &data.datacenter {
+                let id = insert_scope(&tx, SERVICE, service, data.recorded, pop_name, "", stats)?;
+                insert_histogram(&tx, id, &stats.miss_histogram)?;
+            }
+        }
+        update_rollups::<ServiceStats>(
+            &tx,
+            SERVICE,
+            service,
+            seconds.iter().map(|data| data.recorded),
+        )?;
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// Insert or replace seconds of data of the origins of `service`, and update their rollups
+    pub fn upsert_origins(&mut self, service: &str, seconds: &[OriginDataInSecond]) -> Result<()> {
+        let tx = self.connection.transaction()?;
+        for data in seconds {
+            delete_second(&tx, ORIGIN, service, data.recorded)?;
+
+            for (origin_name, stats) in &data.aggregated {
+                insert_scope(&tx, ORIGIN, service, data.recorded, "", origin_name, stats)?;
+            }
+            for (pop_name, origins) in &data.datacenter {
+                for (origin_name, stats) in origins {
+                    insert_scope(
+                        &tx,
+                        ORIGIN,
+                        service,
+                        data.recorded,
+                        pop_name,
+                        origin_name,
+                        stats,
+                    )?;
+                }
+            }
+        }
+        update_rollups::<OriginStats>(
+            &tx,
+            ORIGIN,
+            service,
+            seconds.iter().map(|data| data.recorded),
+        )?;
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// Stats of `service` in `scope` with `recorded` in `from..to`, at `resolution`, in order
+    pub fn service(
+        &self,
+        service: &str,
+        scope: &Scope,
+        resolution: Resolution,
+        from: u64,
+        to: u64,
+    ) -> Result<Vec<(u64, ServiceStats)>> {
+        let mut series: Vec<(u64, ServiceStats)> =
+            self.series(SERVICE, service, scope, "", resolution, from, to)?;
+
+        let mut statement = self.connection.prepare_cached(
+            "SELECT s.recorded, h.upper_ms, h.count FROM scopes s
+            JOIN miss_histogram h ON h.scope_id = s.id
+            WHERE s.kind = ?1 AND s.service = ?2 AND s.resolution = ?3 AND s.pop = ?4 AND s.origin = ''
+                AND s.recorded >= ?5 AND s.recorded < ?6
+            ORDER BY s.recorded",
+        )?;
+        let mut rows = statement.query(params![
+            SERVICE,
+            service,
+            resolution.seconds(),
+            scope.pop(),
+            from,
+            to
+        ])?;
+        let mut index = 0;
+        while let Some(row) = rows.next()? {
+            let recorded: u64 = row.get(0)?;
+            while series[index].0 != recorded {
+                index += 1;
+            }
+            series[index].1.miss_histogram.add(row.get(1)?, row.get(2)?);
+        }
+
+        Ok(series)
+    }
+
+    /// Stats of `service` in `scope` summed over `from..to`
+    pub fn service_total(
+        &self,
+        service: &str,
+        scope: &Scope,
+        from: u64,
+        to: u64,
+    ) -> Result<ServiceStats> {
+        let mut total = ServiceStats::default();
+        for (_, stats) in self.service(service, scope, Resolution::Second, from, to)? {
+            total += &stats;
+        }
+        Ok(total)
+    }
+
+    /// Stats of `origin` of `service` in `scope` with `recorded` in `from..to`, at `resolution`, in order
+    pub fn origin(
+        &self,
+        service: &str,
+        origin: &str,
+        scope: &Scope,
+        resolution: Resolution,
+        from: u64,
+        to: u64,
+    ) -> Result<Vec<(u64, OriginStats)>> {
+        self.series(ORIGIN, service, scope, origin, resolution, from, to)
+    }
+
+    /// Names of the origins of `service` with data in `from..to`
+    pub fn origins(&self, service: &str, from: u64, to: u64) -> Result<Vec<String>> {
+        let mut statement = self.connection.prepare_cached(
+            "SELECT DISTINCT origin FROM scopes
+            WHERE kind = ?1 AND service = ?2 AND resolution = 1 AND recorded >= ?3 AND recorded < ?4
+            ORDER BY origin",
+        )?;
+        let origins = statement
+            .query_map(params![ORIGIN, service, from, to], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(origins)
+    }
+
+    /// Remove data with `recorded` before `before` at `resolution`, e.g. to keep seconds for a day only
+    ///
+    /// Rollups are rebuilt from the data they cover when it is stored again, so only windows
+    /// which are complete should be pruned. Returns the number of scopes removed.
+    pub fn prune(&mut self, resolution: Resolution, before: u64) -> Result<usize> {
+        let removed = self.connection.execute(
+            "DELETE FROM scopes WHERE resolution = ?1 AND recorded < ?2",
+            params![resolution.seconds(), before],
+        )?;
+        Ok(removed)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn series<S: Metrics + Default>(
+        &self,
+        kind: &str,
+        service: &str,
+        scope: &Scope,
+        origin: &str,
+        resolution: Resolution,
+        from: u64,
+        to: u64,
+    ) -> Result<Vec<(u64, S)>> {
+        let mut statement = self.connection.prepare_cached(
+            "SELECT s.recorded, v.metric, v.value FROM scopes s
+            LEFT JOIN metric_values v ON v.scope_id = s.id
+            WHERE s.kind = ?1 AND s.service = ?2 AND s.resolution = ?3 AND s.pop = ?4 AND s.origin = ?5
+                AND s.recorded >= ?6 AND s.recorded < ?7
+            ORDER BY s.recorded",
+        )?;
+        let mut rows = statement.query(params![
+            kind,
+            service,
+            resolution.seconds(),
+            scope.pop(),
+            origin,
+            from,
+            to
+        ])?;
+
+        let mut series: Vec<(u64, S)> = Vec::new();
+        while let Some(row) = rows.next()? {
+            let recorded: u64 = row.get(0)?;
+            if series.last().map(|(last, _)| *last) != Some(recorded) {
+                series.push((recorded, S::default()));
+            }
+
+            let metric: Option<String> = row.get(1)?;
+            // Metrics unknown to this version are left out
+            if let Some(metric) = metric.and_then(|metric| S::metric_info(&metric)) {
+                (metric.set)(&mut series.last_mut().unwrap().1, row.get(2)?);
+            }
+        }
+
+        Ok(series)
+    }
+}
+
+fn delete_second(tx: &Transaction, kind: &str, service: &str, recorded: u64) -> Result<()> {
+    tx.prepare_cached(
+        "DELETE FROM scopes WHERE kind = ?1 AND service = ?2 AND resolution = 1 AND recorded = ?3",
+    )?
+    .execute(params![kind, service, recorded])?;
+    Ok(())
+}
+
+/// Insert a scope of a second with its metrics which are not zero, returns its id
+fn insert_scope<S: Metrics>(
+    tx: &Transaction,
+    kind: &str,
+    service: &str,
+    recorded: u64,
+    pop: &str,
+    origin: &str,
+    stats: &S,
+) -> Result<i64> {
+    tx.prepare_cached(
+        "INSERT INTO scopes (kind, service, resolution, recorded, pop, origin) VALUES (?1, ?2, 1, ?3, ?4, ?5)",
+    )?
+    .execute(params![kind, service, recorded, pop, origin])?;
+    let id = tx.last_insert_rowid();
+
+    let mut statement = tx.prepare_cached(
+        "INSERT INTO metric_values (scope_id, metric, value) VALUES (?1, ?2, ?3)",
+    )?;
+    for (metric, value) in stats.metrics() {
+        if value != 0.0 {
+            statement.execute(params![id, metric.name, value])?;
+        }
+    }
+
+    Ok(id)
+}
+
+fn insert_histogram(tx: &Transaction, id: i64, histogram: &LatencyHistogram) -> Result<()> {
+    let mut statement = tx.prepare_cached(
+        "INSERT INTO miss_histogram (scope_id, upper_ms, count) VALUES (?1, ?2, ?3)",
+    )?;
+    for (upper_ms, count) in histogram.buckets() {
+        if count != 0 {
+            statement.execute(params![id, upper_ms, count])?;
+        }
+    }
+    Ok(())
+}
+
+/// Rebuild the minute rollups covering `recorded`, then the hour rollups from the minutes
+fn update_rollups<S: Metrics>(
+    tx: &Transaction,
+    kind: &str,
+    service: &str,
+    recorded: impl Iterator<Item = u64>,
+) -> Result<()> {
+    let minutes: BTreeSet<u64> = recorded.map(|recorded| recorded / 60 * 60).collect();
+    let hours: BTreeSet<u64> = minutes.iter().map(|minute| minute / 3600 * 3600).collect();
+    let gauges = S::METRICS
+        .iter()
+        .filter(|metric| metric.kind == MetricKind::Gauge)
+        .map(|metric| format!("'{}'", metric.name))
+        .collect::<Vec<String>>()
+        .join(", ");
+
+    for (windows, from, to) in [
+        (minutes, Resolution::Second, Resolution::Minute),
+        (hours, Resolution::Minute, Resolution::Hour),
+    ] {
+        for start in windows {
+            for sql in ROLLUP {
+                tx.prepare_cached(&sql.replace("{gauges}", &gauges))?
+                    .execute(params![kind, service, from.seconds(), to.seconds(), start])?;
+            }
+        }
+    }
+
+    Ok(())
+}
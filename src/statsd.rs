@@ -0,0 +1,309 @@
+use crate::histogram::LatencyHistogram;
+use crate::metric::{sorted, Metric, MetricFilter, MetricKind, Metrics};
+use crate::origin::{OriginDataInSecond, OriginStats};
+use crate::service::{ServiceDataInSecond, ServiceStats};
+use anyhow::Result;
+use std::fmt::Write;
+use std::net::{ToSocketAddrs, UdpSocket};
+
+/// Dialect of the emitted lines
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flavor {
+    /// Plain StatsD, service, POP and origin are parts of the metric name,
+    /// e.g. `fastly_rt.www.NRT.requests:3|c`
+    Statsd,
+
+    /// DogStatsD, service, POP and origin are tags, e.g. `fastly_rt.requests:3|c|#service:www,pop:NRT`
+    DogStatsd,
+}
+
+/// Configuration of a [`StatsdSink`]
+#[derive(Debug, Clone)]
+pub struct StatsdConfig {
+    pub flavor: Flavor,
+
+    /// Prefix of metric names, without the trailing `.`
+    pub prefix: String,
+
+    /// Selection of the emitted metrics, and of those emitted by POP
+    pub filter: MetricFilter,
+
+    /// Tags added to every line, DogStatsD only
+    pub tags: Vec<(String, String)>,
+
+    /// Maximum size of a packet, lines are batched into packets up to this size
+    pub max_packet_size: usize,
+}
+
+impl Default for StatsdConfig {
+    fn default() -> StatsdConfig {
+        StatsdConfig {
+            flavor: Flavor::DogStatsd,
+            prefix: "fastly_rt".to_string(),
+            filter: MetricFilter::default(),
+            tags: Vec::new(),
+            max_packet_size: StatsdConfig::ETHERNET_PACKET_SIZE,
+        }
+    }
+}
+
+impl StatsdConfig {
+    /// Payload size recommended by StatsD for UDP over the Internet, under the 1472 bytes fitting
+    /// an Ethernet frame of 1500 bytes, to leave room for IP options and tunnels
+    pub const ETHERNET_PACKET_SIZE: usize = 1432;
+}
+
+/// Emits each polled second as StatsD counters, gauges for gauge metrics such as `compute_ram_used`,
+/// and `miss_histogram` as timings, over UDP
+///
+/// Counters with a zero value are left out, being added up by the StatsD server anyway.
+///
+/// ```no_run
+/// use fastly_rt::service::ServiceClient;
+/// use fastly_rt::statsd::{StatsdConfig, StatsdSink};
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let mut rt = ServiceClient::new("api_key", "service_id").unwrap();
+///     let sink = StatsdSink::connect("127.0.0.1:8125", StatsdConfig::default()).unwrap();
+///
+///     loop {
+///         for data in rt.get_stats_consecutive().await.unwrap().data {
+///             sink.send_service("www", &data).unwrap();
+///         }
+///     }
+/// }
+/// ```
+pub struct StatsdSink {
+    socket: UdpSocket,
+    config: StatsdConfig,
+}
+
+impl StatsdSink {
+    /// Send to the StatsD server at `addr`
+    pub fn connect<A: ToSocketAddrs>(addr: A, config: StatsdConfig) -> Result<StatsdSink> {
+        let socket = UdpSocket::bind(("0.0.0.0", 0))?;
+        socket.connect(addr)?;
+
+        Ok(StatsdSink { socket, config })
+    }
+
+    pub fn config(&self) -> &StatsdConfig {
+        &self.config
+    }
+
+    /// Send one second of data of `service`, returns the number of packets sent
+    pub fn send_service(&self, service: &str, data: &ServiceDataInSecond) -> Result<usize> {
+        self.send(&service_lines(&self.config, service, data))
+    }
+
+    /// Send one second of data of the origins of `service`, returns the number of packets sent
+    pub fn send_origins(&self, service: &str, data: &OriginDataInSecond) -> Result<usize> {
+        self.send(&origin_lines(&self.config, service, data))
+    }
+
+    fn send(&self, lines: &[String]) -> Result<usize> {
+        let packets = batch(lines, self.config.max_packet_size);
+        for packet in &packets {
+            self.socket.send(packet.as_bytes())?;
+        }
+
+        Ok(packets.len())
+    }
+}
+
+/// StatsD lines of one second of data of `service`
+pub fn service_lines(
+    config: &StatsdConfig,
+    service: &str,
+    data: &ServiceDataInSecond,
+) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    for metric in ServiceStats::METRICS {
+        if config.filter.allows_pop(metric.name) {
+            for (pop_name, stats) in sorted(&data.datacenter) {
+                let scope = [("service", service), ("pop", pop_name)];
+                push_metric(&mut lines, config, &scope, "", metric, stats);
+            }
+        } else if config.filter.allows(metric.name) {
+            let scope = [("service", service)];
+            push_metric(&mut lines, config, &scope, "", metric, &data.aggregated);
+        }
+    }
+
+    if config.filter.allows_pop("miss_histogram") {
+        for (pop_name, stats) in sorted(&data.datacenter) {
+            let scope = [("service", service), ("pop", pop_name)];
+            push_timings(&mut lines, config, &scope, &stats.miss_histogram);
+        }
+    } else if config.filter.allows("miss_histogram") {
+        let scope = [("service", service)];
+        push_timings(&mut lines, config, &scope, &data.aggregated.miss_histogram);
+    }
+
+    lines
+}
+
+/// StatsD lines of one second of data of the origins of `service`
+pub fn origin_lines(
+    config: &StatsdConfig,
+    service: &str,
+    data: &OriginDataInSecond,
+) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    for metric in OriginStats::METRICS {
+        let name = format!("origin_{}", metric.name);
+
+        if config.filter.allows_pop(&name) {
+            for (pop_name, origins) in sorted(&data.datacenter) {
+                for (origin_name, stats) in sorted(origins) {
+                    let scope = [
+                        ("service", service),
+                        ("pop", pop_name),
+                        ("origin", origin_name),
+                    ];
+                    push_metric(&mut lines, config, &scope, "origin.", metric, stats);
+                }
+            }
+        } else if config.filter.allows(&name) {
+            for (origin_name, stats) in sorted(&data.aggregated) {
+                let scope = [("service", service), ("origin", origin_name)];
+                push_metric(&mut lines, config, &scope, "origin.", metric, stats);
+            }
+        }
+    }
+
+    lines
+}
+
+/// Join lines with line feeds into packets of at most `max_packet_size` bytes,
+/// a line longer than that being sent alone
+pub fn batch(lines: &[String], max_packet_size: usize) -> Vec<String> {
+    let mut packets = Vec::new();
+    let mut packet = String::new();
+
+    for line in lines {
+        if !packet.is_empty() && packet.len() + 1 + line.len() > max_packet_size {
+            packets.push(std::mem::take(&mut packet));
+        }
+        if !packet.is_empty() {
+            packet.push('\n');
+        }
+        packet.push_str(line);
+    }
+    if !packet.is_empty() {
+        packets.push(packet);
+    }
+
+    packets
+}
+
+fn push_metric<S>(
+    lines: &mut Vec<String>,
+    config: &StatsdConfig,
+    scope: &[(&str, &str)],
+    infix: &str,
+    metric: &Metric<S>,
+    stats: &S,
+) {
+    let value = (metric.value)(stats);
+    let kind = match metric.kind {
+        MetricKind::Counter if value == 0.0 => return,
+        MetricKind::Counter => "c",
+        MetricKind::Gauge => "g",
+    };
+    let name = format!("{}{}", infix, metric.name);
+    lines.push(line(config, scope, &name, &value.to_string(), kind, None));
+}
+
+/// One timing per bucket, at the middle of the bucket, sampled so the server counts it `count` times
+fn push_timings(
+    lines: &mut Vec<String>,
+    config: &StatsdConfig,
+    scope: &[(&str, &str)],
+    histogram: &LatencyHistogram,
+) {
+    for (lower_ms, upper_ms, count) in histogram.bounds() {
+        if count == 0 {
+            continue;
+        }
+        let middle_ms = (lower_ms + upper_ms) / 2;
+        let rate = (count > 1).then(|| 1.0 / count as f64);
+        lines.push(line(
+            config,
+            scope,
+            "miss_latency",
+            &middle_ms.to_string(),
+            "ms",
+            rate,
+        ));
+    }
+}
+
+fn line(
+    config: &StatsdConfig,
+    scope: &[(&str, &str)],
+    name: &str,
+    value: &str,
+    kind: &str,
+    rate: Option<f64>,
+) -> String {
+    let mut line = String::new();
+    if !config.prefix.is_empty() {
+        write!(line, "{}.", config.prefix).ok();
+    }
+
+    if config.flavor == Flavor::Statsd {
+        for (_, scope_value) in scope {
+            write!(line, "{}.", sanitize_name(scope_value)).ok();
+        }
+    }
+    write!(line, "{}:{}|{}", name, value, kind).ok();
+
+    if let Some(rate) = rate {
+        write!(line, "|@{}", rate).ok();
+    }
+
+    if config.flavor == Flavor::DogStatsd {
+        let tags: Vec<String> = config
+            .tags
+            .iter()
+            .map(|(tag, tag_value)| (tag.as_str(), tag_value.as_str()))
+            .chain(scope.iter().copied())
+            .map(|(tag, tag_value)| format!("{}:{}", sanitize_tag(tag), sanitize_tag(tag_value)))
+            .collect();
+        if !tags.is_empty() {
+            write!(line, "|#{}", tags.join(",")).ok();
+        }
+    }
+
+    line
+}
+
+/// Replace characters other than alphanumerics, `_` and `-` of a part of a metric name by `_`
+fn sanitize_name(part: &str) -> String {
+    part.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Replace characters with a meaning in DogStatsD lines by `_`
+fn sanitize_tag(tag: &str) -> String {
+    tag.chars()
+        .map(|c| {
+            if matches!(c, ',' | '|' | '#' | ':' | '\n') {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,613 @@
+use crate::histogram::LatencyHistogram;
+use crate::origin::{OriginDataInSecond, OriginStats};
+use crate::pop::Pop;
+use crate::service::{ServiceDataInSecond, ServiceStats};
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::ops::Range;
+
+/// Share of responses of each status class, the shares should sum to 1
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusMix {
+    pub status_2xx: f64,
+    pub status_3xx: f64,
+    pub status_4xx: f64,
+    pub status_5xx: f64,
+}
+
+impl Default for StatusMix {
+    fn default() -> StatusMix {
+        StatusMix {
+            status_2xx: 0.90,
+            status_3xx: 0.05,
+            status_4xx: 0.04,
+            status_5xx: 0.01,
+        }
+    }
+}
+
+/// Kind of a scripted [`Incident`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum IncidentKind {
+    /// The POP disappears from `datacenter`, its traffic moving to the other POPs of its region,
+    /// or to all other POPs when it is alone in its region
+    PopOutage { pop: String },
+
+    /// Fraction `ratio` of the responses of the origin are 503, and so are the matching edge responses
+    Origin503Storm { origin: String, ratio: f64 },
+
+    /// Requests are multiplied by `multiplier` in `pops`, all POPs when empty,
+    /// the extra requests being blocked by WAF with a 403
+    AttackSpike { pops: Vec<String>, multiplier: f64 },
+}
+
+/// Incident scripted from `start` for `duration` seconds
+#[derive(Debug, Clone, PartialEq)]
+pub struct Incident {
+    /// Unix timestamp of the first second of the incident
+    pub start: u64,
+
+    /// Number of seconds of the incident
+    pub duration: u64,
+
+    pub kind: IncidentKind,
+}
+
+impl Incident {
+    pub fn is_active(&self, recorded: u64) -> bool {
+        recorded >= self.start && recorded - self.start < self.duration
+    }
+}
+
+/// Configuration of a [`Generator`]
+#[derive(Debug, Clone)]
+pub struct SynthConfig {
+    /// Seed of generated values, the same seed always generates the same data
+    pub seed: u64,
+
+    /// Names of POPs in the `datacenter` maps
+    pub pops: Vec<String>,
+
+    /// Names of origins, origin fetches are spread over them with decreasing weights
+    pub origins: Vec<String>,
+
+    /// Average number of requests per second of each POP
+    pub requests_per_second: f64,
+
+    /// Relative amplitude of the daily traffic curve, 0 for flat traffic
+    pub diurnal_amplitude: f64,
+
+    /// Local hour (0 to 24) of the traffic peak of each POP
+    pub peak_hour: f64,
+
+    /// Relative amplitude of random variation of traffic between seconds
+    pub jitter: f64,
+
+    /// Share of cacheable requests served from cache
+    pub hit_ratio: f64,
+
+    /// Share of requests passed to origin without caching
+    pub pass_ratio: f64,
+
+    pub status_mix: StatusMix,
+
+    /// Median latency of origin fetches in milliseconds
+    pub miss_latency_ms: f64,
+
+    pub incidents: Vec<Incident>,
+}
+
+impl Default for SynthConfig {
+    fn default() -> SynthConfig {
+        SynthConfig {
+            seed: 0,
+            pops: ["NRT", "HKG", "LHR", "IAD", "SYD"]
+                .iter()
+                .map(|pop| pop.to_string())
+                .collect(),
+            origins: vec!["origin_1".to_string(), "origin_2".to_string()],
+            requests_per_second: 100.0,
+            diurnal_amplitude: 0.3,
+            peak_hour: 20.0,
+            jitter: 0.1,
+            hit_ratio: 0.9,
+            pass_ratio: 0.02,
+            status_mix: StatusMix::default(),
+            miss_latency_ms: 80.0,
+            incidents: Vec::new(),
+        }
+    }
+}
+
+impl SynthConfig {
+    /// Use `count` origins named `origin_1`, `origin_2`...
+    pub fn with_origin_count(mut self, count: usize) -> SynthConfig {
+        self.origins = (1..=count).map(|i| format!("origin_{}", i)).collect();
+        self
+    }
+}
+
+/// Generator of realistic, internally consistent real time data without Fastly
+///
+/// Data of each second only depends on the configuration and the `recorded` timestamp,
+/// so seconds can be generated in any order and service and origin data of a second match.
+///
+/// Generated data guarantees, for the aggregate and each POP:
+/// - `aggregated` is the sum of the `datacenter` entries
+/// - `requests` = `hits` + `miss` + `pass` + `synth`, and `origin_fetches` = `miss` + `pass`
+/// - status classes sum to `requests`, and each class is at least the sum of its detailed statuses
+/// - responses of origins of a POP sum to `origin_fetches` of the POP
+pub struct Generator {
+    config: SynthConfig,
+}
+
+impl Generator {
+    pub fn new(config: SynthConfig) -> Generator {
+        Generator { config }
+    }
+
+    pub fn config(&self) -> &SynthConfig {
+        &self.config
+    }
+
+    /// Generate service data of the second `recorded`
+    pub fn service_second(&self, recorded: u64) -> ServiceDataInSecond {
+        let mut data = ServiceDataInSecond {
+            recorded,
+            ..Default::default()
+        };
+
+        for (pop, requests) in self.pop_requests(recorded) {
+            let (stats, _) = self.pop_second(recorded, &pop, requests);
+            data.aggregated.merge(&stats);
+            data.datacenter.insert(pop, stats);
+        }
+
+        data
+    }
+
+    /// Generate origin data of the second `recorded`
+    pub fn origin_second(&self, recorded: u64) -> OriginDataInSecond {
+        let mut data = OriginDataInSecond {
+            recorded,
+            ..Default::default()
+        };
+
+        for (pop, requests) in self.pop_requests(recorded) {
+            let (_, origins) = self.pop_second(recorded, &pop, requests);
+            for (origin, stats) in &origins {
+                data.aggregated
+                    .entry(origin.clone())
+                    .or_default()
+                    .merge(stats);
+            }
+            data.datacenter.insert(pop, origins);
+        }
+
+        data
+    }
+
+    /// Generate service data of each second of `seconds`
+    pub fn service_seconds(
+        &self,
+        seconds: Range<u64>,
+    ) -> impl Iterator<Item = ServiceDataInSecond> + '_ {
+        seconds.map(move |recorded| self.service_second(recorded))
+    }
+
+    /// Generate origin data of each second of `seconds`
+    pub fn origin_seconds(
+        &self,
+        seconds: Range<u64>,
+    ) -> impl Iterator<Item = OriginDataInSecond> + '_ {
+        seconds.map(move |recorded| self.origin_second(recorded))
+    }
+
+    fn active_incidents(&self, recorded: u64) -> impl Iterator<Item = &IncidentKind> {
+        self.config
+            .incidents
+            .iter()
+            .filter(move |incident| incident.is_active(recorded))
+            .map(|incident| &incident.kind)
+    }
+
+    /// Expected requests of each POP up, after moving traffic of POPs in outage
+    fn pop_requests(&self, recorded: u64) -> Vec<(String, f64)> {
+        let down: Vec<&String> = self
+            .active_incidents(recorded)
+            .filter_map(|kind| match kind {
+                IncidentKind::PopOutage { pop } => Some(pop),
+                _ => None,
+            })
+            .collect();
+
+        let mut requests: Vec<(String, f64)> = self
+            .config
+            .pops
+            .iter()
+            .filter(|pop| !down.contains(pop))
+            .map(|pop| (pop.clone(), self.diurnal_requests(recorded, pop)))
+            .collect();
+
+        for pop_down in self.config.pops.iter().filter(|pop| down.contains(pop)) {
+            let region = Pop::new(pop_down).region();
+            let shifted = self.diurnal_requests(recorded, pop_down);
+            let same_region = requests
+                .iter()
+                .filter(|(pop, _)| Pop::new(pop).region() == region)
+                .count();
+
+            let receivers = if same_region > 0 {
+                same_region
+            } else {
+                requests.len()
+            };
+            for (pop, pop_requests) in requests.iter_mut() {
+                if same_region == 0 || Pop::new(pop).region() == region {
+                    *pop_requests += shifted / receivers as f64;
+                }
+            }
+        }
+
+        requests
+    }
+
+    fn diurnal_requests(&self, recorded: u64, pop: &str) -> f64 {
+        let config = &self.config;
+        let longitude = Pop::new(pop)
+            .coordinates()
+            .map(|(_, longitude)| longitude)
+            .unwrap_or(0.0);
+        let local_hour = (recorded % 86400) as f64 / 3600.0 + longitude / 15.0;
+        let phase = 2.0 * PI * (local_hour - config.peak_hour) / 24.0;
+
+        config.requests_per_second * (1.0 + config.diurnal_amplitude * phase.cos()).max(0.0)
+    }
+
+    /// Generate service stats and origin stats of one POP
+    fn pop_second(
+        &self,
+        recorded: u64,
+        pop: &str,
+        expected_requests: f64,
+    ) -> (ServiceStats, HashMap<String, OriginStats>) {
+        let config = &self.config;
+        let mut rng = Rng::new(config.seed, recorded, pop);
+
+        let mut multiplier = 1.0;
+        let mut storms: HashMap<&str, f64> = HashMap::new();
+        for kind in self.active_incidents(recorded) {
+            match kind {
+                IncidentKind::AttackSpike {
+                    pops,
+                    multiplier: attack,
+                } if pops.is_empty() || pops.iter().any(|attacked| attacked == pop) => {
+                    multiplier *= attack.max(1.0);
+                }
+                IncidentKind::Origin503Storm { origin, ratio } => {
+                    storms.insert(origin, ratio.clamp(0.0, 1.0));
+                }
+                _ => {}
+            }
+        }
+
+        let jitter = 1.0 + config.jitter * (2.0 * rng.unit() - 1.0);
+        let legit = (expected_requests * jitter).round().max(0.0) as u64;
+        let blocked = (expected_requests * (multiplier - 1.0)).round() as u64;
+        let pass = share(legit, config.pass_ratio);
+        let hits = share(legit - pass, config.hit_ratio * (0.98 + 0.04 * rng.unit()));
+        let miss = legit - pass - hits;
+        let fetches = miss + pass;
+
+        let mut origins = HashMap::new();
+        let mut origin_statuses = Statuses::default();
+        let weights: Vec<f64> = (0..config.origins.len())
+            .map(|i| 1.0 / (i + 1) as f64)
+            .collect();
+        let total_weight: f64 = weights.iter().sum();
+        let mut remaining = fetches;
+        for (i, origin) in config.origins.iter().enumerate() {
+            let responses = if i + 1 == config.origins.len() {
+                remaining
+            } else {
+                share(fetches, weights[i] / total_weight).min(remaining)
+            };
+            remaining -= responses;
+
+            let storm_503 = storms
+                .get(origin.as_str())
+                .map(|ratio| share(responses, *ratio))
+                .unwrap_or(0);
+            let mut statuses = Statuses::mixed(responses - storm_503, &config.status_mix);
+            statuses.status_503 += storm_503;
+            statuses.status_5xx += storm_503;
+            origin_statuses.add(&statuses);
+
+            let mut stats = OriginStats {
+                responses,
+                resp_header_bytes: responses * 300,
+                resp_body_bytes: (responses - storm_503) * 20_000 + storm_503 * 200,
+                ..Default::default()
+            };
+            statuses.apply_origin(&mut stats);
+            origins.insert(origin.clone(), stats);
+        }
+        if config.origins.is_empty() {
+            origin_statuses = Statuses::mixed(fetches, &config.status_mix);
+        }
+
+        let mut edge_statuses = Statuses::mixed(hits, &config.status_mix);
+        edge_statuses.add(&origin_statuses);
+        edge_statuses.status_403 += blocked;
+        edge_statuses.status_4xx += blocked;
+
+        let requests = legit + blocked;
+        let origin_body_bytes: u64 = origins.values().map(|stats| stats.resp_body_bytes).sum();
+        let hit_body_bytes = hits * 20_000;
+        let miss_body_bytes = miss * 20_000;
+        let pass_body_bytes = pass * 10_000;
+        let synth_body_bytes = blocked * 100;
+
+        let mut stats = ServiceStats {
+            requests,
+            edge_requests: requests,
+            edge_hit_requests: hits,
+            edge_miss_requests: miss,
+            hits,
+            miss,
+            pass,
+            synth: blocked,
+            origin_fetches: fetches,
+            origin_fetch_header_bytes: fetches * 500,
+            origin_fetch_resp_header_bytes: fetches * 300,
+            origin_fetch_resp_body_bytes: origin_body_bytes,
+            bereq_header_bytes: fetches * 500,
+            req_header_bytes: requests * 500,
+            resp_header_bytes: requests * 400,
+            resp_body_bytes: hit_body_bytes + miss_body_bytes + pass_body_bytes + synth_body_bytes,
+            edge_resp_header_bytes: requests * 400,
+            edge_resp_body_bytes: hit_body_bytes
+                + miss_body_bytes
+                + pass_body_bytes
+                + synth_body_bytes,
+            hit_resp_body_bytes: hit_body_bytes,
+            miss_resp_body_bytes: miss_body_bytes,
+            pass_resp_body_bytes: pass_body_bytes,
+            hits_time: hits as f64 * 0.0004,
+            pass_time: pass as f64 * config.miss_latency_ms / 1000.0,
+            tls: share(requests, 0.98),
+            http2: share(requests, 0.6),
+            ipv6: share(requests, 0.2),
+            waf_blocked: blocked,
+            attack_req_header_bytes: blocked * 500,
+            attack_blocked_req_header_bytes: blocked * 500,
+            attack_resp_synth_bytes: synth_body_bytes,
+            ..Default::default()
+        };
+        edge_statuses.apply_service(&mut stats);
+
+        let storm_latency = if storms.is_empty() { 1.0 } else { 3.0 };
+        stats.miss_time = self.add_miss_latency(
+            &mut stats,
+            miss,
+            config.miss_latency_ms * storm_latency,
+            &mut rng,
+        );
+
+        (stats, origins)
+    }
+
+    /// Spread `miss` origin fetches around `median_ms` in the miss histogram, returns the total time in seconds
+    fn add_miss_latency(
+        &self,
+        stats: &mut ServiceStats,
+        miss: u64,
+        median_ms: f64,
+        rng: &mut Rng,
+    ) -> f64 {
+        const SPREAD: [(f64, f64); 6] = [
+            (0.5, 0.15),
+            (0.75, 0.25),
+            (1.5, 0.15),
+            (2.0, 0.1),
+            (4.0, 0.05),
+            (1.0, 0.3),
+        ];
+
+        let median_ms = median_ms * (0.9 + 0.2 * rng.unit());
+        let mut remaining = miss;
+        let mut total_ms = 0.0;
+        for (i, (factor, weight)) in SPREAD.iter().enumerate() {
+            let count = if i + 1 == SPREAD.len() {
+                remaining
+            } else {
+                share(miss, *weight).min(remaining)
+            };
+            remaining -= count;
+
+            let latency_ms = (median_ms * factor).max(1.0);
+            let upper_ms = LatencyHistogram::upper_bound(latency_ms);
+            stats.miss_histogram.add(upper_ms, count);
+            total_ms += latency_ms * count as f64;
+        }
+
+        total_ms / 1000.0
+    }
+}
+
+/// Round `total` * `ratio`, never more than `total`
+fn share(total: u64, ratio: f64) -> u64 {
+    ((total as f64 * ratio.clamp(0.0, 1.0)).round() as u64).min(total)
+}
+
+/// Split `total` by `ratios`, each share clamped to what the ones before it left
+fn split<const N: usize>(total: u64, ratios: [f64; N]) -> [u64; N] {
+    let mut remaining = total;
+    ratios.map(|ratio| {
+        let count = share(total, ratio).min(remaining);
+        remaining -= count;
+        count
+    })
+}
+
+/// Counts of response statuses
+#[derive(Debug, Default)]
+struct Statuses {
+    status_200: u64,
+    status_204: u64,
+    status_206: u64,
+    status_2xx: u64,
+    status_301: u64,
+    status_302: u64,
+    status_304: u64,
+    status_3xx: u64,
+    status_400: u64,
+    status_401: u64,
+    status_403: u64,
+    status_404: u64,
+    status_416: u64,
+    status_429: u64,
+    status_4xx: u64,
+    status_500: u64,
+    status_502: u64,
+    status_503: u64,
+    status_504: u64,
+    status_5xx: u64,
+}
+
+impl Statuses {
+    /// Split `responses` into statuses according to `mix`, statuses of a class summing to at most the class
+    fn mixed(responses: u64, mix: &StatusMix) -> Statuses {
+        let status_3xx = share(responses, mix.status_3xx);
+        let status_4xx = share(responses - status_3xx, mix.status_4xx);
+        let status_5xx = share(responses - status_3xx - status_4xx, mix.status_5xx);
+        let status_2xx = responses - status_3xx - status_4xx - status_5xx;
+
+        let [status_200, status_204, status_206] = split(status_2xx, [0.9, 0.02, 0.05]);
+        let [status_301, status_302, status_304] = split(status_3xx, [0.2, 0.15, 0.6]);
+        let [status_400, status_401, status_403, status_404, status_416, status_429] =
+            split(status_4xx, [0.1, 0.05, 0.15, 0.6, 0.01, 0.05]);
+        let [status_500, status_502, status_503, status_504] =
+            split(status_5xx, [0.25, 0.2, 0.4, 0.15]);
+
+        Statuses {
+            status_200,
+            status_204,
+            status_206,
+            status_2xx,
+            status_301,
+            status_302,
+            status_304,
+            status_3xx,
+            status_400,
+            status_401,
+            status_403,
+            status_404,
+            status_416,
+            status_429,
+            status_4xx,
+            status_500,
+            status_502,
+            status_503,
+            status_504,
+            status_5xx,
+        }
+    }
+
+    fn add(&mut self, other: &Statuses) {
+        self.status_200 += other.status_200;
+        self.status_204 += other.status_204;
+        self.status_206 += other.status_206;
+        self.status_2xx += other.status_2xx;
+        self.status_301 += other.status_301;
+        self.status_302 += other.status_302;
+        self.status_304 += other.status_304;
+        self.status_3xx += other.status_3xx;
+        self.status_400 += other.status_400;
+        self.status_401 += other.status_401;
+        self.status_403 += other.status_403;
+        self.status_404 += other.status_404;
+        self.status_416 += other.status_416;
+        self.status_429 += other.status_429;
+        self.status_4xx += other.status_4xx;
+        self.status_500 += other.status_500;
+        self.status_502 += other.status_502;
+        self.status_503 += other.status_503;
+        self.status_504 += other.status_504;
+        self.status_5xx += other.status_5xx;
+    }
+
+    fn apply_service(&self, stats: &mut ServiceStats) {
+        stats.status_200 = self.status_200;
+        stats.status_204 = self.status_204;
+        stats.status_206 = self.status_206;
+        stats.status_2xx = self.status_2xx;
+        stats.status_301 = self.status_301;
+        stats.status_302 = self.status_302;
+        stats.status_304 = self.status_304;
+        stats.status_3xx = self.status_3xx;
+        stats.status_400 = self.status_400;
+        stats.status_401 = self.status_401;
+        stats.status_403 = self.status_403;
+        stats.status_404 = self.status_404;
+        stats.status_416 = self.status_416;
+        stats.status_429 = self.status_429;
+        stats.status_4xx = self.status_4xx;
+        stats.status_500 = self.status_500;
+        stats.status_502 = self.status_502;
+        stats.status_503 = self.status_503;
+        stats.status_504 = self.status_504;
+        stats.status_5xx = self.status_5xx;
+    }
+
+    fn apply_origin(&self, stats: &mut OriginStats) {
+        stats.status_200 = self.status_200;
+        stats.status_204 = self.status_204;
+        stats.status_206 = self.status_206;
+        stats.status_2xx = self.status_2xx;
+        stats.status_301 = self.status_301;
+        stats.status_302 = self.status_302;
+        stats.status_304 = self.status_304;
+        stats.status_3xx = self.status_3xx;
+        stats.status_400 = self.status_400;
+        stats.status_401 = self.status_401;
+        stats.status_403 = self.status_403;
+        stats.status_404 = self.status_404;
+        stats.status_416 = self.status_416;
+        stats.status_429 = self.status_429;
+        stats.status_4xx = self.status_4xx;
+        stats.status_500 = self.status_500;
+        stats.status_502 = self.status_502;
+        stats.status_503 = self.status_503;
+        stats.status_504 = self.status_504;
+        stats.status_5xx = self.status_5xx;
+    }
+}
+
+/// SplitMix64 generator seeded by the seed, the second and the POP
+struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    fn new(seed: u64, recorded: u64, pop: &str) -> Rng {
+        let state = pop.bytes().fold(
+            seed ^ recorded.wrapping_mul(0x9e37_79b9_7f4a_7c15),
+            |state, byte| state.wrapping_mul(31).wrapping_add(byte as u64),
+        );
+        Rng { state }
+    }
+
+    fn next(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform value in [0, 1)
+    fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
@@ -0,0 +1,376 @@
+use crate::metric::{matches_pattern, Metrics};
+use crate::origin::{OriginDataInSecond, OriginStats};
+use crate::service::{ServiceDataInSecond, ServiceStats};
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::AddAssign;
+
+/// Part of the data of a second: aggregated across POPs or of one POP, of a service or of one origin
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Scope {
+    /// POP name, `None` when aggregated across POPs
+    pub pop: Option<String>,
+
+    /// Origin name, `None` for service data
+    pub origin: Option<String>,
+}
+
+impl Scope {
+    /// Aggregated across POPs
+    pub fn aggregated() -> Scope {
+        Scope::default()
+    }
+
+    pub fn pop(pop_name: &str) -> Scope {
+        Scope {
+            pop: Some(pop_name.to_string()),
+            origin: None,
+        }
+    }
+
+    /// Origin aggregated across POPs
+    pub fn origin(origin_name: &str) -> Scope {
+        Scope {
+            pop: None,
+            origin: Some(origin_name.to_string()),
+        }
+    }
+
+    /// Narrow the scope to the POP `pop_name`
+    pub fn in_pop(mut self, pop_name: &str) -> Scope {
+        self.pop = Some(pop_name.to_string());
+        self
+    }
+
+    pub fn is_aggregated(&self) -> bool {
+        self.pop.is_none()
+    }
+}
+
+impl fmt::Display for Scope {
+    /// `aggregated`, `pop NRT`, `origin s3` or `origin s3 in pop NRT`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.origin, &self.pop) {
+            (None, None) => f.write_str("aggregated"),
+            (None, Some(pop_name)) => write!(f, "pop {}", pop_name),
+            (Some(origin_name), None) => write!(f, "origin {}", origin_name),
+            (Some(origin_name), Some(pop_name)) => {
+                write!(f, "origin {} in pop {}", origin_name, pop_name)
+            }
+        }
+    }
+}
+
+/// Services, origins and POPs an alert rule, an anomaly watch or an objective applies to
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScopeFilter {
+    /// Services by patterns where `*` matches anything, all of them when empty
+    pub services: Vec<String>,
+
+    /// Origins by patterns for origin data, all of them when empty
+    pub origins: Vec<String>,
+
+    /// Each POP instead of the aggregate across POPs
+    pub by_pop: bool,
+}
+
+impl ScopeFilter {
+    /// Whether `scope` of `service` passes the filter
+    pub fn applies(&self, service: &str, scope: &Scope) -> bool {
+        scope.is_aggregated() != self.by_pop
+            && matching(&self.services, service)
+            && scope
+                .origin
+                .as_ref()
+                .is_none_or(|origin| matching(&self.origins, origin))
+    }
+}
+
+fn matching(patterns: &[String], name: &str) -> bool {
+    patterns.is_empty()
+        || patterns
+            .iter()
+            .any(|pattern| matches_pattern(pattern, name))
+}
+
+/// A second of data split into the stats of each of its scopes
+pub trait Scoped {
+    type Stats: Metrics + Default + Clone + for<'a> AddAssign<&'a Self::Stats>;
+
+    fn recorded(&self) -> u64;
+
+    /// Stats of every scope present in the second
+    fn scopes(&self) -> Vec<(Scope, &Self::Stats)>;
+}
+
+impl Scoped for ServiceDataInSecond {
+    type Stats = ServiceStats;
+
+    fn recorded(&self) -> u64 {
+        self.recorded
+    }
+
+    /// Aggregated, and each POP
+    fn scopes(&self) -> Vec<(Scope, &ServiceStats)> {
+        let mut scopes = vec![(Scope::aggregated(), &self.aggregated)];
+        for (pop_name, stats) in &self.datacenter {
+            scopes.push((Scope::pop(pop_name), stats));
+        }
+        scopes
+    }
+}
+
+impl Scoped for OriginDataInSecond {
+    type Stats = OriginStats;
+
+    fn recorded(&self) -> u64 {
+        self.recorded
+    }
+
+    /// Each origin, aggregated and in each POP
+    fn scopes(&self) -> Vec<(Scope, &OriginStats)> {
+        let mut scopes = Vec::new();
+        for (origin_name, stats) in &self.aggregated {
+            scopes.push((Scope::origin(origin_name), stats));
+        }
+        for (pop_name, origins) in &self.datacenter {
+            for (origin_name, stats) in origins {
+                scopes.push((Scope::origin(origin_name).in_pop(pop_name), stats));
+            }
+        }
+        scopes
+    }
+}
+
+/// Bounded store of the latest seconds of several services, by scope
+///
+/// Each series is a ring buffer of `capacity` seconds indexed by `recorded`, so inserting a second
+/// costs O(1) per scope of the service and evicts the second `capacity` seconds older. Series of scopes
+/// with no data in the last `capacity` seconds are dropped.
+///
+/// Windows are the last seconds before the latest second received for the service, inclusive.
+/// A scope missing from a received second, e.g. a POP without traffic, counts as zero in that second.
+///
+/// ```
+/// use fastly_rt::service::ServiceDataInSecond;
+/// use fastly_rt::timeseries::{Scope, TimeSeriesStore};
+///
+/// let mut store = TimeSeriesStore::<ServiceDataInSecond>::new(300);
+/// for recorded in 1..=120 {
+///     let mut data = ServiceDataInSecond { recorded, ..Default::default() };
+///     data.aggregated.requests = 2;
+///     store.insert("www", &data);
+/// }
+///
+/// assert_eq!(store.metric_sum("www", &Scope::aggregated(), "requests", 60), Some(120.0));
+/// assert_eq!(store.rate("www", &Scope::aggregated(), "requests", 300), Some(2.0));
+/// ```
+pub struct TimeSeriesStore<D: Scoped> {
+    capacity: usize,
+    services: HashMap<String, Series<D::Stats>>,
+}
+
+/// Series of one service
+struct Series<S> {
+    latest: u64,
+    received: Ring<()>,
+    scopes: HashMap<Scope, Ring<S>>,
+}
+
+impl<D: Scoped> TimeSeriesStore<D> {
+    /// Store the last `capacity` seconds of each service
+    pub fn new(capacity: usize) -> TimeSeriesStore<D> {
+        TimeSeriesStore {
+            capacity: capacity.max(1),
+            services: HashMap::new(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Insert a second of data of `service`, ignored when older than the capacity
+    pub fn insert(&mut self, service: &str, data: &D) {
+        let capacity = self.capacity;
+        let recorded = data.recorded();
+        let series = self
+            .services
+            .entry(service.to_string())
+            .or_insert_with(|| Series {
+                latest: recorded,
+                received: Ring::new(capacity),
+                scopes: HashMap::new(),
+            });
+        if recorded + (capacity as u64) <= series.latest {
+            return;
+        }
+
+        series.received.insert(recorded, ());
+        for (scope, stats) in data.scopes() {
+            series
+                .scopes
+                .entry(scope)
+                .or_insert_with(|| Ring::new(capacity))
+                .insert(recorded, stats.clone());
+        }
+
+        if recorded > series.latest {
+            series.latest = recorded;
+            let oldest = recorded.saturating_sub(capacity as u64 - 1);
+            series.scopes.retain(|_, ring| ring.latest >= oldest);
+        }
+    }
+
+    /// Insert seconds of data of `service`
+    pub fn extend<'a, I: IntoIterator<Item = &'a D>>(&mut self, service: &str, seconds: I)
+    where
+        D: 'a,
+    {
+        for data in seconds {
+            self.insert(service, data);
+        }
+    }
+
+    /// Services with data
+    pub fn services(&self) -> impl Iterator<Item = &str> {
+        self.services.keys().map(String::as_str)
+    }
+
+    /// Latest second received for `service`
+    pub fn latest(&self, service: &str) -> Option<u64> {
+        self.services.get(service).map(|series| series.latest)
+    }
+
+    /// Scopes of `service` with data, sorted
+    pub fn scopes(&self, service: &str) -> Vec<&Scope> {
+        let mut scopes: Vec<&Scope> = self
+            .services
+            .get(service)
+            .map(|series| series.scopes.keys().collect())
+            .unwrap_or_default();
+        scopes.sort();
+        scopes
+    }
+
+    /// Seconds received for `service` in the last `seconds` seconds, in order
+    pub fn received(&self, service: &str, seconds: u64) -> Vec<u64> {
+        match self.services.get(service) {
+            Some(series) => self.window(series, seconds).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Stats of `service` in `scope` at `recorded`, `None` when the scope had no data then
+    pub fn get(&self, service: &str, scope: &Scope, recorded: u64) -> Option<&D::Stats> {
+        self.services.get(service)?.scopes.get(scope)?.get(recorded)
+    }
+
+    /// Stats of `service` in `scope` summed over the last `seconds` seconds,
+    /// `None` when the service has no data
+    pub fn sum(&self, service: &str, scope: &Scope, seconds: u64) -> Option<D::Stats> {
+        let series = self.services.get(service)?;
+        let mut sum = D::Stats::default();
+        if let Some(ring) = series.scopes.get(scope) {
+            for recorded in self.window(series, seconds) {
+                if let Some(stats) = ring.get(recorded) {
+                    sum += stats;
+                }
+            }
+        }
+        Some(sum)
+    }
+
+    /// Values of `metric` of `service` in `scope` for each second received in the last `seconds` seconds,
+    /// `None` when the service has no data or there is no such metric
+    pub fn values(
+        &self,
+        service: &str,
+        scope: &Scope,
+        metric: &str,
+        seconds: u64,
+    ) -> Option<Vec<(u64, f64)>> {
+        let series = self.services.get(service)?;
+        let metric = D::Stats::metric_info(metric)?;
+        let ring = series.scopes.get(scope);
+
+        Some(
+            self.window(series, seconds)
+                .map(|recorded| {
+                    let stats = ring.and_then(|ring| ring.get(recorded));
+                    (recorded, stats.map_or(0.0, |stats| (metric.value)(stats)))
+                })
+                .collect(),
+        )
+    }
+
+    /// Sum of `metric` of `service` in `scope` over the last `seconds` seconds
+    pub fn metric_sum(
+        &self,
+        service: &str,
+        scope: &Scope,
+        metric: &str,
+        seconds: u64,
+    ) -> Option<f64> {
+        self.values(service, scope, metric, seconds)
+            .map(|values| values.iter().map(|(_, value)| value).sum())
+    }
+
+    /// Average of `metric` per second received over the last `seconds` seconds,
+    /// `None` too when no second was received
+    pub fn rate(&self, service: &str, scope: &Scope, metric: &str, seconds: u64) -> Option<f64> {
+        let values = self.values(service, scope, metric, seconds)?;
+        if values.is_empty() {
+            return None;
+        }
+        let sum: f64 = values.iter().map(|(_, value)| value).sum();
+        Some(sum / values.len() as f64)
+    }
+
+    /// Seconds received in the window of `seconds` seconds ending at the latest one
+    fn window<'a>(
+        &self,
+        series: &'a Series<D::Stats>,
+        seconds: u64,
+    ) -> impl Iterator<Item = u64> + 'a {
+        let length = seconds.min(self.capacity as u64);
+        let first = (series.latest + 1).saturating_sub(length);
+        (first..=series.latest).filter(move |recorded| series.received.get(*recorded).is_some())
+    }
+}
+
+/// Fixed number of slots, the slot of a second being `recorded % capacity`
+struct Ring<T> {
+    latest: u64,
+    slots: Vec<Option<(u64, T)>>,
+}
+
+impl<T> Ring<T> {
+    fn new(capacity: usize) -> Ring<T> {
+        Ring {
+            latest: 0,
+            slots: (0..capacity).map(|_| None).collect(),
+        }
+    }
+
+    fn index(&self, recorded: u64) -> usize {
+        (recorded % self.slots.len() as u64) as usize
+    }
+
+    /// Insert the value of `recorded`, unless its slot holds a later second
+    fn insert(&mut self, recorded: u64, value: T) {
+        let index = self.index(recorded);
+        if matches!(&self.slots[index], Some((held, _)) if *held > recorded) {
+            return;
+        }
+        self.slots[index] = Some((recorded, value));
+        self.latest = self.latest.max(recorded);
+    }
+
+    fn get(&self, recorded: u64) -> Option<&T> {
+        match &self.slots[self.index(recorded)] {
+            Some((held, value)) if *held == recorded => Some(value),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,27 @@
+//! Calendar of Unix timestamps in UTC, for naming files by hour
+
+/// `(year, month, day, hour)` in UTC of the Unix timestamp `timestamp`
+pub(crate) fn civil(timestamp: u64) -> (i64, u32, u32, u32) {
+    let days = (timestamp / 86_400) as i64;
+    let hour = (timestamp % 86_400 / 3600) as u32;
+
+    // Howard Hinnant's days_from_civil, inverted
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    (year, month, day, hour)
+}
+
+/// Hour of `timestamp` in UTC, formatted as `YYYY-MM-DDTHH`
+pub(crate) fn hour_label(timestamp: u64) -> String {
+    let (year, month, day, hour) = civil(timestamp);
+    format!("{:04}-{:02}-{:02}T{:02}", year, month, day, hour)
+}
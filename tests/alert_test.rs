@@ -0,0 +1,246 @@
+use fastly_rt::alert::{
+    parse_duration, AlertConfig, AlertEngine, AlertEvent, AlertState, Comparison, Condition,
+    Expression, Rule,
+};
+use fastly_rt::origin::{OriginDataInSecond, OriginStats};
+use fastly_rt::service::{ServiceDataInSecond, ServiceStats};
+use fastly_rt::timeseries::Scope;
+use std::sync::{Arc, Mutex};
+
+fn service_second(recorded: u64, nrt_5xx: u64, lhr_5xx: u64) -> ServiceDataInSecond {
+    let mut data = ServiceDataInSecond {
+        recorded,
+        ..Default::default()
+    };
+    for (pop_name, status_5xx) in [("NRT", nrt_5xx), ("LHR", lhr_5xx)] {
+        let stats = data.datacenter.entry(pop_name.to_string()).or_default();
+        stats.requests = 100;
+        stats.status_5xx = status_5xx;
+    }
+    data.aggregated.requests = 200;
+    data.aggregated.status_5xx = nrt_5xx + lhr_5xx;
+    data
+}
+
+fn states(events: &[AlertEvent]) -> Vec<(AlertState, u64)> {
+    events
+        .iter()
+        .map(|event| (event.state, event.recorded))
+        .collect()
+}
+
+#[test]
+fn parse_conditions() {
+    let condition = Condition::parse("status_5xx / requests > 0.02 for 30s").unwrap();
+    assert_eq!(condition.comparison, Comparison::Greater);
+    assert_eq!(condition.threshold, 0.02);
+    assert_eq!(condition.for_secs, 30);
+    assert_eq!(condition.expression.to_string(), "status_5xx / requests");
+    assert_eq!(
+        condition.to_string(),
+        "status_5xx / requests > 0.02 for 30s"
+    );
+
+    let stats = ServiceStats {
+        requests: 200,
+        status_5xx: 10,
+        status_4xx: 30,
+        ..Default::default()
+    };
+    let expression = Expression::parse("(status_4xx + status_5xx) * 100 / requests").unwrap();
+    assert_eq!(expression.eval(&stats), 20.0);
+    assert_eq!(
+        Expression::parse("-requests + 1").unwrap().eval(&stats),
+        -199.0
+    );
+    assert!(Expression::parse("status_5xx / hits")
+        .unwrap()
+        .eval(&ServiceStats::default())
+        .is_nan());
+
+    assert!(Expression::parse("nonexistent")
+        .unwrap()
+        .validate::<ServiceStats>()
+        .is_err());
+    assert!(Condition::parse("requests >").is_err());
+    assert!(Condition::parse("(requests > 1").is_err());
+    assert!(Condition::parse("requests > 1 for 5d").is_err());
+    assert!(Rule::new("typo", "request > 1").is_ok());
+    assert!(AlertEngine::<ServiceDataInSecond>::new(vec![
+        Rule::new("typo", "request > 1").unwrap()
+    ])
+    .is_err());
+
+    assert_eq!(parse_duration("90").unwrap(), 90);
+    assert_eq!(parse_duration("5m").unwrap(), 300);
+    assert_eq!(parse_duration("1h").unwrap(), 3600);
+}
+
+#[test]
+fn pending_firing_and_resolved_with_hysteresis() {
+    let rule = Rule::new("high_5xx", "status_5xx / requests > 0.02 for 3s")
+        .unwrap()
+        .clear(0.01)
+        .resolve_after(2);
+    let mut engine = AlertEngine::new(vec![rule]).unwrap();
+
+    // 5%, 1% breaking the pending alert, then 5% for 3 seconds
+    let mut events = Vec::new();
+    for (recorded, status_5xx) in [(1, 10), (2, 2), (3, 10), (4, 10), (5, 10)] {
+        events.extend(engine.evaluate(
+            "www",
+            &service_second(recorded, status_5xx / 2, status_5xx / 2),
+        ));
+    }
+    assert_eq!(
+        states(&events),
+        vec![
+            (AlertState::Pending, 1),
+            (AlertState::Pending, 3),
+            (AlertState::Firing, 5)
+        ]
+    );
+    assert_eq!(events[2].since, 3);
+    assert_eq!(events[2].scope, Scope::aggregated());
+    assert_eq!(events[2].value, 0.05);
+    assert_eq!(
+        engine.firing(),
+        vec![("high_5xx", "www", &Scope::aggregated())]
+    );
+
+    // 1.5% is under the threshold but above the clear threshold, the alert keeps firing,
+    // then it has to stay under 1% for 2 seconds
+    let mut events = Vec::new();
+    for (recorded, status_5xx) in [(6, 3), (7, 2), (8, 4), (9, 2), (10, 0)] {
+        events.extend(engine.evaluate("www", &service_second(recorded, status_5xx, 0)));
+    }
+    assert_eq!(states(&events), vec![(AlertState::Resolved, 10)]);
+    assert_eq!(events[0].since, 9);
+    assert!(engine.firing().is_empty());
+}
+
+#[test]
+fn pop_and_origin_scopes() {
+    let rule = Rule::new("pop_5xx", "status_5xx / requests >= 0.1")
+        .unwrap()
+        .by_pop(true)
+        .service("www*");
+    let mut engine = AlertEngine::new(vec![rule]).unwrap();
+
+    let events = engine.evaluate("www", &service_second(1, 0, 20));
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].state, AlertState::Firing);
+    assert_eq!(events[0].scope, Scope::pop("LHR"));
+    assert!(engine.evaluate("api", &service_second(1, 0, 20)).is_empty());
+
+    // LHR without traffic counts as zero, its ratio never holds
+    let mut data = service_second(2, 0, 0);
+    data.datacenter.remove("LHR");
+    let events = engine.evaluate("www", &data);
+    assert_eq!(states(&events), vec![(AlertState::Resolved, 2)]);
+
+    // Origins over 10 seconds, in every POP
+    let rule = Rule::new("origin_503", "status_503 > 5")
+        .unwrap()
+        .origin("s3")
+        .over(10);
+    let mut engine = AlertEngine::<OriginDataInSecond>::new(vec![rule]).unwrap();
+    let mut events = Vec::new();
+    for recorded in 1..=4 {
+        let mut data = OriginDataInSecond {
+            recorded,
+            ..Default::default()
+        };
+        for origin_name in ["s3", "gcs"] {
+            let stats = OriginStats {
+                responses: 10,
+                status_503: 2,
+                ..Default::default()
+            };
+            data.datacenter
+                .entry("NRT".to_string())
+                .or_default()
+                .insert(origin_name.to_string(), stats.clone());
+            data.aggregated.insert(origin_name.to_string(), stats);
+        }
+        events.extend(engine.evaluate("www", &data));
+    }
+    assert_eq!(states(&events), vec![(AlertState::Firing, 3)]);
+    assert_eq!(events[0].scope, Scope::origin("s3"));
+    assert_eq!(events[0].value, 6.0);
+}
+
+#[test]
+fn rules_from_toml() {
+    let config = AlertConfig::from_toml(
+        r#"
+[kpis]
+error_ratio = "status_5xx / requests"
+error_percent = "error_ratio * 100"
+
+[[rule]]
+name = "high_5xx"
+condition = "error_percent > 2 for 1m"
+services = ["www"]
+by_pop = true
+over = "10s"
+clear = 1
+resolve_after = "30s"
+"#,
+    )
+    .unwrap();
+    let rules = config.rules().unwrap();
+    assert_eq!(rules.len(), 1);
+
+    let rule = &rules[0];
+    assert_eq!(rule.name, "high_5xx");
+    assert_eq!(rule.condition.for_secs, 60);
+    assert_eq!(rule.filter.services, vec!["www"]);
+    assert!(rule.filter.by_pop);
+    assert_eq!(rule.over_secs, 10);
+    assert_eq!(rule.clear, Some(1.0));
+    assert_eq!(rule.resolve_secs, 30);
+    assert_eq!(
+        rule.condition.expression.eval(&ServiceStats {
+            requests: 40,
+            status_5xx: 10,
+            ..Default::default()
+        }),
+        25.0
+    );
+
+    assert!(AlertConfig::from_toml(
+        "[[rule]]\nname = \"x\"\ncondition = \"requests > 1\"\nfor = 1"
+    )
+    .is_err());
+    let config =
+        AlertConfig::from_toml("[[rule]]\nname = \"x\"\ncondition = \"requests >\"").unwrap();
+    assert!(config.rules().is_err());
+    let config = AlertConfig::from_toml("[kpis]\na = \"b + 1\"\nb = \"a * 2\"").unwrap();
+    assert!(config.rules().is_err());
+}
+
+#[tokio::test]
+async fn callbacks_and_channels() {
+    let rule = Rule::new("any_5xx", "status_5xx > 0").unwrap();
+    let notified = Arc::new(Mutex::new(Vec::new()));
+    let shared = notified.clone();
+    let mut engine = AlertEngine::new(vec![rule])
+        .unwrap()
+        .on_event(move |event| shared.lock().unwrap().push(event.to_string()));
+    let mut receiver = engine.subscribe();
+
+    engine.evaluate("www", &service_second(1, 1, 0));
+    engine.evaluate("www", &service_second(2, 0, 0));
+
+    assert_eq!(
+        *notified.lock().unwrap(),
+        vec![
+            "Firing any_5xx of www in aggregated, value 1",
+            "Resolved any_5xx of www in aggregated, value 0"
+        ]
+    );
+    assert_eq!(receiver.recv().await.unwrap().state, AlertState::Firing);
+    assert_eq!(receiver.recv().await.unwrap().state, AlertState::Resolved);
+    assert!(receiver.try_recv().is_err());
+}
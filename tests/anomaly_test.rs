@@ -0,0 +1,176 @@
+use fastly_rt::anomaly::{AnomalyMonitor, Detector, Ewma, Mad, Seasonal, Watch};
+use fastly_rt::origin::{OriginDataInSecond, OriginStats};
+use fastly_rt::service::ServiceDataInSecond;
+use fastly_rt::timeseries::Scope;
+
+fn service_second(recorded: u64, requests: u64) -> ServiceDataInSecond {
+    let mut data = ServiceDataInSecond {
+        recorded,
+        ..Default::default()
+    };
+    data.aggregated.requests = requests;
+    data
+}
+
+#[test]
+fn ewma_fits_any_traffic_level() {
+    let watch = Watch::new("requests", Ewma::new(0.1, 30));
+    let mut monitor = AnomalyMonitor::<ServiceDataInSecond>::new(vec![watch]).unwrap();
+
+    // Same relative noise at 100 and 100000 requests per second, then twice the traffic
+    let mut anomalies = Vec::new();
+    for recorded in 1..=120 {
+        let noise = [0, 2, 1, 3, 2][recorded as usize % 5];
+        for (service, level) in [("small", 100), ("large", 100_000)] {
+            let requests = if recorded == 100 {
+                2 * level
+            } else {
+                level + noise * level / 100
+            };
+            anomalies.extend(monitor.observe(service, &service_second(recorded, requests)));
+        }
+    }
+
+    assert_eq!(anomalies.len(), 2);
+    for anomaly in &anomalies {
+        assert_eq!(anomaly.recorded, 100);
+        assert_eq!(anomaly.detector, "ewma");
+        assert_eq!(anomaly.scope, Scope::aggregated());
+        assert!(anomaly.score > 3.0);
+        assert!(anomaly.lower < anomaly.expected && anomaly.expected < anomaly.upper);
+        assert!(anomaly.upper < anomaly.value);
+    }
+    let large = anomalies.iter().find(|a| a.service == "large").unwrap();
+    assert!(large.expected > 100_000.0 && large.expected < 103_000.0);
+}
+
+#[test]
+fn seasonal_baseline_by_time_of_period() {
+    // Periods of 40 seconds, a quiet and a busy half
+    let level = |recorded: u64| {
+        let base = if recorded % 40 < 20 { 10 } else { 1000 };
+        base + recorded % 2
+    };
+    let mut detector = Detector::from(Seasonal::new(40, 10));
+
+    for recorded in 0..40 {
+        assert!(detector.baseline(recorded).is_none());
+        detector.learn(recorded, level(recorded) as f64);
+    }
+    for recorded in 40..120 {
+        let baseline = detector.baseline(recorded).unwrap();
+        assert!(baseline.score(level(recorded) as f64).abs() <= 1.0);
+        detector.learn(recorded, level(recorded) as f64);
+    }
+
+    // Busy traffic in the quiet half is an anomaly, in the busy half it is not
+    let quiet = detector.baseline(125).unwrap();
+    assert_eq!(quiet.expected, 10.5);
+    assert!(quiet.score(1000.0) > 100.0);
+    let busy = detector.baseline(145).unwrap();
+    assert_eq!(busy.expected, 1000.5);
+    assert!(busy.score(1000.0).abs() <= 1.0);
+
+    // A day is split in quarters of an hour
+    let daily = Seasonal::daily();
+    assert_eq!((daily.period, daily.bucket), (86400, 900));
+}
+
+#[test]
+fn mad_is_robust_to_outliers() {
+    let mut mad = Mad::new(20);
+    for index in 0..20 {
+        let value = if index % 7 == 0 {
+            5000.0
+        } else {
+            100.0 + (index % 3) as f64
+        };
+        mad.learn(value);
+    }
+
+    // The outliers of the window do not move the baseline
+    let baseline = mad.baseline().unwrap();
+    assert_eq!(baseline.expected, 101.0);
+    assert!((baseline.spread - 1.4826).abs() < 1e-9);
+    assert!(baseline.score(110.0) > 3.0);
+    assert!(baseline.score(99.0).abs() < 3.0);
+
+    let mut ewma = Ewma::new(0.5, 1);
+    ewma.learn(1.0);
+    assert_eq!(ewma.baseline().unwrap().spread, 0.0);
+    assert_eq!(ewma.baseline().unwrap().score(1.0), 0.0);
+    assert_eq!(ewma.baseline().unwrap().score(2.0), f64::INFINITY);
+}
+
+#[test]
+fn monitor_pops_and_origins() {
+    assert!(
+        AnomalyMonitor::<ServiceDataInSecond>::new(vec![Watch::new("nope", Mad::default())])
+            .is_err()
+    );
+
+    let watch = Watch::new("status_5xx", Mad::new(10)).by_pop(true);
+    let mut monitor = AnomalyMonitor::<ServiceDataInSecond>::new(vec![watch]).unwrap();
+    let second = |recorded: u64, lhr_5xx: u64| {
+        let mut data = service_second(recorded, 100);
+        for (pop_name, status_5xx) in [("NRT", 1 + recorded % 2), ("LHR", lhr_5xx)] {
+            data.datacenter
+                .entry(pop_name.to_string())
+                .or_default()
+                .status_5xx = status_5xx;
+        }
+        data
+    };
+    for recorded in 1..=10 {
+        assert!(monitor
+            .observe("www", &second(recorded, 1 + recorded % 2))
+            .is_empty());
+    }
+    let anomalies = monitor.observe("www", &second(11, 50));
+    assert_eq!(anomalies.len(), 1);
+    assert_eq!(anomalies[0].scope, Scope::pop("LHR"));
+    assert_eq!(anomalies[0].value, 50.0);
+    assert_eq!(
+        anomalies[0].to_string(),
+        format!(
+            "status_5xx of www in pop LHR: 50 out of {}..{}, score {:.1}",
+            anomalies[0].lower, anomalies[0].upper, anomalies[0].score
+        )
+    );
+
+    // A second not later than the last one observed is ignored
+    assert!(monitor.observe("www", &second(11, 90)).is_empty());
+    monitor.reset("www");
+    assert!(monitor.observe("www", &second(12, 90)).is_empty());
+
+    let watch = Watch::new("status_503", Ewma::new(0.2, 5)).origin("s3");
+    let mut monitor = AnomalyMonitor::<OriginDataInSecond>::new(vec![watch]).unwrap();
+    let mut anomalies = Vec::new();
+    for recorded in 1..=20 {
+        let mut data = OriginDataInSecond {
+            recorded,
+            ..Default::default()
+        };
+        for origin_name in ["s3", "gcs"] {
+            let status_503 = if recorded == 20 { 40 } else { 4 + recorded % 2 };
+            data.aggregated.insert(
+                origin_name.to_string(),
+                OriginStats {
+                    status_503,
+                    ..Default::default()
+                },
+            );
+        }
+        anomalies.extend(monitor.observe_response(
+            "www",
+            &fastly_rt::realtime::RtResponse {
+                aggregate_delay: 0,
+                data: vec![data],
+                timestamp: recorded,
+            },
+        ));
+    }
+    assert_eq!(anomalies.len(), 1);
+    assert_eq!(anomalies[0].scope, Scope::origin("s3"));
+    assert_eq!(anomalies[0].recorded, 20);
+}
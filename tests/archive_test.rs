@@ -0,0 +1,242 @@
+mod common;
+
+use fastly_rt::archive::{ArchiveConfig, ArchiveReader, Archiver, Compression};
+use fastly_rt::origin::OriginDataInSecond;
+use fastly_rt::service::ServiceDataInSecond;
+use std::path::Path;
+use std::time::Duration;
+
+// 2023-11-14T22:13:20Z, the next hour starting 2800 seconds later
+const START: u64 = 1700000000;
+const NEXT_HOUR: u64 = 1700002800;
+
+fn service_second(recorded: u64) -> ServiceDataInSecond {
+    serde_json::from_str(&format!(
+        r#"{{"recorded": {}, "aggregated": {{"requests": 3}}, "datacenter": {{"NRT": {{"requests": 3}}}}}}"#,
+        recorded
+    ))
+    .unwrap()
+}
+
+fn file_names(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .collect();
+    names.sort();
+    names
+}
+
+fn recorded(reader: &ArchiveReader<ServiceDataInSecond>, from: u64, to: u64) -> Vec<u64> {
+    reader
+        .range(from, to)
+        .unwrap()
+        .map(|data| data.unwrap().recorded)
+        .collect()
+}
+
+#[test]
+fn hourly_files_compressed_with_zstd() {
+    let dir = common::temp_dir("archive-zstd");
+    let mut archiver = Archiver::new(ArchiveConfig::new(&dir)).unwrap();
+    let seconds: Vec<ServiceDataInSecond> = [START, START + 1, NEXT_HOUR - 1, NEXT_HOUR]
+        .into_iter()
+        .map(service_second)
+        .collect();
+    archiver.append(&seconds[..2]).unwrap();
+    archiver.append(&seconds[2..]).unwrap();
+
+    assert_eq!(
+        file_names(&dir),
+        vec![
+            "service-2023-11-14T22-1700000000.ndjson.zst",
+            "service-2023-11-14T23-1700002800.ndjson",
+        ]
+    );
+    archiver.close().unwrap();
+    assert_eq!(
+        file_names(&dir)[1],
+        "service-2023-11-14T23-1700002800.ndjson.zst"
+    );
+
+    let reader = ArchiveReader::new(&dir);
+    let all: Vec<ServiceDataInSecond> = reader.all().unwrap().map(|data| data.unwrap()).collect();
+    assert_eq!(all.len(), 4);
+    assert_eq!(all[3].aggregated.requests, 3);
+    assert_eq!(all[3].datacenter["NRT"].requests, 3);
+
+    assert_eq!(
+        recorded(&reader, START + 1, NEXT_HOUR),
+        vec![START + 1, NEXT_HOUR - 1]
+    );
+    assert_eq!(recorded(&reader, NEXT_HOUR, u64::MAX), vec![NEXT_HOUR]);
+    assert!(recorded(&reader, 0, START).is_empty());
+
+    let origins = ArchiveReader::<OriginDataInSecond>::new(&dir);
+    assert_eq!(origins.all().unwrap().count(), 0);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn files_rotated_by_size_compressed_with_gzip() {
+    let dir = common::temp_dir("archive-gzip");
+    let config = ArchiveConfig {
+        max_file_size: Some(1),
+        compression: Compression::Gzip,
+        ..ArchiveConfig::new(&dir)
+    };
+    let mut archiver = Archiver::new(config).unwrap();
+    let seconds: Vec<ServiceDataInSecond> = (START..START + 3).map(service_second).collect();
+    archiver.append(&seconds).unwrap();
+    drop(archiver);
+
+    let names = file_names(&dir);
+    assert_eq!(names.len(), 3);
+    assert!(names.iter().all(|name| name.ends_with(".ndjson.gz")));
+
+    let reader = ArchiveReader::new(&dir);
+    assert_eq!(
+        recorded(&reader, 0, u64::MAX),
+        vec![START, START + 1, START + 2]
+    );
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn retention_by_age() {
+    let dir = common::temp_dir("archive-age");
+    let config = ArchiveConfig {
+        max_age: Some(Duration::from_secs(3600)),
+        ..ArchiveConfig::new(&dir)
+    };
+    let mut archiver = Archiver::new(config).unwrap();
+    for recorded in [START, NEXT_HOUR, NEXT_HOUR + 3600, NEXT_HOUR + 7200] {
+        archiver.append(&[service_second(recorded)]).unwrap();
+    }
+    archiver.close().unwrap();
+
+    // When the third file is closed, data of the first one ends an hour before its last second
+    let reader = ArchiveReader::new(&dir);
+    assert_eq!(
+        recorded(&reader, 0, u64::MAX),
+        vec![NEXT_HOUR, NEXT_HOUR + 3600, NEXT_HOUR + 7200]
+    );
+
+    // Also enforced on start, e.g. by an archiver restarted before rotating
+    let config = ArchiveConfig {
+        max_age: Some(Duration::from_secs(1800)),
+        ..ArchiveConfig::new(&dir)
+    };
+    drop(Archiver::<ServiceDataInSecond>::new(config).unwrap());
+    assert_eq!(
+        recorded(&reader, 0, u64::MAX),
+        vec![NEXT_HOUR + 3600, NEXT_HOUR + 7200]
+    );
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn retention_by_total_size() {
+    let dir = common::temp_dir("archive-size");
+    let config = ArchiveConfig {
+        max_file_size: Some(1),
+        compression: Compression::None,
+        max_total_size: Some(1),
+        ..ArchiveConfig::new(&dir)
+    };
+    let mut archiver = Archiver::new(config).unwrap();
+    let seconds: Vec<ServiceDataInSecond> = (START..START + 5).map(service_second).collect();
+    archiver.append(&seconds).unwrap();
+
+    // Every closed file is removed, the current one is kept
+    assert_eq!(
+        file_names(&dir),
+        vec!["service-2023-11-14T22-1700000004.ndjson"]
+    );
+    let reader = ArchiveReader::new(&dir);
+    assert_eq!(recorded(&reader, 0, u64::MAX), vec![START + 4]);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn plain_files_compressed_on_start() {
+    let dir = common::temp_dir("archive-restart");
+    let config = ArchiveConfig {
+        compression: Compression::None,
+        ..ArchiveConfig::new(&dir)
+    };
+    let mut archiver = Archiver::new(config).unwrap();
+    archiver.append(&[service_second(START)]).unwrap();
+    archiver.close().unwrap();
+    assert_eq!(
+        file_names(&dir),
+        vec!["service-2023-11-14T22-1700000000.ndjson"]
+    );
+
+    // Files not named after a first second are left alone
+    std::fs::write(dir.join("service-notes.ndjson"), "notes").unwrap();
+    let archiver = Archiver::<ServiceDataInSecond>::new(ArchiveConfig::new(&dir)).unwrap();
+    assert_eq!(
+        file_names(&dir),
+        vec![
+            "service-2023-11-14T22-1700000000.ndjson.zst",
+            "service-notes.ndjson"
+        ]
+    );
+    assert_eq!(archiver.config().compression, Compression::Zstd);
+
+    let reader = ArchiveReader::new(&dir);
+    assert_eq!(recorded(&reader, 0, u64::MAX), vec![START]);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn compressed_file_of_the_same_name_appended() {
+    let dir = common::temp_dir("archive-append");
+    for (compression, seconds) in [
+        (Compression::Gzip, [START, START + 1]),
+        (Compression::Zstd, [NEXT_HOUR, NEXT_HOUR + 1]),
+    ] {
+        // A restart opens the file of the same first second again
+        for recorded in seconds {
+            let config = ArchiveConfig {
+                compression,
+                ..ArchiveConfig::new(&dir)
+            };
+            let mut archiver = Archiver::new(config).unwrap();
+            archiver
+                .append(&[service_second(seconds[0]), service_second(recorded)])
+                .unwrap();
+            archiver.close().unwrap();
+        }
+    }
+
+    assert_eq!(
+        file_names(&dir),
+        vec![
+            "service-2023-11-14T22-1700000000.ndjson.gz",
+            "service-2023-11-14T23-1700002800.ndjson.zst",
+        ]
+    );
+    let reader = ArchiveReader::new(&dir);
+    assert_eq!(
+        recorded(&reader, 0, u64::MAX),
+        vec![
+            START,
+            START,
+            START,
+            START + 1,
+            NEXT_HOUR,
+            NEXT_HOUR,
+            NEXT_HOUR,
+            NEXT_HOUR + 1
+        ]
+    );
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
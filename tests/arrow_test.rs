@@ -0,0 +1,174 @@
+mod common;
+
+use arrow_array::cast::AsArray;
+use arrow_array::types::{Float64Type, TimestampSecondType};
+use arrow_array::{Array, RecordBatch};
+use fastly_rt::arrow::{Columnar, Layout, ParquetWriter};
+use fastly_rt::metric::Metrics;
+use fastly_rt::origin::{OriginDataInSecond, OriginStats};
+use fastly_rt::service::{ServiceDataInSecond, ServiceStats};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use std::fs::File;
+use std::path::Path;
+
+fn service_second(recorded: u64) -> ServiceDataInSecond {
+    serde_json::from_str(&format!(
+        r#"{{
+            "recorded": {},
+            "aggregated": {{"requests": 3, "hits_time": 0.5, "miss_histogram": {{"10": 1, "30": 2}}}},
+            "datacenter": {{"NRT": {{"requests": 1}}, "LHR": {{"requests": 2}}}}
+        }}"#,
+        recorded
+    ))
+    .unwrap()
+}
+
+fn origin_second(recorded: u64) -> OriginDataInSecond {
+    serde_json::from_str(&format!(
+        r#"{{
+            "recorded": {},
+            "aggregated": {{"s3": {{"responses": 3}}, "gcs": {{"responses": 1}}}},
+            "datacenter": {{"NRT": {{"s3": {{"responses": 3}}}}, "LHR": {{"gcs": {{"responses": 1}}}}}}
+        }}"#,
+        recorded
+    ))
+    .unwrap()
+}
+
+fn strings(batch: &RecordBatch, column: &str) -> Vec<Option<String>> {
+    batch
+        .column_by_name(column)
+        .unwrap()
+        .as_string::<i32>()
+        .iter()
+        .map(|value| value.map(str::to_string))
+        .collect()
+}
+
+fn floats(batch: &RecordBatch, column: &str) -> Vec<Option<f64>> {
+    batch
+        .column_by_name(column)
+        .unwrap()
+        .as_primitive::<Float64Type>()
+        .iter()
+        .collect()
+}
+
+fn read(path: &Path) -> Vec<RecordBatch> {
+    ParquetRecordBatchReaderBuilder::try_new(File::open(path).unwrap())
+        .unwrap()
+        .build()
+        .unwrap()
+        .map(|batch| batch.unwrap())
+        .collect()
+}
+
+#[test]
+fn service_wide() {
+    let seconds = vec![service_second(1700000000), service_second(1700000001)];
+    let batch = ServiceDataInSecond::wide(&seconds, false).unwrap();
+
+    assert_eq!(batch.num_rows(), 2);
+    assert_eq!(
+        batch.num_columns(),
+        2 + ServiceStats::METRICS.len() + 3,
+        "recorded, pop, metrics and percentiles"
+    );
+    let recorded = batch.column(0).as_primitive::<TimestampSecondType>();
+    assert_eq!(recorded.value(1), 1700000001);
+    assert_eq!(strings(&batch, "pop"), vec![None, None]);
+    assert_eq!(floats(&batch, "requests"), vec![Some(3.0), Some(3.0)]);
+    assert_eq!(floats(&batch, "hits_time"), vec![Some(0.5), Some(0.5)]);
+    assert!(floats(&batch, "miss_latency_p50")[0].is_some());
+}
+
+#[test]
+fn service_wide_by_pop() {
+    let batch = ServiceDataInSecond::wide(&[service_second(1700000000)], true).unwrap();
+
+    assert_eq!(
+        strings(&batch, "pop"),
+        vec![Some("LHR".to_string()), Some("NRT".to_string())]
+    );
+    assert_eq!(floats(&batch, "requests"), vec![Some(2.0), Some(1.0)]);
+    assert_eq!(floats(&batch, "miss_latency_p50"), vec![None, None]);
+    assert_eq!(batch.column_by_name("pop").unwrap().null_count(), 0);
+}
+
+#[test]
+fn service_long() {
+    let batch = ServiceDataInSecond::long(&[service_second(1700000000)], false).unwrap();
+
+    let metrics = strings(&batch, "metric");
+    assert_eq!(metrics.len(), ServiceStats::METRICS.len() + 3);
+    let requests = metrics
+        .iter()
+        .position(|metric| metric.as_deref() == Some("requests"))
+        .unwrap();
+    assert_eq!(floats(&batch, "value")[requests], Some(3.0));
+    assert_eq!(metrics.last().unwrap().as_deref(), Some("miss_latency_p99"));
+    assert!(batch.column_by_name("origin").is_none());
+}
+
+#[test]
+fn origin_wide_and_long() {
+    let seconds = [origin_second(1700000000)];
+
+    let batch = OriginDataInSecond::wide(&seconds, false).unwrap();
+    assert_eq!(batch.num_columns(), 3 + OriginStats::METRICS.len());
+    assert_eq!(
+        strings(&batch, "origin"),
+        vec![Some("gcs".to_string()), Some("s3".to_string())]
+    );
+    assert_eq!(floats(&batch, "responses"), vec![Some(1.0), Some(3.0)]);
+
+    let batch = OriginDataInSecond::long(&seconds, true).unwrap();
+    assert_eq!(batch.num_rows(), 2 * OriginStats::METRICS.len());
+    assert_eq!(strings(&batch, "pop")[0].as_deref(), Some("LHR"));
+    assert_eq!(strings(&batch, "origin")[0].as_deref(), Some("gcs"));
+}
+
+#[test]
+fn parquet_files_by_hour() {
+    let dir = common::temp_dir("parquet");
+    // 2023-11-14T22:13:20Z, the next hour starting 2800 seconds later
+    let seconds: Vec<ServiceDataInSecond> = [1700000000, 1700002799, 1700002800]
+        .into_iter()
+        .map(service_second)
+        .collect();
+
+    let mut writer = ParquetWriter::new(&dir, Layout::Wide);
+    writer.write(&seconds[..1]).unwrap();
+    writer.write(&seconds[1..]).unwrap();
+    let files = writer.close().unwrap();
+
+    assert_eq!(
+        files,
+        vec![
+            dir.join("service-2023-11-14T22.parquet"),
+            dir.join("service-2023-11-14T23.parquet")
+        ]
+    );
+    let rows: Vec<usize> = files
+        .iter()
+        .map(|file| read(file).iter().map(RecordBatch::num_rows).sum())
+        .collect();
+    assert_eq!(rows, vec![2, 1]);
+    let batch = &read(&files[1])[0];
+    assert_eq!(floats(batch, "requests"), vec![Some(3.0)]);
+
+    let mut writer = ParquetWriter::new(&dir, Layout::Long).by_pop(true);
+    writer.write(&[origin_second(1700000000)]).unwrap();
+    let files = writer.close().unwrap();
+    assert_eq!(files, vec![dir.join("origin-2023-11-14T22.parquet")]);
+
+    let mut writer = ParquetWriter::new(&dir, Layout::Wide);
+    writer.write(&seconds[..1]).unwrap();
+    drop(writer);
+    assert_eq!(
+        read(&dir.join("service-2023-11-14T22-1.parquet"))[0].num_rows(),
+        1
+    );
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,154 @@
+use fastly_rt::cassette::{CassetteEntry, Pace, Recorder, Replay};
+use fastly_rt::fake::{FakeConfig, FakeServer, Fault};
+use fastly_rt::origin::OriginDataInSecond;
+use fastly_rt::realtime::{Query, RealtimeClient, RtClient};
+use fastly_rt::service::{ServiceClient, ServiceDataInSecond};
+use std::io::{self, Cursor, Write};
+use std::time::{Duration, Instant};
+
+#[tokio::test]
+async fn record_and_replay() {
+    let config = FakeConfig::default();
+    let server = FakeServer::start(config.clone()).await.unwrap();
+    let client = ServiceClient::with_base_url(&config.api_key, "sid", &server.base_url()).unwrap();
+    let mut recorder = Recorder::new(client, Vec::new());
+
+    let first = recorder.get_stats_max(3).await.unwrap();
+    server.inject(Fault::Status(503));
+    assert!(recorder.get_stats_consecutive().await.is_err());
+    let second = recorder.get_stats_consecutive().await.unwrap();
+
+    let (_, cassette) = recorder.into_inner();
+    let lines: Vec<CassetteEntry> = String::from_utf8(cassette.clone())
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0].query, Query::Max(3));
+    assert_eq!(lines[0].endpoint, "/v1/channel");
+    assert_eq!(lines[0].service_id, "sid");
+    assert!(lines[1].error.as_ref().unwrap().contains("503"));
+    assert_eq!(lines[2].query, Query::Consecutive);
+
+    let mut replay = Replay::<ServiceDataInSecond>::from_reader(Cursor::new(&cassette)).unwrap();
+    assert_eq!(replay.remaining(), 3);
+
+    let replayed = replay.get_stats_120s().await.unwrap();
+    assert_eq!(replayed.timestamp, first.timestamp);
+    assert_eq!(replayed.data.len(), 3);
+    assert_eq!(
+        replayed.data[2].aggregated.requests,
+        first.data[2].aggregated.requests
+    );
+    assert!(replay.get_stats_consecutive().await.is_err());
+    assert_eq!(
+        replay.get_stats_consecutive().await.unwrap().timestamp,
+        second.timestamp
+    );
+    assert!(replay.get_stats_consecutive().await.is_err());
+
+    let origin = Replay::<OriginDataInSecond>::from_reader(Cursor::new(&cassette)).unwrap();
+    assert_eq!(origin.remaining(), 0);
+}
+
+#[tokio::test]
+async fn reset_recorded_and_replayed() {
+    let config = FakeConfig::default();
+    let server = FakeServer::start(config.clone()).await.unwrap();
+    let client = ServiceClient::with_base_url(&config.api_key, "sid", &server.base_url()).unwrap();
+    let mut recorder = Recorder::new(client, Vec::new());
+
+    recorder.get_stats_consecutive().await.unwrap();
+    recorder.reset_stats_consecutive();
+    let after_reset = recorder.get_stats_consecutive().await.unwrap();
+
+    let (_, cassette) = recorder.into_inner();
+    let lines: Vec<CassetteEntry> = String::from_utf8(cassette.clone())
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(
+        lines.iter().map(|entry| entry.reset).collect::<Vec<bool>>(),
+        vec![false, true, false]
+    );
+    assert!(lines[1].response.is_none() && lines[1].error.is_none());
+
+    // Consumed by a reset of the replay
+    let mut replay = Replay::<ServiceDataInSecond>::from_reader(Cursor::new(&cassette)).unwrap();
+    replay.get_stats_consecutive().await.unwrap();
+    replay.reset_stats_consecutive();
+    assert_eq!(replay.remaining(), 1);
+    assert_eq!(
+        replay.get_stats_consecutive().await.unwrap().timestamp,
+        after_reset.timestamp
+    );
+
+    // Skipped by queries
+    let mut replay = Replay::<ServiceDataInSecond>::from_reader(Cursor::new(&cassette)).unwrap();
+    replay.get_stats_consecutive().await.unwrap();
+    assert_eq!(
+        replay.get_stats_consecutive().await.unwrap().timestamp,
+        after_reset.timestamp
+    );
+    assert_eq!(replay.remaining(), 0);
+}
+
+struct BrokenWriter;
+
+impl Write for BrokenWriter {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::Error::other("disk full"))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn response_returned_when_cassette_write_fails() {
+    let config = FakeConfig::default();
+    let server = FakeServer::start(config.clone()).await.unwrap();
+    let client = ServiceClient::with_base_url(&config.api_key, "sid", &server.base_url()).unwrap();
+    let recorder = Recorder::new(client, BrokenWriter);
+
+    assert!(recorder.take_write_error().is_none());
+    let response = recorder.get_stats_max(2).await.unwrap();
+    assert_eq!(response.data.len(), 2);
+
+    let error = recorder.take_write_error().unwrap();
+    assert!(error.to_string().contains("disk full"), "{}", error);
+    assert!(recorder.take_write_error().is_none());
+}
+
+#[tokio::test]
+async fn replay_pace() {
+    let entry = |at_ms| CassetteEntry {
+        at_ms,
+        endpoint: "/v1/channel".to_string(),
+        service_id: "sid".to_string(),
+        query: Query::Consecutive,
+        response: Some(serde_json::json!({"Timestamp": at_ms, "AggregateDelay": 0, "Data": []})),
+        error: None,
+        reset: false,
+    };
+    let mut replay = Replay::<ServiceDataInSecond>::from_entries(vec![entry(0), entry(1000)])
+        .with_pace(Pace::Accelerated(10.0));
+
+    let start = Instant::now();
+    replay.get_stats_consecutive().await.unwrap();
+    replay.get_stats_consecutive().await.unwrap();
+
+    assert!(start.elapsed() >= Duration::from_millis(100));
+    assert!(start.elapsed() < Duration::from_millis(1000));
+}
+
+#[test]
+fn recorder_is_a_realtime_client() {
+    fn data_type_of<C: RealtimeClient<Data = ServiceDataInSecond>>(_client: &C) {}
+
+    let client: RtClient<ServiceDataInSecond> = ServiceClient::new("key", "sid").unwrap();
+    data_type_of(&Recorder::new(client, Vec::new()));
+}
@@ -0,0 +1,102 @@
+use fastly_rt::csv::CsvWriter;
+use fastly_rt::metric::Metrics;
+use fastly_rt::origin::{OriginDataInSecond, OriginResponse};
+use fastly_rt::service::{ServiceDataInSecond, ServiceResponse, ServiceStats};
+
+fn service_second(recorded: u64) -> ServiceDataInSecond {
+    serde_json::from_str(&format!(
+        r#"{{
+            "recorded": {},
+            "aggregated": {{"requests": 3, "hits_time": 0.5}},
+            "datacenter": {{"NRT": {{"requests": 1}}, "LHR": {{"requests": 2, "hits_time": 0.5}}}}
+        }}"#,
+        recorded
+    ))
+    .unwrap()
+}
+
+fn text<W: Into<Vec<u8>>>(out: W) -> String {
+    String::from_utf8(out.into()).unwrap()
+}
+
+#[test]
+fn service_rows() {
+    let response = ServiceResponse {
+        data: vec![service_second(1700000000), service_second(1700000001)],
+        aggregate_delay: 0,
+        timestamp: 0,
+    };
+
+    let mut csv = CsvWriter::service(Vec::new(), &["requests", "hits_time"]).unwrap();
+    csv.write_service(&response).unwrap();
+
+    assert_eq!(
+        text(csv.into_inner().unwrap()),
+        "recorded,scope,requests,hits_time\n\
+         1700000000,aggregated,3,0.5\n\
+         1700000001,aggregated,3,0.5\n"
+    );
+}
+
+#[test]
+fn service_rows_with_pops() {
+    let mut csv = CsvWriter::service(Vec::new(), &["requests"])
+        .unwrap()
+        .with_pops(true);
+    csv.write_service_second(&service_second(1700000000))
+        .unwrap();
+
+    assert_eq!(
+        text(csv.into_inner().unwrap()),
+        "recorded,scope,requests\n\
+         1700000000,aggregated,3\n\
+         1700000000,LHR,2\n\
+         1700000000,NRT,1\n"
+    );
+}
+
+#[test]
+fn all_metrics_when_none_selected() {
+    let csv = CsvWriter::service(Vec::new(), &[]).unwrap();
+    assert_eq!(csv.metrics().len(), ServiceStats::METRICS.len());
+
+    let header = text(csv.into_inner().unwrap());
+    let columns: Vec<&str> = header.trim_end().split(',').collect();
+    assert_eq!(columns.len(), 2 + ServiceStats::METRICS.len());
+    assert_eq!(columns[2], ServiceStats::METRICS[0].name);
+}
+
+#[test]
+fn unknown_metric() {
+    assert!(CsvWriter::service(Vec::new(), &["requests", "nope"]).is_err());
+    assert!(CsvWriter::origins(Vec::new(), &["requests"]).is_err());
+}
+
+#[test]
+fn origin_rows() {
+    let response = OriginResponse {
+        data: vec![serde_json::from_str::<OriginDataInSecond>(
+            r#"{
+                "recorded": 1700000000,
+                "aggregated": {"s3": {"responses": 3}, "my, \"origin\"": {"responses": 1}},
+                "datacenter": {"NRT": {"s3": {"responses": 3}}}
+            }"#,
+        )
+        .unwrap()],
+        aggregate_delay: 0,
+        timestamp: 0,
+    };
+
+    let mut csv = CsvWriter::origins(Vec::new(), &["responses"])
+        .unwrap()
+        .with_pops(true);
+    csv.write_origins(&response).unwrap();
+
+    assert_eq!(
+        text(csv.into_inner().unwrap()),
+        "recorded,scope,origin,responses\n\
+         1700000000,aggregated,\"my, \"\"origin\"\"\",1\n\
+         1700000000,aggregated,s3,3\n\
+         1700000000,NRT,s3,3\n"
+    );
+}
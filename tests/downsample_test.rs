@@ -0,0 +1,111 @@
+use fastly_rt::downsample::{Downsampler, Window, HOUR, MINUTE};
+use fastly_rt::origin::OriginDataInSecond;
+use fastly_rt::realtime::RtResponse;
+use fastly_rt::service::ServiceDataInSecond;
+
+fn service_second(recorded: u64) -> ServiceDataInSecond {
+    serde_json::from_str(&format!(
+        r#"{{
+            "recorded": {recorded},
+            "aggregated": {{"requests": 2, "hits_time": 0.5, "miss_histogram": {{"10": 1}}}},
+            "datacenter": {{"NRT": {{"requests": 2, "hits_time": 0.5}}}}
+        }}"#
+    ))
+    .unwrap()
+}
+
+fn spans<D>(windows: &[Window<D>]) -> Vec<(u64, u64, u64)> {
+    windows
+        .iter()
+        .map(|window| (window.start, window.resolution, window.seconds))
+        .collect()
+}
+
+#[test]
+fn minutes_and_hours() {
+    let mut downsampler = Downsampler::new(&[MINUTE, HOUR]);
+    let mut windows = Vec::new();
+    for recorded in 3540..3661 {
+        windows.extend(downsampler.push(&service_second(recorded)));
+    }
+
+    assert_eq!(
+        spans(&windows),
+        vec![(3540, 60, 60), (0, 3600, 60), (3600, 60, 60)]
+    );
+    let minute = &windows[0].data;
+    assert_eq!(minute.aggregated.requests, 120);
+    assert_eq!(minute.aggregated.hits_time, 30.0);
+    assert_eq!(minute.aggregated.miss_histogram.total(), 60);
+    assert_eq!(minute.datacenter["NRT"].requests, 120);
+    assert_eq!(minute.recorded, 3599);
+    assert!(windows[0].is_complete());
+    assert!(!windows[1].is_complete());
+    assert_eq!(windows[1].coverage(), 60.0 / 3600.0);
+
+    let rest = downsampler.flush();
+    assert_eq!(spans(&rest), vec![(3660, 60, 1), (3600, 3600, 61)]);
+    assert!(downsampler.flush().is_empty());
+}
+
+#[test]
+fn late_missing_and_duplicate_seconds() {
+    let mut downsampler = Downsampler::new(&[MINUTE]).with_lateness(5);
+    let mut windows = Vec::new();
+    for recorded in [0, 1, 2, 59, 61, 62, 30, 64, 30] {
+        windows.extend(downsampler.push(&service_second(recorded)));
+    }
+
+    // Second 30 arrived 3 seconds after the end of its minute, within the lateness
+    assert_eq!(spans(&windows), vec![(0, 60, 5)]);
+    assert_eq!(windows[0].data.aggregated.requests, 10);
+    assert_eq!(downsampler.duplicate_seconds(), 0);
+    assert_eq!(downsampler.late_seconds(), 1);
+
+    downsampler.push(&service_second(62));
+    assert_eq!(downsampler.duplicate_seconds(), 1);
+    let windows = downsampler.flush();
+    assert_eq!(windows[0].data.aggregated.requests, 6);
+}
+
+#[test]
+fn lateness_from_aggregate_delay() {
+    let mut downsampler = Downsampler::new(&[MINUTE]);
+    let response = RtResponse {
+        aggregate_delay: 10,
+        data: (0..65).map(service_second).collect(),
+        timestamp: 65,
+    };
+    assert!(downsampler.push_response(&response).is_empty());
+
+    let response = RtResponse {
+        aggregate_delay: 10,
+        data: (65..70).map(service_second).collect(),
+        timestamp: 70,
+    };
+    assert_eq!(
+        spans(&downsampler.push_response(&response)),
+        vec![(0, 60, 60)]
+    );
+}
+
+#[test]
+fn origins() {
+    let second = |recorded: u64| -> OriginDataInSecond {
+        serde_json::from_str(&format!(
+            r#"{{"recorded": {recorded}, "aggregated": {{"s3": {{"responses": 3}}}},
+                "datacenter": {{"NRT": {{"s3": {{"responses": 3}}}}}}}}"#
+        ))
+        .unwrap()
+    };
+
+    let mut downsampler = Downsampler::new(&[MINUTE]);
+    for recorded in 0..30 {
+        downsampler.push(&second(recorded));
+    }
+    let windows = downsampler.flush();
+
+    assert_eq!(windows.len(), 1);
+    assert_eq!(windows[0].data.aggregated["s3"].responses, 90);
+    assert_eq!(windows[0].data.datacenter["NRT"]["s3"].responses, 90);
+}
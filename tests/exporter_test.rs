@@ -0,0 +1,177 @@
+use fastly_rt::exporter::{Exporter, ExporterConfig, MetricFilter, Registry};
+use fastly_rt::fake::{FakeConfig, FakeServer};
+use fastly_rt::origin::OriginDataInSecond;
+use fastly_rt::service::ServiceDataInSecond;
+use std::time::Duration;
+
+fn service_second() -> ServiceDataInSecond {
+    serde_json::from_str(
+        r#"{
+            "recorded": 1,
+            "aggregated": {"requests": 3, "status_5xx": 1, "compute_ram_used": 1024, "miss_histogram": {"10": 1, "30": 2}},
+            "datacenter": {
+                "NRT": {"requests": 1, "status_5xx": 1, "miss_histogram": {"10": 1}},
+                "LHR": {"requests": 2, "miss_histogram": {"30": 2}}
+            }
+        }"#,
+    )
+    .unwrap()
+}
+
+#[test]
+fn config_from_toml() {
+    let config = ExporterConfig::from_toml(
+        r#"
+        api_key = "key"
+
+        [[service]]
+        id = "sid1"
+        name = "www"
+        origins = true
+
+        [[service]]
+        id = "sid2"
+
+        [filter]
+        deny = ["compute_*"]
+        pop_allow = ["requests"]
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(config.listen.port(), 9150);
+    assert_eq!(config.poll_interval_ms, 1000);
+    assert_eq!(config.services.len(), 2);
+    assert_eq!(config.services[0].label(), "www");
+    assert!(config.services[0].origins);
+    assert_eq!(config.services[1].label(), "sid2");
+    assert!(!config.services[1].origins);
+    assert!(!config.filter.allows("compute_requests"));
+    assert!(config.filter.allows_pop("requests"));
+
+    assert!(ExporterConfig::from_toml("unknown = 1").is_err());
+}
+
+#[test]
+fn counters_accumulate() {
+    let mut registry = Registry::new(MetricFilter::default());
+    registry.add_service("www", &service_second());
+    registry.add_service("www", &service_second());
+
+    let text = registry.render();
+    assert!(text.contains("# TYPE fastly_rt_requests_total counter\n"));
+    assert!(text.contains("fastly_rt_requests_total{service=\"www\"} 6\n"));
+    assert!(text.contains("fastly_rt_status_5xx_total{service=\"www\"} 2\n"));
+    assert!(!text.contains("pop="));
+
+    assert!(text.contains("# TYPE fastly_rt_compute_ram_used gauge\n"));
+    assert!(text.contains("fastly_rt_compute_ram_used{service=\"www\"} 1024\n"));
+    assert!(!text.contains("fastly_rt_compute_ram_used_total"));
+
+    assert!(text.contains("# TYPE fastly_rt_miss_latency_seconds histogram\n"));
+    assert!(text.contains("fastly_rt_miss_latency_seconds_bucket{service=\"www\",le=\"0.01\"} 2\n"));
+    assert!(text.contains("fastly_rt_miss_latency_seconds_bucket{service=\"www\",le=\"0.03\"} 6\n"));
+    assert!(text.contains("fastly_rt_miss_latency_seconds_bucket{service=\"www\",le=\"+Inf\"} 6\n"));
+    assert!(text.contains("fastly_rt_miss_latency_seconds_count{service=\"www\"} 6\n"));
+}
+
+#[test]
+fn filter_metrics_and_pops() {
+    let filter = MetricFilter {
+        allow: vec![
+            "requests".to_string(),
+            "status_*".to_string(),
+            "origin_*".to_string(),
+        ],
+        pop_allow: vec!["requests".to_string(), "origin_*".to_string()],
+        pop_deny: vec!["origin_status_*".to_string()],
+        ..MetricFilter::default()
+    };
+    let mut registry = Registry::new(filter);
+    registry.add_service("www", &service_second());
+
+    let origins: OriginDataInSecond = serde_json::from_str(
+        r#"{
+            "recorded": 1,
+            "aggregated": {"be": {"responses": 3, "status_5xx": 1}},
+            "datacenter": {"NRT": {"be": {"responses": 1, "status_5xx": 1}}, "LHR": {"be": {"responses": 2}}}
+        }"#,
+    )
+    .unwrap();
+    registry.add_origins("www", &origins);
+
+    let text = registry.render();
+    assert!(text.contains("fastly_rt_requests_total{service=\"www\",pop=\"NRT\"} 1\n"));
+    assert!(text.contains("fastly_rt_requests_total{service=\"www\",pop=\"LHR\"} 2\n"));
+    assert!(!text.contains("fastly_rt_requests_total{service=\"www\"}"));
+    assert!(text.contains("fastly_rt_status_5xx_total{service=\"www\"} 1\n"));
+    assert!(!text.contains("fastly_rt_hits_total"));
+    assert!(!text.contains("miss_latency"));
+
+    assert!(text.contains(
+        "fastly_rt_origin_responses_total{service=\"www\",pop=\"LHR\",origin=\"be\"} 2\n"
+    ));
+    assert!(text.contains("fastly_rt_origin_status_5xx_total{service=\"www\",origin=\"be\"} 1\n"));
+}
+
+#[test]
+fn label_values_are_escaped() {
+    let mut registry = Registry::new(MetricFilter {
+        allow: vec!["requests".to_string()],
+        ..MetricFilter::default()
+    });
+    registry.add_service("a\"b\\c", &service_second());
+
+    assert!(registry
+        .render()
+        .contains("fastly_rt_requests_total{service=\"a\\\"b\\\\c\"} 3\n"));
+}
+
+#[tokio::test]
+async fn serves_metrics_polled_from_fake() {
+    let fake_config = FakeConfig::default();
+    let fake = FakeServer::start(fake_config.clone()).await.unwrap();
+
+    let config = ExporterConfig::from_toml(&format!(
+        r#"
+        listen = "127.0.0.1:0"
+        api_key = "{}"
+        base_url = "{}"
+        poll_interval_ms = 50
+
+        [[service]]
+        id = "sid"
+        name = "www"
+        origins = true
+
+        [filter]
+        pop_allow = ["requests"]
+        "#,
+        fake_config.api_key,
+        fake.base_url()
+    ))
+    .unwrap();
+    let exporter = Exporter::start(config).await.unwrap();
+
+    let url = format!("http://{}/metrics", exporter.local_addr());
+    let mut text = String::new();
+    for _ in 0..100 {
+        text = reqwest::get(&url).await.unwrap().text().await.unwrap();
+        if text.contains("fastly_rt_origin_responses_total") && text.contains("pop=") {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    assert!(text.contains("fastly_rt_requests_total{service=\"www\",pop=\""));
+    assert!(text.contains("fastly_rt_hits_total{service=\"www\"}"));
+    assert!(text.contains("fastly_rt_origin_responses_total{service=\"www\",origin=\""));
+    assert!(text.contains(
+        "fastly_rt_exporter_poll_errors_total{service=\"www\",endpoint=\"service\"} 0\n"
+    ));
+
+    let response = reqwest::get(format!("http://{}/other", exporter.local_addr()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+}
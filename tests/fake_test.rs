@@ -0,0 +1,87 @@
+use fastly_rt::fake::{FakeConfig, FakeServer, Fault};
+use fastly_rt::origin::OriginClient;
+use fastly_rt::service::ServiceClient;
+use std::time::{Duration, Instant};
+
+async fn start() -> (FakeServer, FakeConfig) {
+    let config = FakeConfig::default();
+    let server = FakeServer::start(config.clone()).await.unwrap();
+    (server, config)
+}
+
+#[tokio::test]
+async fn serves_consistent_data() {
+    let (server, config) = start().await;
+    let rt = ServiceClient::with_base_url(&config.api_key, "sid", &server.base_url()).unwrap();
+
+    let rt_data = rt.get_stats_120s().await.unwrap();
+    assert_eq!(rt_data.data.len(), 120);
+    assert_eq!(rt_data.aggregate_delay, config.aggregate_delay);
+    assert_eq!(rt_data.timestamp, rt_data.data[119].recorded + 1);
+
+    let data = &rt_data.data[0];
+    assert_eq!(data.datacenter.len(), config.traffic.pops.len());
+    let pop_requests: u64 = data.datacenter.values().map(|stats| stats.requests).sum();
+    assert_eq!(data.aggregated.requests, pop_requests);
+    assert!(data.aggregated.status_2xx >= data.aggregated.status_200);
+
+    let origin = OriginClient::with_base_url(&config.api_key, "sid", &server.base_url()).unwrap();
+    let origin_data = origin.get_stats_max(2).await.unwrap();
+    assert_eq!(origin_data.data.len(), 2);
+    assert_eq!(
+        origin_data.data[0].aggregated.len(),
+        config.traffic.origins.len()
+    );
+}
+
+#[tokio::test]
+async fn consecutive_stats_follow_timestamp() {
+    let (server, config) = start().await;
+    let mut rt = ServiceClient::with_base_url(&config.api_key, "sid", &server.base_url()).unwrap();
+
+    let first = rt.get_stats_consecutive().await.unwrap();
+    assert_eq!(first.data.len(), 1);
+
+    let next = rt.get_stats_from(first.timestamp + 10).await.unwrap();
+    assert!(next.data.is_empty());
+    assert_eq!(next.timestamp, first.timestamp + 10);
+}
+
+#[tokio::test]
+async fn rejects_wrong_api_key() {
+    let (server, _config) = start().await;
+    let rt = ServiceClient::with_base_url("wrong", "sid", &server.base_url()).unwrap();
+
+    let error = rt.get_stats_max(1).await.unwrap_err();
+    assert!(error.to_string().contains("401"));
+}
+
+#[tokio::test]
+async fn injected_faults() {
+    let (server, config) = start().await;
+    let rt = ServiceClient::with_base_url(&config.api_key, "sid", &server.base_url()).unwrap();
+
+    server.inject(Fault::Status(429));
+    server.inject(Fault::Status(503));
+    server.inject(Fault::Delay(Duration::from_millis(100)));
+
+    assert!(rt
+        .get_stats_max(1)
+        .await
+        .unwrap_err()
+        .to_string()
+        .contains("429"));
+    assert!(rt
+        .get_stats_max(1)
+        .await
+        .unwrap_err()
+        .to_string()
+        .contains("503"));
+
+    let start = Instant::now();
+    assert_eq!(rt.get_stats_max(1).await.unwrap().data.len(), 1);
+    assert!(start.elapsed() >= Duration::from_millis(100));
+
+    assert!(rt.get_stats_max(1).await.is_ok());
+    assert_eq!(server.requests(), 4);
+}
@@ -0,0 +1,93 @@
+use fastly_rt::graphite::{GraphiteWriter, Plaintext};
+use fastly_rt::metric::MetricFilter;
+use fastly_rt::origin::OriginDataInSecond;
+use fastly_rt::service::ServiceDataInSecond;
+use std::io::Read;
+use std::net::TcpListener;
+use std::thread;
+
+fn service_second() -> ServiceDataInSecond {
+    serde_json::from_str(
+        r#"{
+            "recorded": 1700000000,
+            "aggregated": {"requests": 3, "hits_time": 0.5},
+            "datacenter": {"NRT": {"requests": 1}, "L.HR": {"requests": 2}}
+        }"#,
+    )
+    .unwrap()
+}
+
+fn filter(allow: &[&str], pop_allow: &[&str]) -> MetricFilter {
+    MetricFilter {
+        allow: allow.iter().map(|name| name.to_string()).collect(),
+        pop_allow: pop_allow.iter().map(|name| name.to_string()).collect(),
+        ..MetricFilter::default()
+    }
+}
+
+#[test]
+fn service_paths() {
+    let lines = Plaintext::new().service("www", &service_second());
+    assert!(lines.contains("fastly_rt.www.requests 3 1700000000\n"));
+    assert!(lines.contains("fastly_rt.www.hits_time 0.5 1700000000\n"));
+    assert!(!lines.contains(".pops."));
+
+    let lines = Plaintext::new()
+        .with_prefix("cdn")
+        .with_filter(filter(&["requests", "hits"], &["requests"]))
+        .service("my site", &service_second());
+    assert_eq!(
+        lines,
+        "cdn.my_site.hits 0 1700000000\n\
+         cdn.my_site.pops.L_HR.requests 2 1700000000\n\
+         cdn.my_site.pops.NRT.requests 1 1700000000\n"
+    );
+}
+
+#[test]
+fn origin_paths() {
+    let origins: OriginDataInSecond = serde_json::from_str(
+        r#"{"recorded": 2, "aggregated": {"b": {"responses": 2}, "a.com": {"responses": 1}},
+            "datacenter": {"NRT": {"a.com": {"responses": 1}}}}"#,
+    )
+    .unwrap();
+
+    let lines = Plaintext::new()
+        .with_prefix("")
+        .with_filter(filter(&["origin_responses"], &["origin_responses"]))
+        .origins("www", &origins);
+    assert_eq!(lines, "www.pops.NRT.origins.a_com.responses 1 2\n");
+
+    let lines = Plaintext::new()
+        .with_filter(filter(&["origin_responses"], &[]))
+        .origins("www", &origins);
+    assert_eq!(
+        lines,
+        "fastly_rt.www.origins.a_com.responses 1 2\n\
+         fastly_rt.www.origins.b.responses 2 2\n"
+    );
+}
+
+#[test]
+fn writes_over_tcp() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let receiver = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut received = String::new();
+        stream.read_to_string(&mut received).unwrap();
+        received
+    });
+
+    let lines = Plaintext::new().with_filter(filter(&["requests"], &[]));
+    {
+        let mut writer = GraphiteWriter::connect(addr, lines).unwrap();
+        writer.write_service("www", &service_second()).unwrap();
+        writer.write("custom.metric 1 2\n").unwrap();
+    }
+
+    assert_eq!(
+        receiver.join().unwrap(),
+        "fastly_rt.www.requests 3 1700000000\ncustom.metric 1 2\n"
+    );
+}
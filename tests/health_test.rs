@@ -0,0 +1,218 @@
+use fastly_rt::health::{HealthConfig, HealthState, HealthWeights, OriginHealth};
+use fastly_rt::origin::{OriginDataInSecond, OriginStats};
+use fastly_rt::service::ServiceDataInSecond;
+use fastly_rt::timeseries::Scope;
+
+fn stats(responses: u64, status_5xx: u64, status_503: u64) -> OriginStats {
+    OriginStats {
+        responses,
+        status_5xx,
+        status_503,
+        ..Default::default()
+    }
+}
+
+/// Origins `s3` and `gcs` in NRT and LHR, `s3` in NRT returning `nrt_5xx` 503s of 100 responses
+fn origin_second(recorded: u64, nrt_5xx: u64) -> OriginDataInSecond {
+    let mut data = OriginDataInSecond {
+        recorded,
+        ..Default::default()
+    };
+    for pop_name in ["NRT", "LHR"] {
+        for origin_name in ["s3", "gcs"] {
+            let status_5xx = if (pop_name, origin_name) == ("NRT", "s3") {
+                nrt_5xx
+            } else {
+                0
+            };
+            data.datacenter
+                .entry(pop_name.to_string())
+                .or_default()
+                .insert(origin_name.to_string(), stats(100, status_5xx, status_5xx));
+        }
+    }
+    for origin_name in ["s3", "gcs"] {
+        let status_5xx = if origin_name == "s3" { nrt_5xx } else { 0 };
+        data.aggregated
+            .insert(origin_name.to_string(), stats(200, status_5xx, status_5xx));
+    }
+    data
+}
+
+#[test]
+fn weighted_score() {
+    let config = HealthConfig::default();
+    let mut origin = OriginStats {
+        responses: 100,
+        status_5xx: 5,
+        status_4xx: 10,
+        status_503: 1,
+        status_504: 1,
+        ..Default::default()
+    };
+
+    let score = config.score(&origin, None).unwrap();
+    assert!((score.score - 0.575).abs() < 1e-9);
+    assert_eq!(score.server_errors, 0.05);
+    assert_eq!(score.client_errors, 0.1);
+    assert_eq!(score.unavailable, 2);
+    assert_eq!(config.state(score.score), HealthState::Failing);
+
+    // Latency half way to its limit
+    let score = config.score(&origin, Some(2750.0)).unwrap();
+    assert!((score.score - 0.56).abs() < 1e-9);
+    assert_eq!(score.latency_ms, Some(2750.0));
+
+    // Only 5xx counts
+    let config = HealthConfig {
+        weights: HealthWeights {
+            server_errors: 1.0,
+            client_errors: 0.0,
+            unavailable: 0.0,
+            latency: 0.0,
+        },
+        ..Default::default()
+    };
+    let score = config.score(&origin, Some(60000.0)).unwrap();
+    assert!((score.score - 0.5).abs() < 1e-9);
+    assert_eq!(config.state(score.score), HealthState::Failing);
+    assert_eq!(config.state(0.7), HealthState::Degraded);
+    assert_eq!(config.state(0.95), HealthState::Healthy);
+
+    origin.responses = 0;
+    assert!(config.score(&origin, None).is_none());
+}
+
+#[test]
+fn debounced_states() {
+    let mut health = OriginHealth::new(HealthConfig::default());
+    let s3 = Scope::origin("s3");
+    let mut events = Vec::new();
+
+    // One bad second, then three
+    for (recorded, nrt_5xx) in [(1, 0), (2, 50), (3, 0), (4, 50), (5, 50), (6, 50)] {
+        events.extend(health.observe("www", &origin_second(recorded, nrt_5xx)));
+    }
+    let aggregated: Vec<_> = events.iter().filter(|event| event.scope == s3).collect();
+    assert_eq!(aggregated.len(), 1);
+    assert_eq!(aggregated[0].recorded, 6);
+    assert_eq!(aggregated[0].previous, HealthState::Healthy);
+    assert_eq!(aggregated[0].state, HealthState::Failing);
+    assert_eq!(
+        aggregated[0].to_string(),
+        format!(
+            "origin s3 of www failing after healthy, score {:.2}",
+            aggregated[0].score.score
+        )
+    );
+    assert_eq!(health.state("www", &s3), HealthState::Failing);
+    assert_eq!(
+        health.state("www", &Scope::origin("s3").in_pop("NRT")),
+        HealthState::Failing
+    );
+    assert_eq!(
+        health.state("www", &Scope::origin("gcs")),
+        HealthState::Healthy
+    );
+
+    // Seconds already scored and seconds without responses are ignored
+    assert!(health.observe("www", &origin_second(6, 0)).is_empty());
+    let mut empty = origin_second(7, 0);
+    empty.aggregated.clear();
+    empty.datacenter.clear();
+    assert!(health.observe("www", &empty).is_empty());
+
+    let mut events = Vec::new();
+    for recorded in 8..=10 {
+        events.extend(health.observe("www", &origin_second(recorded, 0)));
+    }
+    let states: Vec<_> = events
+        .iter()
+        .map(|event| (event.scope.to_string(), event.state, event.recorded))
+        .collect();
+    assert_eq!(
+        states,
+        vec![
+            ("origin s3".to_string(), HealthState::Healthy, 10),
+            ("origin s3 in pop NRT".to_string(), HealthState::Healthy, 10)
+        ]
+    );
+}
+
+#[test]
+fn summary_of_worst_origins() {
+    let mut health = OriginHealth::new(HealthConfig {
+        debounce: 1,
+        ..Default::default()
+    });
+
+    // s3 fails in NRT only, degrading its aggregate
+    health.observe("www", &origin_second(1, 6));
+    let mut api = OriginDataInSecond {
+        recorded: 1,
+        ..Default::default()
+    };
+    api.aggregated
+        .insert("backend".to_string(), stats(100, 100, 100));
+    health.observe("api", &api);
+
+    let summary = health.summary(10);
+    assert_eq!(summary.len(), 2);
+    assert_eq!(
+        (summary[0].service.as_str(), summary[0].origin.as_str()),
+        ("api", "backend")
+    );
+    assert_eq!(summary[0].state, HealthState::Failing);
+    assert!((summary[0].score - 0.125).abs() < 1e-9);
+    assert!(summary[0].pops.is_empty());
+
+    assert_eq!(summary[1].origin, "s3");
+    assert_eq!(summary[1].state, HealthState::Degraded);
+    assert_eq!(summary[1].pops.len(), 1);
+    assert_eq!(summary[1].pops[0].0, "NRT");
+    assert_eq!(summary[1].pops[0].1, HealthState::Failing);
+
+    assert_eq!(health.summary(1).len(), 1);
+    assert!(health
+        .score("www", &Scope::origin("gcs").in_pop("LHR"))
+        .is_some());
+}
+
+#[test]
+fn latency_of_service_misses() {
+    let mut health = OriginHealth::new(HealthConfig {
+        debounce: 1,
+        ..Default::default()
+    });
+    let mut service = ServiceDataInSecond {
+        recorded: 1,
+        ..Default::default()
+    };
+    service.aggregated.miss_histogram.add(100, 10);
+    service
+        .datacenter
+        .entry("NRT".to_string())
+        .or_default()
+        .miss_histogram
+        .add(9000, 10);
+
+    let events = health.observe_with_latency("www", &origin_second(1, 0), &service);
+    assert_eq!(events.len(), 2);
+    assert!(events
+        .iter()
+        .all(|event| event.scope.pop.as_deref() == Some("NRT")));
+
+    let slow = health
+        .score("www", &Scope::origin("s3").in_pop("NRT"))
+        .unwrap();
+    assert!(slow.latency_ms.unwrap() > 8900.0);
+    assert!((slow.score - 0.8).abs() < 1e-9);
+    let fast = health.score("www", &Scope::origin("s3")).unwrap();
+    assert!(fast.latency_ms.unwrap() <= 100.0);
+    assert_eq!(fast.score, 1.0);
+    assert!(health
+        .score("www", &Scope::origin("s3").in_pop("LHR"))
+        .unwrap()
+        .latency_ms
+        .is_none());
+}
@@ -0,0 +1,109 @@
+use fastly_rt::histogram::LatencyHistogram;
+use fastly_rt::service::ServiceStats;
+use std::collections::HashMap;
+
+fn histogram(buckets: &[(&str, u64)]) -> LatencyHistogram {
+    let raw: HashMap<String, u64> = buckets
+        .iter()
+        .map(|(label, count)| (label.to_string(), *count))
+        .collect();
+    LatencyHistogram::parse(&raw).unwrap()
+}
+
+#[test]
+fn parse_from_service_stats() {
+    let stats: ServiceStats =
+        serde_json::from_str(r#"{"miss": 3, "miss_histogram": {"10": 1, "20": 2}}"#).unwrap();
+
+    assert_eq!(stats.miss_histogram.total(), 3);
+    assert_eq!(
+        stats.miss_histogram.buckets().collect::<Vec<_>>(),
+        vec![(10, 1), (20, 2)]
+    );
+
+    let json = serde_json::to_value(&stats).unwrap();
+    assert_eq!(json["miss_histogram"]["20"], 2);
+
+    // Unexpected labels are skipped, unless parsed strictly
+    let stats: ServiceStats =
+        serde_json::from_str(r#"{"miss_histogram": {"slow": 1, "10": 2}}"#).unwrap();
+    assert_eq!(
+        stats.miss_histogram.buckets().collect::<Vec<_>>(),
+        vec![(10, 2)]
+    );
+    let raw = HashMap::from([("slow".to_string(), 1)]);
+    assert!(LatencyHistogram::parse(&raw).is_err());
+}
+
+#[test]
+fn merge_and_percentile() {
+    let mut merged = histogram(&[("10", 50), ("20", 40)]);
+    merged.merge(&histogram(&[("20", 0), ("100", 9), ("1000", 1)]));
+
+    assert_eq!(merged.total(), 100);
+    assert_eq!(merged.p50(), Some(10.0));
+    assert_eq!(merged.p90(), Some(20.0));
+    assert_eq!(merged.p99(), Some(100.0));
+    assert_eq!(merged.percentile(1.0), Some(1000.0));
+
+    let summed: LatencyHistogram = [histogram(&[("10", 1)]), histogram(&[("10", 1)])]
+        .iter()
+        .sum();
+    assert_eq!(summed.total(), 2);
+
+    assert_eq!(LatencyHistogram::new().p50(), None);
+}
+
+#[test]
+fn wider_buckets() {
+    let slow = histogram(&[("1000", 10), ("1050", 10), ("3000", 70), ("3500", 10)]);
+
+    // Wider as latency grows, without overlapping the bucket before
+    assert_eq!(
+        slow.bounds().collect::<Vec<_>>(),
+        vec![
+            (950, 1000, 10),
+            (1000, 1050, 10),
+            (2900, 3000, 70),
+            (3000, 3500, 10)
+        ]
+    );
+    assert_eq!(slow.percentile(0.15), Some(1025.0));
+    assert!((slow.percentile(0.55).unwrap() - 2950.0).abs() < 1e-9);
+    assert_eq!(slow.p90(), Some(3000.0));
+    assert!((slow.p99().unwrap() - 3450.0).abs() < 1e-9);
+    assert_eq!(
+        slow.sum_ms(),
+        975.0 * 10.0 + 1025.0 * 10.0 + 2950.0 * 70.0 + 3250.0 * 10.0
+    );
+
+    // A lone bucket does not stretch down to 0
+    let lone = histogram(&[("9000", 10)]);
+    assert_eq!(lone.bounds().next(), Some((8500, 9000, 10)));
+    assert_eq!(lone.p50(), Some(8750.0));
+}
+
+#[test]
+fn upper_bound_of_latency() {
+    let bounds: Vec<u64> = [
+        0.2, 3.0, 12.0, 410.0, 1220.0, 3790.0, 10_000.0, 25_000.0, 90_000.0,
+    ]
+    .into_iter()
+    .map(LatencyHistogram::upper_bound)
+    .collect();
+
+    assert_eq!(
+        bounds,
+        vec![1, 3, 20, 450, 1300, 4000, 10_000, 25_000, 60_000]
+    );
+}
+
+#[test]
+fn cumulative_buckets() {
+    let buckets = histogram(&[("10", 1), ("20", 2), ("60000", 3)]).cumulative_buckets();
+
+    assert_eq!(
+        buckets,
+        vec![(0.01, 1), (0.02, 3), (60.0, 6), (f64::INFINITY, 6)]
+    );
+}
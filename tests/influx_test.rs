@@ -0,0 +1,113 @@
+mod common;
+
+use common::StandIn;
+use fastly_rt::influx::{InfluxWriter, LineProtocol};
+use fastly_rt::metric::MetricFilter;
+use fastly_rt::origin::OriginDataInSecond;
+use fastly_rt::service::ServiceDataInSecond;
+use std::time::Duration;
+
+fn service_second() -> ServiceDataInSecond {
+    serde_json::from_str(
+        r#"{
+            "recorded": 1700000000,
+            "aggregated": {"requests": 3, "hits_time": 0.5, "miss_histogram": {"10": 1, "30": 2}},
+            "datacenter": {"NRT": {"requests": 1}, "LHR": {"requests": 2}}
+        }"#,
+    )
+    .unwrap()
+}
+
+fn filter(allow: &[&str], pop_allow: &[&str]) -> MetricFilter {
+    MetricFilter {
+        allow: allow.iter().map(|name| name.to_string()).collect(),
+        pop_allow: pop_allow.iter().map(|name| name.to_string()).collect(),
+        ..MetricFilter::default()
+    }
+}
+
+#[test]
+fn service_lines() {
+    let lines = LineProtocol::new().service("www", &service_second());
+    let lines: Vec<&str> = lines.lines().collect();
+    assert_eq!(lines.len(), 1);
+
+    let line = lines[0];
+    assert!(line.starts_with("fastly_rt,service=www "));
+    assert!(line.ends_with(" 1700000000000000000"));
+    assert!(line.contains(",requests=3,"));
+    assert!(line.contains(",hits_time=0.5,"));
+    assert!(line.contains(",miss_latency_p50="));
+    assert!(line.contains(",miss_latency_p99="));
+}
+
+#[test]
+fn lines_by_pop_and_escaping() {
+    let lines = LineProtocol::new()
+        .with_measurement("cdn stats")
+        .with_tag("env", "prod,eu")
+        .with_filter(filter(&["requests", "status_5xx"], &["requests"]))
+        .service("my service", &service_second());
+
+    assert_eq!(
+        lines,
+        "cdn\\ stats,env=prod\\,eu,service=my\\ service status_5xx=0 1700000000000000000\n\
+         cdn\\ stats,env=prod\\,eu,service=my\\ service,pop=LHR requests=2 1700000000000000000\n\
+         cdn\\ stats,env=prod\\,eu,service=my\\ service,pop=NRT requests=1 1700000000000000000\n"
+    );
+}
+
+#[test]
+fn origin_lines() {
+    let origins: OriginDataInSecond = serde_json::from_str(
+        r#"{"recorded": 2, "aggregated": {"b": {"responses": 2}, "a": {"responses": 1}},
+            "datacenter": {"NRT": {"a": {"responses": 1}}}}"#,
+    )
+    .unwrap();
+
+    let lines = LineProtocol::new()
+        .with_filter(filter(&["origin_responses"], &[]))
+        .origins("www", &origins);
+    assert_eq!(
+        lines,
+        "fastly_rt_origin,service=www,origin=a responses=1 2000000000\n\
+         fastly_rt_origin,service=www,origin=b responses=2 2000000000\n"
+    );
+}
+
+#[tokio::test]
+async fn writes_over_http() {
+    let influx = StandIn::start(204);
+    let url = format!("{}/api/v2/write?org=o&bucket=b&precision=ns", influx.url());
+    let writer = InfluxWriter::new(&url, LineProtocol::new())
+        .unwrap()
+        .with_token("secret");
+
+    writer
+        .write_service("www", &[service_second(), service_second()])
+        .await
+        .unwrap();
+
+    let received = influx
+        .requests
+        .recv_timeout(Duration::from_secs(5))
+        .unwrap();
+    assert_eq!(received.method, "POST");
+    assert_eq!(received.path, "/api/v2/write?org=o&bucket=b&precision=ns");
+    assert!(received
+        .headers
+        .contains(&("authorization".to_string(), "Token secret".to_string())));
+    let body = String::from_utf8(received.body).unwrap();
+    assert_eq!(body.lines().count(), 2);
+
+    writer.write_service("www", &[]).await.unwrap();
+    assert!(influx
+        .requests
+        .recv_timeout(Duration::from_millis(200))
+        .is_err());
+
+    let failing = StandIn::start(400);
+    let writer = InfluxWriter::new(&failing.url(), LineProtocol::new()).unwrap();
+    let error = writer.write_service("www", &[service_second()]).await;
+    assert!(error.unwrap_err().to_string().contains("400"));
+}
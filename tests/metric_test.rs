@@ -0,0 +1,109 @@
+use fastly_rt::metric::{matches_pattern, MetricKind, MetricUnit, Metrics};
+use fastly_rt::origin::OriginStats;
+use fastly_rt::service::ServiceStats;
+
+#[test]
+fn catalogs_are_sorted() {
+    for names in [
+        ServiceStats::METRICS
+            .iter()
+            .map(|m| m.name)
+            .collect::<Vec<_>>(),
+        OriginStats::METRICS
+            .iter()
+            .map(|m| m.name)
+            .collect::<Vec<_>>(),
+    ] {
+        assert!(names.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+}
+
+#[test]
+fn service_metrics() {
+    let stats: ServiceStats = serde_json::from_str(
+        r#"{"requests": 7, "hits_time": 0.5, "compute_execution_time_ms": 12.5, "resp_body_bytes": 100}"#,
+    )
+    .unwrap();
+
+    assert_eq!(stats.metric("requests"), Some(7.0));
+    assert_eq!(stats.metric("hits_time"), Some(0.5));
+    assert_eq!(stats.metric("miss_histogram"), None);
+    assert_eq!(stats.metric("unknown"), None);
+
+    assert_eq!(
+        ServiceStats::metric_info("requests").unwrap().unit,
+        MetricUnit::Count
+    );
+    assert_eq!(
+        ServiceStats::metric_info("hits_time").unwrap().unit,
+        MetricUnit::Seconds
+    );
+    assert_eq!(
+        ServiceStats::metric_info("compute_execution_time_ms")
+            .unwrap()
+            .unit,
+        MetricUnit::Milliseconds
+    );
+    assert_eq!(
+        ServiceStats::metric_info("body_size").unwrap().unit,
+        MetricUnit::Bytes
+    );
+
+    let total: f64 = stats.metrics().map(|(_, value)| value).sum();
+    assert_eq!(total, 7.0 + 0.5 + 12.5 + 100.0);
+}
+
+#[test]
+fn gauges_are_not_added_up() {
+    assert_eq!(
+        ServiceStats::metric_info("compute_ram_used").unwrap().kind,
+        MetricKind::Gauge
+    );
+    assert_eq!(
+        ServiceStats::metric_info("requests").unwrap().kind,
+        MetricKind::Counter
+    );
+
+    let mut stats = ServiceStats {
+        requests: 1,
+        compute_ram_used: 300,
+        ..Default::default()
+    };
+    stats.merge(&ServiceStats {
+        requests: 2,
+        compute_ram_used: 500,
+        ..Default::default()
+    });
+    stats.merge(&ServiceStats {
+        requests: 3,
+        compute_ram_used: 100,
+        ..Default::default()
+    });
+    assert_eq!(stats.requests, 6);
+    assert_eq!(stats.compute_ram_used, 500);
+}
+
+#[test]
+fn origin_metrics() {
+    let stats = OriginStats {
+        responses: 3,
+        status_5xx: 1,
+        ..OriginStats::default()
+    };
+
+    assert_eq!(stats.metric("responses"), Some(3.0));
+    assert_eq!(stats.metric("status_5xx"), Some(1.0));
+    assert_eq!(stats.metrics().count(), OriginStats::METRICS.len());
+}
+
+#[test]
+fn patterns() {
+    assert!(matches_pattern("requests", "requests"));
+    assert!(!matches_pattern("requests", "edge_requests"));
+    assert!(matches_pattern("*", "anything"));
+    assert!(matches_pattern("status_*", "status_5xx"));
+    assert!(matches_pattern("*_bytes", "resp_body_bytes"));
+    assert!(matches_pattern("*body*", "resp_body_bytes"));
+    assert!(matches_pattern("edge_*_bytes", "edge_hit_resp_body_bytes"));
+    assert!(!matches_pattern("edge_*_bytes", "edge_requests"));
+}
@@ -0,0 +1,81 @@
+use fastly_rt::mock::{MockCall, MockSource};
+use fastly_rt::realtime::{RealtimeClient, RealtimeSource, RtResponse};
+use fastly_rt::service::ServiceDataInSecond;
+use std::time::{Duration, Instant};
+
+fn response(timestamp: u64, requests: &[u64]) -> RtResponse<ServiceDataInSecond> {
+    let data = requests
+        .iter()
+        .enumerate()
+        .map(|(index, requests)| {
+            let mut data = ServiceDataInSecond {
+                recorded: timestamp + index as u64,
+                ..Default::default()
+            };
+            data.aggregated.requests = *requests;
+            data
+        })
+        .collect();
+
+    RtResponse {
+        aggregate_delay: 0,
+        data,
+        timestamp,
+    }
+}
+
+async fn total_requests<S: RealtimeSource<Data = ServiceDataInSecond>>(
+    source: &mut S,
+    polls: usize,
+) -> (u64, usize) {
+    let mut total = 0;
+    let mut errors = 0;
+
+    for _ in 0..polls {
+        match source.next_stats().await {
+            Ok(response) => {
+                total += response
+                    .data
+                    .iter()
+                    .map(|d| d.aggregated.requests)
+                    .sum::<u64>()
+            }
+            Err(_) => errors += 1,
+        }
+    }
+
+    (total, errors)
+}
+
+#[tokio::test]
+async fn scripted_responses_and_errors() {
+    let mut source = MockSource::new()
+        .respond(response(10, &[1, 2]))
+        .fail("503 Service Unavailable")
+        .respond(response(11, &[4]));
+
+    assert_eq!(source.remaining(), 3);
+    assert_eq!(total_requests(&mut source, 4).await, (7, 2));
+    assert_eq!(source.remaining(), 0);
+    assert_eq!(source.calls(), vec![MockCall::GetStatsConsecutive; 4]);
+
+    source.reset_stats_consecutive();
+    assert!(source.get_stats_max(5).await.is_err());
+    assert_eq!(
+        source.calls()[4..],
+        [MockCall::ResetStatsConsecutive, MockCall::GetStatsMax(5)]
+    );
+}
+
+#[tokio::test]
+async fn scripted_delay() {
+    let source = MockSource::new()
+        .delay(Duration::from_millis(50))
+        .respond(response(10, &[1]));
+
+    let start = Instant::now();
+    let response = source.get_stats_120s().await.unwrap();
+
+    assert!(start.elapsed() >= Duration::from_millis(50));
+    assert_eq!(response.timestamp, 10);
+}
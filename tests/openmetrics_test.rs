@@ -0,0 +1,109 @@
+use fastly_rt::openmetrics::{Encoder, Format};
+use fastly_rt::origin::OriginDataInSecond;
+use fastly_rt::service::ServiceDataInSecond;
+
+fn service_second(recorded: u64) -> ServiceDataInSecond {
+    serde_json::from_str(&format!(
+        r#"{{
+            "recorded": {},
+            "aggregated": {{"requests": 3, "hits_time": 0.25, "compute_execution_time_ms": 1500.0,
+                            "miss_histogram": {{"10": 1, "30": 2}}}},
+            "datacenter": {{
+                "NRT": {{"requests": 1, "miss_histogram": {{"10": 1}}}},
+                "LHR": {{"requests": 2, "miss_histogram": {{"30": 2}}}}
+            }}
+        }}"#,
+        recorded
+    ))
+    .unwrap()
+}
+
+#[test]
+fn openmetrics_service() {
+    let text = Encoder::new()
+        .with_label("service", "www")
+        .encode_service(&service_second(1700000000));
+
+    assert!(text.contains(
+        "# TYPE fastly_rt_requests gauge\n# HELP fastly_rt_requests Number of requests processed\n"
+    ));
+    assert!(text.contains("fastly_rt_requests{service=\"www\"} 3 1700000000\n"));
+
+    assert!(text.contains("# UNIT fastly_rt_hits_time_seconds seconds\n"));
+    assert!(text.contains("fastly_rt_hits_time_seconds{service=\"www\"} 0.25 1700000000\n"));
+    assert!(
+        text.contains("fastly_rt_compute_execution_time_seconds{service=\"www\"} 1.5 1700000000\n")
+    );
+    assert!(text.contains("# UNIT fastly_rt_resp_body_bytes bytes\n"));
+    assert!(!text.contains("# UNIT fastly_rt_requests"));
+    assert!(text.contains("category status codes delivered\n"));
+
+    assert!(text.contains("# TYPE fastly_rt_miss_latency_seconds histogram\n"));
+    assert!(text.contains(
+        "fastly_rt_miss_latency_seconds_bucket{service=\"www\",le=\"0.01\"} 1 1700000000\n"
+    ));
+    assert!(text.contains(
+        "fastly_rt_miss_latency_seconds_bucket{service=\"www\",le=\"+Inf\"} 3 1700000000\n"
+    ));
+    assert!(text.contains("fastly_rt_miss_latency_seconds_count{service=\"www\"} 3 1700000000\n"));
+    assert!(
+        text.contains("fastly_rt_miss_latency_seconds_sum{service=\"www\"} 0.0595 1700000000\n")
+    );
+
+    assert!(text.ends_with("# EOF\n"));
+    assert_eq!(text.matches("# EOF").count(), 1);
+}
+
+#[test]
+fn prometheus_by_pop() {
+    let encoder = Encoder::new()
+        .with_format(Format::Prometheus)
+        .with_prefix("cdn")
+        .by_pop(true);
+    let text = encoder.encode_service(&service_second(10));
+
+    assert!(text
+        .contains("# HELP cdn_requests Number of requests processed\n# TYPE cdn_requests gauge\n"));
+    assert!(!text.contains("# UNIT"));
+    assert!(!text.contains("# EOF"));
+    let lhr = text.find("cdn_requests{pop=\"LHR\"} 2 10000\n").unwrap();
+    let nrt = text.find("cdn_requests{pop=\"NRT\"} 1 10000\n").unwrap();
+    assert!(lhr < nrt);
+    assert_eq!(
+        encoder.format().content_type(),
+        "text/plain; version=0.0.4; charset=utf-8"
+    );
+}
+
+#[test]
+fn window_and_origins() {
+    let mut window = service_second(1);
+    window.merge(&service_second(2));
+    assert_eq!(window.recorded, 2);
+    assert_eq!(window.datacenter["NRT"].requests, 2);
+
+    let text = Encoder::new()
+        .with_timestamps(false)
+        .encode_service(&window);
+    assert!(text.contains("fastly_rt_requests 6\n"));
+
+    let origins: OriginDataInSecond = serde_json::from_str(
+        r#"{"recorded": 5, "aggregated": {"b": {"responses": 2}, "a": {"responses": 1}}}"#,
+    )
+    .unwrap();
+    let mut text = String::new();
+    let encoder = Encoder::new();
+    encoder.write_service(&mut text, &window);
+    encoder.write_origins(&mut text, &origins);
+    encoder.finish(&mut text);
+
+    let a = text
+        .find("fastly_rt_origin_responses{origin=\"a\"} 1 5\n")
+        .unwrap();
+    let b = text
+        .find("fastly_rt_origin_responses{origin=\"b\"} 2 5\n")
+        .unwrap();
+    assert!(a < b);
+    assert!(text.contains("# TYPE fastly_rt_origin_status_5xx gauge\n"));
+    assert!(text.ends_with("# EOF\n"));
+}
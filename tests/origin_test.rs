@@ -1,21 +1,26 @@
 use chrono::Utc;
+use fastly_rt::fake::{FakeConfig, FakeServer};
 use fastly_rt::origin::OriginClient;
 use std::env;
 use std::{thread, time};
 
-fn load_api_key() -> String {
-    env::var("KEY").expect("env KEY not set")
-}
-
-fn load_sid() -> String {
-    env::var("SID").expect("env SID not set")
+/// Client of Fastly when env KEY and SID are set, of a local fake server otherwise
+async fn new_client() -> (OriginClient, Option<FakeServer>) {
+    match (env::var("KEY"), env::var("SID")) {
+        (Ok(api_key), Ok(sid)) => (OriginClient::new(&api_key, &sid).unwrap(), None),
+        _ => {
+            let config = FakeConfig::default();
+            let server = FakeServer::start(config.clone()).await.unwrap();
+            let rt =
+                OriginClient::with_base_url(&config.api_key, "fake", &server.base_url()).unwrap();
+            (rt, Some(server))
+        }
+    }
 }
 
 #[tokio::test]
 async fn get_consecutive_stats() {
-    let api_key = load_api_key();
-    let sid = load_sid();
-    let mut rt = OriginClient::new(&api_key, &sid).unwrap();
+    let (mut rt, _server) = new_client().await;
 
     let mut rt_data = rt.get_stats_consecutive().await.unwrap();
     let timestamp = rt_data.timestamp;
@@ -31,14 +36,12 @@ async fn get_consecutive_stats() {
 
     rt.reset_stats_consecutive();
     rt_data = rt.get_stats_consecutive().await.unwrap();
-    assert!(rt_data.data.len() == 0 || rt_data.data.len() == 1)
+    assert!(rt_data.data.is_empty() || rt_data.data.len() == 1)
 }
 
 #[tokio::test]
 async fn get_stats_from() {
-    let api_key = load_api_key();
-    let sid = load_sid();
-    let mut rt = OriginClient::new(&api_key, &sid).unwrap();
+    let (mut rt, _server) = new_client().await;
 
     let timestamp = (Utc::now().timestamp() - 5) as u64;
 
@@ -50,9 +53,7 @@ async fn get_stats_from() {
 
 #[tokio::test]
 async fn get_stats_120s() {
-    let api_key = load_api_key();
-    let sid = load_sid();
-    let rt = OriginClient::new(&api_key, &sid).unwrap();
+    let (rt, _server) = new_client().await;
 
     let rt_data = rt.get_stats_120s().await.unwrap();
     let entry_count = rt_data.data.len();
@@ -63,9 +64,7 @@ async fn get_stats_120s() {
 
 #[tokio::test]
 async fn get_stats_max() {
-    let api_key = load_api_key();
-    let sid = load_sid();
-    let rt = OriginClient::new(&api_key, &sid).unwrap();
+    let (rt, _server) = new_client().await;
 
     let mut rt_data = rt.get_stats_max(3).await.unwrap();
     let mut entry_count = rt_data.data.len();
@@ -0,0 +1,163 @@
+mod common;
+
+use common::StandIn;
+use fastly_rt::origin::OriginResponse;
+use fastly_rt::otlp::{origin_request, service_request, OtlpConfig, OtlpExporter, Resource};
+use fastly_rt::service::ServiceResponse;
+use serde_json::Value;
+use std::time::Duration;
+
+fn service_response() -> ServiceResponse {
+    serde_json::from_str(
+        r#"{
+            "AggregateDelay": 3,
+            "Timestamp": 12,
+            "Data": [
+                {"recorded": 10, "aggregated": {"requests": 3, "compute_ram_used": 1024,
+                                                "miss_histogram": {"10": 1, "30": 2}},
+                 "datacenter": {"NRT": {"requests": 1}, "LHR": {"requests": 2}}},
+                {"recorded": 11, "aggregated": {"requests": 4}}
+            ]
+        }"#,
+    )
+    .unwrap()
+}
+
+fn metric<'a>(request: &'a Value, name: &str) -> &'a Value {
+    request["resourceMetrics"][0]["scopeMetrics"][0]["metrics"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|metric| metric["name"] == name)
+        .unwrap()
+}
+
+#[test]
+fn service_data_points() {
+    let resource = Resource::service("sid")
+        .with_name("www")
+        .with_attribute("env", "prod");
+    let request = service_request(&resource, &service_response(), false);
+
+    let attributes = &request["resourceMetrics"][0]["resource"]["attributes"];
+    assert_eq!(attributes[0]["key"], "fastly.service.id");
+    assert_eq!(attributes[0]["value"]["stringValue"], "sid");
+    assert_eq!(attributes[1]["value"]["stringValue"], "www");
+    assert_eq!(attributes[2]["key"], "env");
+
+    let requests = metric(&request, "fastly.rt.requests");
+    assert_eq!(requests["unit"], "1");
+    assert_eq!(requests["sum"]["aggregationTemporality"], 1);
+    assert_eq!(requests["sum"]["isMonotonic"], true);
+    let points = requests["sum"]["dataPoints"].as_array().unwrap();
+    assert_eq!(points.len(), 2);
+    assert_eq!(points[0]["asDouble"], 3.0);
+    assert_eq!(points[0]["startTimeUnixNano"], "10000000000");
+    assert_eq!(points[0]["timeUnixNano"], "11000000000");
+    assert_eq!(points[1]["asDouble"], 4.0);
+
+    assert_eq!(metric(&request, "fastly.rt.resp_body_bytes")["unit"], "By");
+
+    let ram = metric(&request, "fastly.rt.compute_ram_used");
+    assert!(ram.get("sum").is_none());
+    assert_eq!(ram["gauge"]["dataPoints"][0]["asDouble"], 1024.0);
+
+    let histogram = &metric(&request, "fastly.rt.miss_latency")["histogram"];
+    let point = &histogram["dataPoints"][0];
+    assert_eq!(point["count"], "3");
+    assert_eq!(point["explicitBounds"], serde_json::json!([0.01, 0.03]));
+    assert_eq!(point["bucketCounts"], serde_json::json!(["1", "2", "0"]));
+
+    let delay = &metric(&request, "fastly.rt.aggregate_delay")["gauge"]["dataPoints"][0];
+    assert_eq!(delay["asInt"], "3");
+}
+
+#[test]
+fn pop_and_origin_attributes() {
+    let request = service_request(&Resource::service("sid"), &service_response(), true);
+    let points = metric(&request, "fastly.rt.requests")["sum"]["dataPoints"]
+        .as_array()
+        .unwrap()
+        .clone();
+    assert_eq!(points.len(), 2);
+    assert_eq!(points[0]["attributes"][0]["key"], "fastly.pop");
+    assert_eq!(points[0]["attributes"][0]["value"]["stringValue"], "LHR");
+    assert_eq!(points[0]["asDouble"], 2.0);
+
+    let origins: OriginResponse = serde_json::from_str(
+        r#"{"AggregateDelay": 3, "Timestamp": 11, "Data": [{"recorded": 10,
+            "aggregated": {"b": {"responses": 2}, "a": {"responses": 1}},
+            "datacenter": {"NRT": {"a": {"responses": 1}}}}]}"#,
+    )
+    .unwrap();
+
+    let request = origin_request(&Resource::service("sid"), &origins, false);
+    let points = &metric(&request, "fastly.rt.origin.responses")["sum"]["dataPoints"];
+    assert_eq!(points[0]["attributes"][0]["key"], "fastly.origin");
+    assert_eq!(points[0]["attributes"][0]["value"]["stringValue"], "a");
+    assert_eq!(points[1]["asDouble"], 2.0);
+
+    let request = origin_request(&Resource::service("sid"), &origins, true);
+    let point = &metric(&request, "fastly.rt.origin.responses")["sum"]["dataPoints"][0];
+    assert_eq!(point["attributes"][0]["value"]["stringValue"], "NRT");
+    assert_eq!(point["attributes"][1]["value"]["stringValue"], "a");
+}
+
+#[tokio::test]
+async fn pushes_to_collector() {
+    let collector = StandIn::start(200);
+    let config = OtlpConfig {
+        endpoint: collector.url(),
+        headers: vec![("x-token".to_string(), "secret".to_string())],
+        ..OtlpConfig::default()
+    };
+    let otlp = OtlpExporter::new(config).unwrap();
+
+    otlp.export_service(&Resource::service("sid"), &service_response())
+        .await
+        .unwrap();
+
+    let received = collector
+        .requests
+        .recv_timeout(Duration::from_secs(5))
+        .unwrap();
+    assert_eq!(received.method, "POST");
+    assert_eq!(received.path, "/v1/metrics");
+    assert!(received
+        .headers
+        .contains(&("x-token".to_string(), "secret".to_string())));
+    assert!(received
+        .headers
+        .contains(&("content-type".to_string(), "application/json".to_string())));
+    let body: Value = serde_json::from_slice(&received.body).unwrap();
+    assert!(body["resourceMetrics"][0]["scopeMetrics"][0]["metrics"].is_array());
+
+    let empty = ServiceResponse {
+        aggregate_delay: 3,
+        data: vec![],
+        timestamp: 1,
+    };
+    otlp.export_service(&Resource::service("sid"), &empty)
+        .await
+        .unwrap();
+    assert!(collector
+        .requests
+        .recv_timeout(Duration::from_millis(200))
+        .is_err());
+}
+
+#[tokio::test]
+async fn collector_errors() {
+    let collector = StandIn::start(503);
+    let config = OtlpConfig {
+        endpoint: collector.url(),
+        ..OtlpConfig::default()
+    };
+    let otlp = OtlpExporter::new(config).unwrap();
+
+    let error = otlp
+        .export_service(&Resource::service("sid"), &service_response())
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("503"));
+}
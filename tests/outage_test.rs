@@ -0,0 +1,213 @@
+use fastly_rt::outage::{OutageConfig, OutageDetector, OutageKind};
+use fastly_rt::service::ServiceDataInSecond;
+use fastly_rt::synth::{Generator, Incident, IncidentKind, SynthConfig};
+
+const START: u64 = 1_700_000_000;
+
+fn second(recorded: u64, pops: &[(&str, u64, u64)]) -> ServiceDataInSecond {
+    let mut data = ServiceDataInSecond {
+        recorded,
+        ..Default::default()
+    };
+    for (pop_name, requests, status_5xx) in pops {
+        let stats = data.datacenter.entry(pop_name.to_string()).or_default();
+        stats.requests = *requests;
+        stats.status_5xx = *status_5xx;
+        data.aggregated.requests += requests;
+        data.aggregated.status_5xx += status_5xx;
+    }
+    data
+}
+
+fn config() -> OutageConfig {
+    OutageConfig {
+        warmup: 60,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn vanished_pop_and_recovery() {
+    let generator = Generator::new(SynthConfig {
+        incidents: vec![Incident {
+            start: START + 600,
+            duration: 60,
+            kind: IncidentKind::PopOutage {
+                pop: "NRT".to_string(),
+            },
+        }],
+        ..Default::default()
+    });
+    let mut detector = OutageDetector::new(OutageConfig::default());
+
+    let mut events = Vec::new();
+    for recorded in START..START + 900 {
+        events.extend(detector.observe("www", &generator.service_second(recorded)));
+    }
+
+    // Missing for 5 seconds, then back for good
+    assert_eq!(events.len(), 2, "{:?}", events);
+    let vanished = &events[0];
+    assert_eq!(vanished.kind, OutageKind::Vanished);
+    assert!(!vanished.recovered);
+    assert_eq!(vanished.recorded, START + 604);
+    assert!(vanished.baseline_share > 0.1 && vanished.baseline_share < 0.3);
+    assert_eq!(
+        vanished
+            .absorbed_by
+            .iter()
+            .map(|(pop_name, _)| pop_name.as_str())
+            .collect::<Vec<&str>>(),
+        vec!["HKG"]
+    );
+    assert!(vanished
+        .to_string()
+        .starts_with("Vanished started of NRT www"));
+
+    let recovered = &events[1];
+    assert_eq!(recovered.kind, OutageKind::Vanished);
+    assert!(recovered.recovered);
+    assert_eq!(recovered.recorded, START + 660);
+    assert!(recovered.absorbed_by.is_empty());
+    assert!(detector.problems("www").is_empty());
+
+    let baseline = detector.baseline("www");
+    assert_eq!(baseline.len(), 5);
+    let total: f64 = baseline.iter().map(|(_, share)| share).sum();
+    assert!((total - 1.0).abs() < 0.01);
+}
+
+#[test]
+fn dropped_share_absorbed_by_other_pops() {
+    let mut detector = OutageDetector::new(config());
+    let mut events = Vec::new();
+    for recorded in START..START + 120 {
+        let nrt = if recorded < START + 100 { 500 } else { 50 };
+        let pops = [("NRT", nrt, 0), ("HKG", 300, 0), ("LHR", 200, 0)];
+        events.extend(detector.observe("www", &second(recorded, &pops)));
+    }
+
+    assert_eq!(events.len(), 1);
+    let dropped = &events[0];
+    assert_eq!(
+        (dropped.pop.as_str(), dropped.kind),
+        ("NRT", OutageKind::Dropped)
+    );
+    assert!(dropped.baseline_share > 0.45 && dropped.baseline_share <= 0.5);
+    assert!(dropped.share < 0.25);
+    let absorbed: Vec<&str> = dropped
+        .absorbed_by
+        .iter()
+        .map(|(pop_name, _)| pop_name.as_str())
+        .collect();
+    assert_eq!(absorbed, vec!["HKG", "LHR"]);
+    assert!(dropped.absorbed_by[0].1 > dropped.absorbed_by[1].1);
+
+    // The baseline of the POP with a problem is not updated
+    assert_eq!(detector.baseline("www")[0], ("NRT", dropped.baseline_share));
+    assert_eq!(detector.problems("www"), vec![("NRT", OutageKind::Dropped)]);
+}
+
+#[test]
+fn error_spike_of_a_pop() {
+    let mut detector = OutageDetector::new(config());
+    let mut events = Vec::new();
+    for recorded in START..START + 120 {
+        let lhr_5xx = if (START + 80..START + 100).contains(&recorded) {
+            40
+        } else {
+            1
+        };
+        // A single error of a POP with little traffic is not a spike
+        let syd_5xx = if recorded % 50 == 25 { 1 } else { 0 };
+        let pops = [("NRT", 500, 1), ("LHR", 200, lhr_5xx), ("SYD", 1, syd_5xx)];
+        events.extend(detector.observe("www", &second(recorded, &pops)));
+    }
+
+    let kinds: Vec<(&str, OutageKind, bool, u64)> = events
+        .iter()
+        .map(|event| {
+            (
+                event.pop.as_str(),
+                event.kind,
+                event.recovered,
+                event.recorded,
+            )
+        })
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            ("LHR", OutageKind::Errors, false, START + 82),
+            ("LHR", OutageKind::Errors, true, START + 107)
+        ]
+    );
+    assert!(events[0].error_ratio >= 0.05);
+    assert!(events[0].absorbed_by.is_empty());
+}
+
+#[test]
+fn warmup_and_late_seconds() {
+    let mut detector = OutageDetector::new(config());
+
+    // Nothing is flagged while learning
+    for recorded in START..START + 30 {
+        let pops = [("NRT", 100, 50), ("LHR", 100, 0)];
+        assert!(detector.observe("www", &second(recorded, &pops)).is_empty());
+    }
+    for recorded in START + 30..START + 70 {
+        assert!(detector
+            .observe(
+                "www",
+                &second(recorded, &[("NRT", 100, 0), ("LHR", 100, 0)])
+            )
+            .is_empty());
+    }
+
+    // Late seconds are ignored, services are learned apart
+    assert!(detector
+        .observe("www", &second(START, &[("LHR", 100, 0)]))
+        .is_empty());
+    assert!(detector
+        .observe("api", &second(START + 70, &[("LHR", 100, 0)]))
+        .is_empty());
+    assert_eq!(detector.baseline("api"), vec![("LHR", 1.0)]);
+    assert!(detector.baseline("other").is_empty());
+}
+
+#[test]
+fn decommissioned_pop_forgotten() {
+    let mut detector = OutageDetector::new(OutageConfig {
+        forget_after: 30,
+        ..config()
+    });
+    let mut events = Vec::new();
+    for recorded in START..START + 200 {
+        let mut pops = vec![("LHR", 100, 0)];
+        if !(START + 100..START + 150).contains(&recorded) {
+            pops.push(("NRT", 100, 0));
+        }
+        events.extend(detector.observe("www", &second(recorded, &pops)));
+        if recorded == START + 120 {
+            assert_eq!(
+                detector.problems("www"),
+                vec![("NRT", OutageKind::Vanished)]
+            );
+        }
+        if recorded == START + 140 {
+            assert!(detector.problems("www").is_empty());
+            assert_eq!(detector.baseline("www")[0].0, "LHR");
+            assert_eq!(detector.baseline("www").len(), 1);
+        }
+    }
+
+    // Coming back, it is learned from scratch rather than recovering
+    assert_eq!(events.len(), 1, "{:?}", events);
+    assert_eq!(events[0].kind, OutageKind::Vanished);
+    assert_eq!(events[0].recorded, START + 104);
+    assert!(detector.problems("www").is_empty());
+    let baseline = detector.baseline("www");
+    assert_eq!(baseline[0].0, "LHR");
+    assert_eq!(baseline[1].0, "NRT");
+    assert!(baseline[1].1 > 0.1);
+}
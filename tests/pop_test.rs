@@ -0,0 +1,44 @@
+use fastly_rt::pop::{Continent, Pop, Region};
+use fastly_rt::service::ServiceDataInSecond;
+
+#[test]
+fn catalog_is_sorted() {
+    let catalog = Pop::catalog();
+
+    assert!(catalog.windows(2).all(|pair| pair[0].code < pair[1].code));
+}
+
+#[test]
+fn known_and_unknown_pop() {
+    let nrt: Pop = "nrt".parse().unwrap();
+    assert_eq!(nrt.code(), "NRT");
+    assert_eq!(nrt.city(), Some("Tokyo"));
+    assert_eq!(nrt.country(), Some("JP"));
+    assert_eq!(nrt.continent(), Continent::Asia);
+    assert_eq!(nrt.region(), Region::Asia);
+
+    let unknown = Pop::new("XYZ");
+    assert!(!unknown.is_known());
+    assert_eq!(unknown.region(), Region::Unknown);
+    assert_eq!(unknown.to_string(), "XYZ");
+    assert_eq!(unknown.distance_km(&nrt), None);
+
+    let distance = nrt.distance_km(&Pop::new("LHR")).unwrap();
+    assert!(distance > 9000.0 && distance < 10000.0);
+}
+
+#[test]
+fn pops_of_service_data() {
+    let data: ServiceDataInSecond = serde_json::from_str(
+        r#"{"recorded": 1, "datacenter": {"NRT": {"requests": 1}, "BOM": {"requests": 2}}}"#,
+    )
+    .unwrap();
+
+    let mut regions: Vec<(Region, u64)> = data
+        .pops()
+        .map(|(pop, stats)| (pop.region(), stats.requests))
+        .collect();
+    regions.sort();
+
+    assert_eq!(regions, vec![(Region::Asia, 1), (Region::India, 2)]);
+}
@@ -0,0 +1,165 @@
+use fastly_rt::origin::{OriginDataInSecond, OriginStats};
+use fastly_rt::ranking::{status_codes, Order, Ranker};
+use fastly_rt::service::ServiceDataInSecond;
+use fastly_rt::timeseries::Scope;
+
+/// NRT, HKG and LHR, LHR erroring and SYD vanishing from second 61
+fn service_seconds() -> Vec<ServiceDataInSecond> {
+    (1..=120)
+        .map(|recorded| {
+            let incident = recorded > 60;
+            let mut data = ServiceDataInSecond {
+                recorded,
+                ..Default::default()
+            };
+            let mut pops = vec![
+                ("NRT", 400, 0),
+                ("HKG", 200, 0),
+                ("LHR", 100, if incident { 20 } else { 1 }),
+            ];
+            if !incident {
+                pops.push(("SYD", 150, 0));
+            }
+            for (pop_name, requests, status_503) in pops {
+                let stats = data.datacenter.entry(pop_name.to_string()).or_default();
+                stats.requests = requests;
+                stats.status_503 = status_503;
+                stats.status_5xx = status_503;
+                stats.status_200 = requests - status_503;
+                data.aggregated.requests += requests;
+                data.aggregated.status_503 += status_503;
+                data.aggregated.status_5xx += status_503;
+                data.aggregated.status_200 += requests - status_503;
+            }
+            data
+        })
+        .collect()
+}
+
+#[test]
+fn pops_by_metric_and_kpi() {
+    let seconds = service_seconds();
+
+    let ranking = Ranker::<ServiceDataInSecond>::new("requests")
+        .unwrap()
+        .window(60)
+        .pops(&seconds);
+    assert_eq!((ranking.start, ranking.end), (61, 120));
+    assert_eq!(ranking.total, 42000.0);
+    let names: Vec<&str> = ranking
+        .entries
+        .iter()
+        .map(|entry| entry.name.as_str())
+        .collect();
+    assert_eq!(names, vec!["NRT", "HKG", "LHR", "SYD"]);
+    assert_eq!(ranking.entries[0].value, 24000.0);
+    assert!((ranking.entries[0].share - 24000.0 / 42000.0).abs() < 1e-9);
+    assert_eq!(ranking.entries[0].change(), Some(0.0));
+    assert_eq!(ranking.entries[3].value, 0.0);
+    assert_eq!(ranking.entries[3].previous, Some(9000.0));
+    assert_eq!(ranking.entries[3].change_ratio(), Some(-1.0));
+
+    // Error ratio relative to that of the service
+    let ranking = Ranker::<ServiceDataInSecond>::new("status_5xx / requests")
+        .unwrap()
+        .top(1)
+        .pops(&seconds);
+    assert_eq!(ranking.entries.len(), 1);
+    let lhr = &ranking.entries[0];
+    assert_eq!(lhr.name, "LHR");
+    assert!((lhr.value - 0.2).abs() < 1e-9);
+    assert!((lhr.share - 0.2 / (1200.0 / 42000.0)).abs() < 1e-9);
+    assert!((lhr.change_ratio().unwrap() - 19.0).abs() < 1e-9);
+
+    // The vanished POP changed the most
+    let ranking = Ranker::<ServiceDataInSecond>::new("requests")
+        .unwrap()
+        .order(Order::Change)
+        .pops(&seconds);
+    assert_eq!(ranking.entries[0].name, "SYD");
+    assert_eq!(ranking.entries[0].change(), Some(-9000.0));
+
+    assert!(Ranker::<ServiceDataInSecond>::new("requests_total").is_err());
+}
+
+#[test]
+fn origins_and_their_pops() {
+    let seconds: Vec<OriginDataInSecond> = (1..=30)
+        .map(|recorded| {
+            let mut data = OriginDataInSecond {
+                recorded,
+                ..Default::default()
+            };
+            for (pop_name, origin_name, responses, status_503) in [
+                ("NRT", "s3", 100, 10),
+                ("LHR", "s3", 50, 0),
+                ("NRT", "gcs", 80, 1),
+                ("LHR", "gcs", 20, 0),
+            ] {
+                let stats = OriginStats {
+                    responses,
+                    status_503,
+                    ..Default::default()
+                };
+                data.datacenter
+                    .entry(pop_name.to_string())
+                    .or_default()
+                    .insert(origin_name.to_string(), stats.clone());
+                *data.aggregated.entry(origin_name.to_string()).or_default() += &stats;
+            }
+            data
+        })
+        .collect();
+    let ranker = Ranker::<OriginDataInSecond>::new("status_503")
+        .unwrap()
+        .window(10);
+
+    // Only 30 seconds, no previous window
+    let ranking = ranker.origins(&seconds);
+    assert_eq!(ranking.entries[0].name, "s3");
+    assert_eq!(ranking.entries[0].value, 100.0);
+    assert!((ranking.entries[0].share - 100.0 / 110.0).abs() < 1e-9);
+    assert_eq!(ranking.entries[1].previous, Some(10.0));
+
+    let ranking = ranker.origins_in(&seconds, "LHR");
+    assert_eq!(ranking.total, 0.0);
+    assert_eq!(ranking.entries.len(), 2);
+
+    let ranking = ranker.pops_of(&seconds, "s3");
+    let pops: Vec<(&str, f64)> = ranking
+        .entries
+        .iter()
+        .map(|entry| (entry.name.as_str(), entry.value))
+        .collect();
+    assert_eq!(pops, vec![("NRT", 100.0), ("LHR", 0.0)]);
+
+    let ranking = ranker.window(30).origins(&seconds);
+    assert_eq!(ranking.entries[0].previous, None);
+    assert_eq!(ranking.entries[0].change(), None);
+}
+
+#[test]
+fn ranked_status_codes() {
+    let seconds = service_seconds();
+
+    let ranking = status_codes(&seconds, &Scope::aggregated(), 60, 5);
+    let codes: Vec<(&str, f64)> = ranking
+        .entries
+        .iter()
+        .map(|entry| (entry.name.as_str(), entry.value))
+        .collect();
+    assert_eq!(codes, vec![("200", 40800.0), ("503", 1200.0)]);
+    assert_eq!(ranking.total, 42000.0);
+    assert_eq!(ranking.entries[1].previous, Some(60.0));
+
+    let ranking = status_codes(&seconds, &Scope::pop("LHR"), 60, 1);
+    assert_eq!(ranking.entries.len(), 1);
+    assert_eq!(ranking.entries[0].name, "200");
+    assert!((ranking.entries[0].share - 0.8).abs() < 1e-9);
+
+    // SYD had responses in the previous window only
+    let ranking = status_codes(&seconds, &Scope::pop("SYD"), 60, 5);
+    assert_eq!(ranking.total, 0.0);
+    assert_eq!(ranking.entries[0].value, 0.0);
+    assert_eq!(ranking.entries[0].previous, Some(9000.0));
+}
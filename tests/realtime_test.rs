@@ -0,0 +1,27 @@
+use fastly_rt::origin::{OriginClient, OriginDataInSecond};
+use fastly_rt::realtime::{RealtimeClient, RtResponse};
+use fastly_rt::service::{ServiceClient, ServiceDataInSecond, ServiceResponse};
+
+fn data_type_of<C: RealtimeClient<Data = D>, D>(_client: &C) {}
+
+#[test]
+fn clients_implement_realtime_client() {
+    let service = ServiceClient::new("key", "sid").unwrap();
+    let origin = OriginClient::new("key", "sid").unwrap();
+
+    data_type_of::<_, ServiceDataInSecond>(&service);
+    data_type_of::<_, OriginDataInSecond>(&origin);
+}
+
+#[test]
+fn deserialize_response_envelope() {
+    let json = r#"{"AggregateDelay": 5, "Data": [{"recorded": 10}], "Timestamp": 11}"#;
+
+    let service: ServiceResponse = serde_json::from_str(json).unwrap();
+    assert_eq!(service.aggregate_delay, 5);
+    assert_eq!(service.data[0].recorded, 10);
+    assert_eq!(service.timestamp, 11);
+
+    let origin: RtResponse<OriginDataInSecond> = serde_json::from_str(json).unwrap();
+    assert_eq!(origin.data.len(), 1);
+}
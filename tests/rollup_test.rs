@@ -0,0 +1,56 @@
+use fastly_rt::origin::OriginDataInSecond;
+use fastly_rt::pop::Region;
+use fastly_rt::service::ServiceDataInSecond;
+use std::collections::HashMap;
+
+#[test]
+fn service_by_region() {
+    let data: ServiceDataInSecond = serde_json::from_str(
+        r#"{
+            "recorded": 1,
+            "datacenter": {
+                "NRT": {"requests": 1, "miss_histogram": {"10": 1}},
+                "HKG": {"requests": 2, "miss_histogram": {"10": 2}},
+                "LHR": {"requests": 4},
+                "XYZ": {"requests": 8}
+            }
+        }"#,
+    )
+    .unwrap();
+
+    let regions = data.by_region();
+    assert_eq!(regions.len(), 3);
+    assert_eq!(regions[&Region::Asia].requests, 3);
+    assert_eq!(regions[&Region::Asia].miss_histogram.total(), 3);
+    assert_eq!(regions[&Region::Europe].requests, 4);
+    assert_eq!(regions[&Region::Unknown].requests, 8);
+
+    let groups: HashMap<String, String> = [("NRT", "japan"), ("LHR", "uk")]
+        .iter()
+        .map(|(pop, group)| (pop.to_string(), group.to_string()))
+        .collect();
+    let grouped = data.by_group(&groups);
+    assert_eq!(grouped.len(), 2);
+    assert_eq!(grouped["japan"].requests, 1);
+    assert_eq!(grouped["uk"].requests, 4);
+}
+
+#[test]
+fn origin_by_region() {
+    let data: OriginDataInSecond = serde_json::from_str(
+        r#"{
+            "recorded": 1,
+            "datacenter": {
+                "SYD": {"origin_a": {"responses": 1}},
+                "AKL": {"origin_a": {"responses": 2}, "origin_b": {"responses": 4}}
+            }
+        }"#,
+    )
+    .unwrap();
+
+    let regions = data.by_region();
+    let anz = &regions[&Region::AustraliaNewZealand];
+    assert_eq!(regions.len(), 1);
+    assert_eq!(anz["origin_a"].responses, 3);
+    assert_eq!(anz["origin_b"].responses, 4);
+}
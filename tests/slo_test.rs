@@ -0,0 +1,159 @@
+use fastly_rt::alert::{AlertEvent, AlertState};
+use fastly_rt::service::{ServiceDataInSecond, ServiceStats};
+use fastly_rt::slo::{BurnWindow, Sli, Slo, SloTracker};
+use fastly_rt::timeseries::Scope;
+use std::sync::{Arc, Mutex};
+
+fn second(recorded: u64, requests: u64, status_5xx: u64) -> ServiceDataInSecond {
+    let mut data = ServiceDataInSecond {
+        recorded,
+        ..Default::default()
+    };
+    data.aggregated.requests = requests;
+    data.aggregated.status_5xx = status_5xx;
+    data
+}
+
+#[test]
+fn indicators() {
+    let stats = ServiceStats {
+        requests: 1000,
+        status_5xx: 10,
+        status_503: 4,
+        ..Default::default()
+    };
+    assert_eq!(Sli::availability().measure(&stats), (990.0, 1000.0));
+    assert_eq!(
+        Sli::ratio("requests - status_503", "requests")
+            .unwrap()
+            .measure(&stats),
+        (996.0, 1000.0)
+    );
+    assert!(Sli::ratio("requests - status_999", "requests").is_err());
+    assert!(Sli::ratio("requests -", "requests").is_err());
+
+    // Good events are within 0 and total
+    assert_eq!(
+        Sli::ratio("requests * 2", "requests")
+            .unwrap()
+            .measure(&stats),
+        (1000.0, 1000.0)
+    );
+    assert_eq!(
+        Sli::availability().measure(&ServiceStats::default()),
+        (0.0, 0.0)
+    );
+
+    let mut stats = ServiceStats::default();
+    stats.miss_histogram.add(100, 6);
+    stats.miss_histogram.add(500, 3);
+    stats.miss_histogram.add(2000, 1);
+    assert_eq!(Sli::latency(500).measure(&stats), (9.0, 10.0));
+    assert_eq!(Sli::latency(50).measure(&stats), (0.0, 10.0));
+}
+
+#[test]
+fn error_budget_over_the_period() {
+    let slo = Slo::new("availability", Sli::availability(), 0.99, 600);
+    let mut tracker = SloTracker::new(vec![slo]);
+    assert!(tracker.status("www").is_empty());
+
+    // 0.5% of errors spend half of the budget
+    for recorded in 60..660 {
+        let status_5xx = if recorded % 2 == 0 { 1 } else { 0 };
+        tracker.observe("www", &second(recorded, 100, status_5xx));
+    }
+    let status = &tracker.status("www")[0];
+    assert_eq!(status.slo, "availability");
+    assert_eq!((status.good, status.total), (59700.0, 60000.0));
+    assert!((status.compliance - 0.995).abs() < 1e-9);
+    assert!((status.budget_remaining - 0.5).abs() < 1e-9);
+
+    // The period rolls over minute by minute
+    for recorded in 660..1260 {
+        tracker.observe("www", &second(recorded, 100, 0));
+    }
+    let status = &tracker.status("www")[0];
+    assert_eq!((status.good, status.total), (60000.0, 60000.0));
+    assert_eq!(status.budget_remaining, 1.0);
+
+    // Overspent
+    for recorded in 1260..1320 {
+        tracker.observe("www", &second(recorded, 100, 50));
+    }
+    assert!(tracker.status("www")[0].budget_remaining < 0.0);
+}
+
+#[test]
+fn multi_window_burn_alerts() {
+    let slo = Slo::new("availability", Sli::availability(), 0.99, 86400).with_windows(vec![
+        BurnWindow::new(600, 60, 10.0),
+        BurnWindow::new(1800, 300, 4.0),
+    ]);
+    let mut tracker = SloTracker::new(vec![slo]);
+
+    // Clean, 30% of errors for 5 minutes, then clean again
+    let mut events = Vec::new();
+    for recorded in 1..=2400 {
+        let status_5xx = if (1801..=2100).contains(&recorded) {
+            30
+        } else {
+            0
+        };
+        events.extend(tracker.observe("www", &second(recorded, 100, status_5xx)));
+    }
+
+    let summary: Vec<(&str, AlertState, u64)> = events
+        .iter()
+        .map(|event| (event.rule.as_str(), event.state, event.recorded))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("availability burn 10m/1m", AlertState::Firing, 2001),
+            ("availability burn 30m/5m", AlertState::Firing, 2041),
+            ("availability burn 10m/1m", AlertState::Resolved, 2140),
+            ("availability burn 30m/5m", AlertState::Resolved, 2360)
+        ]
+    );
+    assert_eq!(events[0].service, "www");
+    assert_eq!(events[0].scope, Scope::aggregated());
+    assert!(events[0].value > 10.0);
+
+    let status = &tracker.status("www")[0];
+    assert_eq!(status.burn_rates.len(), 2);
+    assert_eq!(status.burn_rates[0].0.name(), "10m/1m");
+    assert_eq!(status.burn_rates[0].2, 0.0);
+    assert!(status.burn_rates[1].1 > 4.0);
+}
+
+#[tokio::test]
+async fn notifications_and_services() {
+    let slo = Slo::new("availability", Sli::availability(), 0.9, 3600)
+        .with_windows(vec![BurnWindow::new(10, 2, 2.0)])
+        .service("www*");
+    let notified: Arc<Mutex<Vec<AlertEvent>>> = Arc::new(Mutex::new(Vec::new()));
+    let shared = notified.clone();
+    let mut tracker = SloTracker::new(vec![slo]).on_event(move |event| {
+        shared.lock().unwrap().push(event.clone());
+    });
+    let mut receiver = tracker.subscribe();
+
+    let response = fastly_rt::realtime::RtResponse {
+        aggregate_delay: 0,
+        data: (1..=3).map(|recorded| second(recorded, 10, 5)).collect(),
+        timestamp: 3,
+    };
+    assert_eq!(tracker.observe_response("www-2", &response).len(), 1);
+    assert!(tracker.observe_response("api", &response).is_empty());
+    assert!(tracker.status("api").is_empty());
+
+    // Seconds already accounted are ignored
+    assert!(tracker.observe("www-2", &second(2, 10, 0)).is_empty());
+
+    assert_eq!(notified.lock().unwrap().len(), 1);
+    let event = receiver.recv().await.unwrap();
+    assert_eq!(event.state, AlertState::Firing);
+    assert_eq!(event.recorded, 1);
+    assert!((event.value - 5.0).abs() < 1e-9);
+}
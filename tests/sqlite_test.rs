@@ -0,0 +1,265 @@
+mod common;
+
+use fastly_rt::origin::OriginDataInSecond;
+use fastly_rt::service::ServiceDataInSecond;
+use fastly_rt::sqlite::{Resolution, Scope, Store};
+
+// 2023-11-14T22:13:20Z
+const START: u64 = 1700000000;
+
+fn service_second(recorded: u64, requests: u64) -> ServiceDataInSecond {
+    serde_json::from_str(&format!(
+        r#"{{
+            "recorded": {recorded},
+            "aggregated": {{"requests": {requests}, "hits_time": 0.25, "miss_histogram": {{"10": 1, "30": 2}}}},
+            "datacenter": {{"NRT": {{"requests": {requests}, "hits_time": 0.25}}}}
+        }}"#
+    ))
+    .unwrap()
+}
+
+fn origin_second(recorded: u64) -> OriginDataInSecond {
+    serde_json::from_str(&format!(
+        r#"{{
+            "recorded": {recorded},
+            "aggregated": {{"s3": {{"responses": 3, "status_5xx": 1}}, "gcs": {{"responses": 1}}}},
+            "datacenter": {{"NRT": {{"s3": {{"responses": 3, "status_5xx": 1}}}}, "LHR": {{"gcs": {{"responses": 1}}}}}}
+        }}"#
+    ))
+    .unwrap()
+}
+
+#[test]
+fn service_seconds() {
+    let mut store = Store::in_memory().unwrap();
+    store
+        .upsert_service(
+            "www",
+            &[service_second(START, 3), service_second(START + 1, 4)],
+        )
+        .unwrap();
+
+    let series = store
+        .service(
+            "www",
+            &Scope::Aggregated,
+            Resolution::Second,
+            START,
+            START + 10,
+        )
+        .unwrap();
+    assert_eq!(series.len(), 2);
+    assert_eq!(series[0].0, START);
+    assert_eq!(series[1].1.requests, 4);
+    assert_eq!(series[1].1.hits_time, 0.25);
+    assert_eq!(series[1].1.miss_histogram.total(), 3);
+
+    let nrt = store
+        .service(
+            "www",
+            &Scope::Pop("NRT".to_string()),
+            Resolution::Second,
+            START,
+            START + 1,
+        )
+        .unwrap();
+    assert_eq!(nrt.len(), 1);
+    assert_eq!(nrt[0].1.requests, 3);
+    assert!(nrt[0].1.miss_histogram.is_empty());
+
+    assert!(store
+        .service(
+            "api",
+            &Scope::Aggregated,
+            Resolution::Second,
+            START,
+            START + 10
+        )
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn upsert_replaces_seconds() {
+    let mut store = Store::in_memory().unwrap();
+    store
+        .upsert_service("www", &[service_second(START, 3)])
+        .unwrap();
+    store
+        .upsert_service(
+            "www",
+            &[service_second(START, 5), service_second(START + 1, 1)],
+        )
+        .unwrap();
+
+    let total = store
+        .service_total("www", &Scope::Aggregated, START, START + 2)
+        .unwrap();
+    assert_eq!(total.requests, 6);
+    assert_eq!(total.miss_histogram.total(), 6);
+}
+
+#[test]
+fn minute_and_hour_rollups() {
+    let mut store = Store::in_memory().unwrap();
+    // Seconds of 22:13, 22:14 and 23:00
+    let seconds: Vec<ServiceDataInSecond> = [START, START + 39, START + 40, START + 2800]
+        .into_iter()
+        .map(|recorded| service_second(recorded, 2))
+        .collect();
+    store.upsert_service("www", &seconds[..2]).unwrap();
+    store.upsert_service("www", &seconds[2..]).unwrap();
+    store.upsert_service("www", &seconds[1..2]).unwrap();
+
+    let minutes = store
+        .service(
+            "www",
+            &Scope::Aggregated,
+            Resolution::Minute,
+            0,
+            u64::MAX / 2,
+        )
+        .unwrap();
+    let minutes: Vec<(u64, u64)> = minutes
+        .iter()
+        .map(|(recorded, stats)| (*recorded, stats.requests))
+        .collect();
+    assert_eq!(
+        minutes,
+        vec![(START - 20, 4), (START + 40, 2), (START + 2800, 2)]
+    );
+
+    let hours = store
+        .service(
+            "www",
+            &Scope::Pop("NRT".to_string()),
+            Resolution::Hour,
+            0,
+            u64::MAX / 2,
+        )
+        .unwrap();
+    assert_eq!(hours.len(), 2);
+    assert_eq!(hours[0].0, START - 800);
+    assert_eq!(hours[0].1.requests, 6);
+    assert_eq!(hours[0].1.hits_time, 0.75);
+
+    let hours = store
+        .service("www", &Scope::Aggregated, Resolution::Hour, 0, u64::MAX / 2)
+        .unwrap();
+    assert_eq!(hours[0].1.miss_histogram.total(), 9);
+}
+
+#[test]
+fn gauges_rolled_up_to_their_largest_value() {
+    let mut store = Store::in_memory().unwrap();
+    let seconds: Vec<ServiceDataInSecond> = [(START, 100), (START + 1, 300), (START + 2, 200)]
+        .into_iter()
+        .map(|(recorded, ram)| {
+            let mut data = service_second(recorded, 2);
+            data.aggregated.compute_ram_used = ram;
+            data
+        })
+        .collect();
+    store.upsert_service("www", &seconds).unwrap();
+
+    for resolution in [Resolution::Minute, Resolution::Hour] {
+        let rollups = store
+            .service("www", &Scope::Aggregated, resolution, 0, u64::MAX / 2)
+            .unwrap();
+        assert_eq!(rollups.len(), 1);
+        assert_eq!(rollups[0].1.compute_ram_used, 300);
+        assert_eq!(rollups[0].1.requests, 6);
+    }
+    let total = store
+        .service_total("www", &Scope::Aggregated, START, START + 3)
+        .unwrap();
+    assert_eq!(total.compute_ram_used, 300);
+}
+
+#[test]
+fn origins() {
+    let mut store = Store::in_memory().unwrap();
+    store
+        .upsert_origins("www", &[origin_second(START), origin_second(START + 1)])
+        .unwrap();
+
+    assert_eq!(
+        store.origins("www", START, START + 2).unwrap(),
+        vec!["gcs".to_string(), "s3".to_string()]
+    );
+
+    let s3 = store
+        .origin(
+            "www",
+            "s3",
+            &Scope::Aggregated,
+            Resolution::Minute,
+            0,
+            u64::MAX / 2,
+        )
+        .unwrap();
+    assert_eq!(s3.len(), 1);
+    assert_eq!(s3[0].1.responses, 6);
+    assert_eq!(s3[0].1.status_5xx, 2);
+
+    let lhr = store
+        .origin(
+            "www",
+            "s3",
+            &Scope::Pop("LHR".to_string()),
+            Resolution::Second,
+            0,
+            u64::MAX / 2,
+        )
+        .unwrap();
+    assert!(lhr.is_empty());
+}
+
+#[test]
+fn persisted_and_pruned() {
+    let dir = common::temp_dir("sqlite");
+    let path = dir.join("history.sqlite");
+
+    let mut store = Store::open(&path).unwrap();
+    store
+        .upsert_service(
+            "www",
+            &[service_second(START, 3), service_second(START + 60, 3)],
+        )
+        .unwrap();
+    drop(store);
+
+    let mut store = Store::open(&path).unwrap();
+    assert_eq!(
+        store
+            .service_total("www", &Scope::Aggregated, START, START + 61)
+            .unwrap()
+            .requests,
+        6
+    );
+
+    store.prune(Resolution::Second, START + 60).unwrap();
+    let seconds = store
+        .service(
+            "www",
+            &Scope::Aggregated,
+            Resolution::Second,
+            0,
+            u64::MAX / 2,
+        )
+        .unwrap();
+    assert_eq!(seconds.len(), 1);
+    let minutes = store
+        .service(
+            "www",
+            &Scope::Aggregated,
+            Resolution::Minute,
+            0,
+            u64::MAX / 2,
+        )
+        .unwrap();
+    assert_eq!(minutes.len(), 2);
+
+    drop(store);
+    std::fs::remove_dir_all(dir).unwrap();
+}
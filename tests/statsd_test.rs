@@ -0,0 +1,115 @@
+use fastly_rt::metric::MetricFilter;
+use fastly_rt::origin::OriginDataInSecond;
+use fastly_rt::service::ServiceDataInSecond;
+use fastly_rt::statsd::{batch, origin_lines, service_lines, Flavor, StatsdConfig, StatsdSink};
+use std::net::UdpSocket;
+use std::time::Duration;
+
+fn service_second() -> ServiceDataInSecond {
+    serde_json::from_str(
+        r#"{
+            "recorded": 1,
+            "aggregated": {"requests": 3, "hits_time": 0.5, "compute_ram_used": 2048, "miss_histogram": {"10": 1, "30": 2, "3000": 1}},
+            "datacenter": {"NRT": {"requests": 1}, "LHR": {"requests": 2}}
+        }"#,
+    )
+    .unwrap()
+}
+
+fn filter(allow: &[&str], pop_allow: &[&str]) -> MetricFilter {
+    MetricFilter {
+        allow: allow.iter().map(|name| name.to_string()).collect(),
+        pop_allow: pop_allow.iter().map(|name| name.to_string()).collect(),
+        ..MetricFilter::default()
+    }
+}
+
+#[test]
+fn dogstatsd_lines() {
+    let config = StatsdConfig {
+        tags: vec![("env".to_string(), "prod".to_string())],
+        ..StatsdConfig::default()
+    };
+    let lines = service_lines(&config, "www", &service_second());
+
+    assert!(lines.contains(&"fastly_rt.requests:3|c|#env:prod,service:www".to_string()));
+    assert!(lines.contains(&"fastly_rt.hits_time:0.5|c|#env:prod,service:www".to_string()));
+    assert!(lines.contains(&"fastly_rt.miss_latency:9|ms|#env:prod,service:www".to_string()));
+    assert!(lines.contains(&"fastly_rt.miss_latency:25|ms|@0.5|#env:prod,service:www".to_string()));
+    assert!(lines.contains(&"fastly_rt.miss_latency:2950|ms|#env:prod,service:www".to_string()));
+    assert!(lines.contains(&"fastly_rt.compute_ram_used:2048|g|#env:prod,service:www".to_string()));
+    assert!(!lines.iter().any(|line| line.starts_with("fastly_rt.hits:")));
+}
+
+#[test]
+fn statsd_lines_by_pop() {
+    let config = StatsdConfig {
+        flavor: Flavor::Statsd,
+        prefix: "cdn".to_string(),
+        filter: filter(&["requests"], &["requests"]),
+        ..StatsdConfig::default()
+    };
+    let lines = service_lines(&config, "w.w", &service_second());
+    assert_eq!(
+        lines,
+        vec!["cdn.w_w.LHR.requests:2|c", "cdn.w_w.NRT.requests:1|c"]
+    );
+
+    let origins: OriginDataInSecond = serde_json::from_str(
+        r#"{"recorded": 1, "aggregated": {"b": {"responses": 2}, "a": {"responses": 1, "status_5xx": 1}}}"#,
+    )
+    .unwrap();
+    let config = StatsdConfig {
+        filter: filter(&["origin_responses"], &[]),
+        ..StatsdConfig::default()
+    };
+    let lines = origin_lines(&config, "www", &origins);
+    assert_eq!(
+        lines,
+        vec![
+            "fastly_rt.origin.responses:1|c|#service:www,origin:a",
+            "fastly_rt.origin.responses:2|c|#service:www,origin:b",
+        ]
+    );
+}
+
+#[test]
+fn batches_up_to_packet_size() {
+    let lines: Vec<String> = (0..10).map(|i| format!("metric_{}:1|c", i)).collect();
+
+    let packets = batch(&lines, 30);
+    assert!(packets.iter().all(|packet| packet.len() <= 30));
+    assert_eq!(packets.join("\n"), lines.join("\n"));
+    assert_eq!(packets.len(), 5);
+
+    let packets = batch(&["a_long_line:1|c".to_string(), "b:1|c".to_string()], 5);
+    assert_eq!(packets, vec!["a_long_line:1|c", "b:1|c"]);
+}
+
+#[test]
+fn sends_over_udp() {
+    let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    server
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+
+    let config = StatsdConfig {
+        max_packet_size: 64,
+        ..StatsdConfig::default()
+    };
+    let sink = StatsdSink::connect(server.local_addr().unwrap(), config).unwrap();
+    let packets = sink.send_service("www", &service_second()).unwrap();
+    assert!(packets > 1);
+
+    let mut received = Vec::new();
+    let mut buffer = [0u8; 2048];
+    for _ in 0..packets {
+        let size = server.recv(&mut buffer).unwrap();
+        assert!(size <= 64);
+        received.push(String::from_utf8(buffer[..size].to_vec()).unwrap());
+    }
+    assert_eq!(
+        received.join("\n"),
+        service_lines(sink.config(), "www", &service_second()).join("\n")
+    );
+}
@@ -0,0 +1,182 @@
+use fastly_rt::histogram::LatencyHistogram;
+use fastly_rt::service::ServiceStats;
+use fastly_rt::synth::{Generator, Incident, IncidentKind, StatusMix, SynthConfig};
+
+const START: u64 = 1_700_000_000;
+
+fn assert_consistent(stats: &ServiceStats) {
+    assert_eq!(
+        stats.requests,
+        stats.hits + stats.miss + stats.pass + stats.synth
+    );
+    assert_eq!(stats.origin_fetches, stats.miss + stats.pass);
+    assert_eq!(
+        stats.requests,
+        stats.status_1xx
+            + stats.status_2xx
+            + stats.status_3xx
+            + stats.status_4xx
+            + stats.status_5xx
+    );
+    assert!(stats.status_2xx >= stats.status_200 + stats.status_204 + stats.status_206);
+    assert!(stats.status_3xx >= stats.status_301 + stats.status_302 + stats.status_304);
+    assert!(
+        stats.status_4xx
+            >= stats.status_400
+                + stats.status_401
+                + stats.status_403
+                + stats.status_404
+                + stats.status_416
+                + stats.status_429
+    );
+    assert!(
+        stats.status_5xx
+            >= stats.status_500 + stats.status_502 + stats.status_503 + stats.status_504
+    );
+    assert_eq!(stats.miss_histogram.total(), stats.miss);
+}
+
+#[test]
+fn generated_data_is_consistent_and_seeded() {
+    let generator = Generator::new(SynthConfig::default().with_origin_count(3));
+
+    for data in generator.service_seconds(START..START + 30) {
+        assert_consistent(&data.aggregated);
+        let mut sum = ServiceStats::default();
+        for stats in data.datacenter.values() {
+            assert_consistent(stats);
+            sum.merge(stats);
+        }
+        assert_eq!(sum.requests, data.aggregated.requests);
+        assert_eq!(sum.status_503, data.aggregated.status_503);
+    }
+
+    let service = generator.service_second(START);
+    let origin = generator.origin_second(START);
+    assert_eq!(origin.aggregated.len(), 3);
+    for (pop, stats) in &service.datacenter {
+        let responses: u64 = origin.datacenter[pop].values().map(|o| o.responses).sum();
+        assert_eq!(responses, stats.origin_fetches);
+    }
+
+    let again = Generator::new(SynthConfig::default().with_origin_count(3));
+    assert_eq!(
+        serde_json::to_value(again.service_second(START)).unwrap(),
+        serde_json::to_value(&service).unwrap()
+    );
+    let other_seed = Generator::new(SynthConfig {
+        seed: 1,
+        ..SynthConfig::default()
+    });
+    assert_ne!(
+        other_seed.service_second(START).aggregated.requests,
+        service.aggregated.requests
+    );
+}
+
+#[test]
+fn error_heavy_mix_is_consistent() {
+    let generator = Generator::new(SynthConfig {
+        requests_per_second: 20.0,
+        status_mix: StatusMix {
+            status_2xx: 0.5,
+            status_3xx: 0.0,
+            status_4xx: 0.25,
+            status_5xx: 0.25,
+        },
+        ..SynthConfig::default()
+    });
+
+    for data in generator.service_seconds(START..START + 360) {
+        assert_consistent(&data.aggregated);
+        for stats in data.datacenter.values() {
+            assert_consistent(stats);
+        }
+    }
+}
+
+#[test]
+fn miss_latency_in_real_buckets() {
+    let generator = Generator::new(SynthConfig {
+        miss_latency_ms: 900.0,
+        ..SynthConfig::default()
+    });
+
+    for data in generator.service_seconds(START..START + 60) {
+        for (upper_ms, _) in data.aggregated.miss_histogram.buckets() {
+            assert_eq!(
+                LatencyHistogram::upper_bound(upper_ms as f64),
+                upper_ms,
+                "{}",
+                upper_ms
+            );
+        }
+    }
+}
+
+#[test]
+fn diurnal_curve() {
+    let generator = Generator::new(SynthConfig {
+        pops: vec!["LHR".to_string()],
+        jitter: 0.0,
+        diurnal_amplitude: 0.5,
+        peak_hour: 12.0,
+        ..SynthConfig::default()
+    });
+    let midnight = START - START % 86400;
+
+    let night = generator.service_second(midnight).aggregated.requests;
+    let noon = generator
+        .service_second(midnight + 12 * 3600)
+        .aggregated
+        .requests;
+    assert!(noon > 2 * night);
+}
+
+#[test]
+fn scripted_incidents() {
+    let incident = |kind| Incident {
+        start: START + 10,
+        duration: 10,
+        kind,
+    };
+    let config = SynthConfig {
+        incidents: vec![
+            incident(IncidentKind::PopOutage {
+                pop: "NRT".to_string(),
+            }),
+            incident(IncidentKind::Origin503Storm {
+                origin: "origin_1".to_string(),
+                ratio: 0.5,
+            }),
+            incident(IncidentKind::AttackSpike {
+                pops: vec!["LHR".to_string()],
+                multiplier: 10.0,
+            }),
+        ],
+        ..SynthConfig::default()
+    };
+    let generator = Generator::new(config);
+
+    let before = generator.service_second(START);
+    let during = generator.service_second(START + 15);
+    let after = generator.service_second(START + 20);
+
+    assert!(before.datacenter.contains_key("NRT"));
+    assert!(!during.datacenter.contains_key("NRT"));
+    assert!(after.datacenter.contains_key("NRT"));
+    assert!(during.datacenter["HKG"].requests > before.datacenter["HKG"].requests * 3 / 2);
+
+    assert_eq!(before.datacenter["LHR"].waf_blocked, 0);
+    assert!(during.datacenter["LHR"].waf_blocked > 5 * before.datacenter["LHR"].requests);
+    assert_eq!(during.datacenter["IAD"].waf_blocked, 0);
+
+    let origin = generator.origin_second(START + 15);
+    let storm = &origin.aggregated["origin_1"];
+    assert!(storm.status_503 * 2 >= storm.responses);
+    assert!(during.aggregated.status_503 >= storm.status_503);
+
+    for stats in during.datacenter.values() {
+        assert_consistent(stats);
+    }
+}
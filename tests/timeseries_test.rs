@@ -0,0 +1,177 @@
+use fastly_rt::origin::OriginDataInSecond;
+use fastly_rt::service::ServiceDataInSecond;
+use fastly_rt::timeseries::{Scope, ScopeFilter, TimeSeriesStore};
+
+fn service_second(recorded: u64, nrt: u64, lhr: Option<u64>) -> ServiceDataInSecond {
+    let mut data = ServiceDataInSecond {
+        recorded,
+        ..Default::default()
+    };
+    data.datacenter
+        .entry("NRT".to_string())
+        .or_default()
+        .requests = nrt;
+    if let Some(lhr) = lhr {
+        data.datacenter
+            .entry("LHR".to_string())
+            .or_default()
+            .requests = lhr;
+    }
+    data.aggregated.requests = nrt + lhr.unwrap_or(0);
+    data
+}
+
+#[test]
+fn windows_by_scope() {
+    let mut store = TimeSeriesStore::new(600);
+    let seconds: Vec<ServiceDataInSecond> = (1000..1300)
+        .map(|recorded| service_second(recorded, 1, (recorded % 2 == 0).then_some(4)))
+        .collect();
+    store.extend("www", &seconds);
+
+    assert_eq!(store.latest("www"), Some(1299));
+    assert_eq!(
+        store.scopes("www"),
+        vec![&Scope::aggregated(), &Scope::pop("LHR"), &Scope::pop("NRT")]
+    );
+
+    let aggregated = Scope::aggregated();
+    assert_eq!(
+        store.metric_sum("www", &aggregated, "requests", 60),
+        Some(180.0)
+    );
+    assert_eq!(store.rate("www", &aggregated, "requests", 300), Some(3.0));
+    assert_eq!(store.sum("www", &aggregated, 10).unwrap().requests, 30);
+
+    // LHR counts as zero in the seconds it is missing from
+    let lhr = store
+        .values("www", &Scope::pop("LHR"), "requests", 4)
+        .unwrap();
+    assert_eq!(
+        lhr,
+        vec![(1296, 4.0), (1297, 0.0), (1298, 4.0), (1299, 0.0)]
+    );
+    assert!(store.get("www", &Scope::pop("LHR"), 1297).is_none());
+    assert_eq!(
+        store.get("www", &Scope::pop("NRT"), 1297).unwrap().requests,
+        1
+    );
+}
+
+#[test]
+fn unknown_service_scope_or_metric() {
+    let mut store = TimeSeriesStore::new(60);
+    store.insert("www", &service_second(1000, 1, None));
+
+    assert!(store.sum("api", &Scope::aggregated(), 60).is_none());
+    assert!(store
+        .values("www", &Scope::aggregated(), "nope", 60)
+        .is_none());
+    assert_eq!(
+        store.sum("www", &Scope::pop("LHR"), 60).unwrap().requests,
+        0
+    );
+    assert_eq!(
+        store.rate("www", &Scope::pop("LHR"), "requests", 60),
+        Some(0.0)
+    );
+    assert!(store.received("api", 60).is_empty());
+}
+
+#[test]
+fn eviction() {
+    let mut store = TimeSeriesStore::new(10);
+    store.insert("www", &service_second(1000, 1, Some(1)));
+    for recorded in 1001..1020 {
+        store.insert("www", &service_second(recorded, 1, None));
+    }
+
+    // Only the last 10 seconds are kept, and LHR has no data in them anymore
+    assert_eq!(
+        store.received("www", 60),
+        (1010..1020).collect::<Vec<u64>>()
+    );
+    assert_eq!(
+        store.metric_sum("www", &Scope::aggregated(), "requests", 60),
+        Some(10.0)
+    );
+    assert!(!store.scopes("www").contains(&&Scope::pop("LHR")));
+
+    // Late seconds are kept within the capacity only
+    store.insert("www", &service_second(1005, 7, None));
+    assert!(store.get("www", &Scope::aggregated(), 1005).is_none());
+    let mut late = service_second(1009, 7, None);
+    late.recorded = 1015;
+    store.insert("www", &late);
+    assert_eq!(
+        store
+            .get("www", &Scope::aggregated(), 1015)
+            .unwrap()
+            .requests,
+        7
+    );
+}
+
+#[test]
+fn services_apart() {
+    let mut store = TimeSeriesStore::new(60);
+    store.insert("www", &service_second(1000, 1, None));
+    store.insert("api", &service_second(2000, 5, None));
+
+    let mut services: Vec<&str> = store.services().collect();
+    services.sort();
+    assert_eq!(services, vec!["api", "www"]);
+    assert_eq!(
+        store.metric_sum("www", &Scope::aggregated(), "requests", 60),
+        Some(1.0)
+    );
+    assert_eq!(
+        store.metric_sum("api", &Scope::aggregated(), "requests", 60),
+        Some(5.0)
+    );
+}
+
+#[test]
+fn origin_scopes() {
+    let data: OriginDataInSecond = serde_json::from_str(
+        r#"{
+            "recorded": 1000,
+            "aggregated": {"s3": {"responses": 3}},
+            "datacenter": {"NRT": {"s3": {"responses": 1}}, "LHR": {"s3": {"responses": 2}}}
+        }"#,
+    )
+    .unwrap();
+    let mut store = TimeSeriesStore::new(60);
+    store.insert("www", &data);
+
+    assert_eq!(
+        store.scopes("www"),
+        vec![
+            &Scope::origin("s3"),
+            &Scope::origin("s3").in_pop("LHR"),
+            &Scope::origin("s3").in_pop("NRT")
+        ]
+    );
+    let s3_lhr = Scope::origin("s3").in_pop("LHR");
+    assert_eq!(store.metric_sum("www", &s3_lhr, "responses", 60), Some(2.0));
+    assert_eq!(s3_lhr.to_string(), "origin s3 in pop LHR");
+}
+
+#[test]
+fn scope_filter() {
+    let filter = ScopeFilter {
+        services: vec!["www*".to_string()],
+        origins: vec!["s3".to_string()],
+        by_pop: true,
+    };
+
+    assert!(filter.applies("www-2", &Scope::pop("NRT")));
+    assert!(filter.applies("www", &Scope::origin("s3").in_pop("NRT")));
+    assert!(!filter.applies("www", &Scope::origin("gcs").in_pop("NRT")));
+    assert!(!filter.applies("www", &Scope::aggregated()));
+    assert!(!filter.applies("api", &Scope::pop("NRT")));
+
+    let all = ScopeFilter::default();
+    assert!(all.applies("api", &Scope::origin("gcs")));
+    assert!(!all.applies("api", &Scope::pop("NRT")));
+}